@@ -0,0 +1,24 @@
+-- Persistent chat rooms; every chat now belongs to exactly one room.
+CREATE TABLE IF NOT EXISTS rooms (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    "ownerId" BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    archived_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Seed the room that replaces the old hard-coded "general_chat" channel
+INSERT INTO rooms (name)
+SELECT 'general'
+WHERE NOT EXISTS (SELECT 1 FROM rooms);
+
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS "roomId" BIGINT REFERENCES rooms(id);
+
+UPDATE chats
+SET "roomId" = (SELECT MIN(id) FROM rooms)
+WHERE "roomId" IS NULL;
+
+ALTER TABLE chats ALTER COLUMN "roomId" SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS chats_room_id_created_at_idx ON chats ("roomId", created_at);
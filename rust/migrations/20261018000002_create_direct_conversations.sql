@@ -0,0 +1,25 @@
+-- One conversation per unordered pair of users: "userAId" is always the lower ID
+CREATE TABLE IF NOT EXISTS direct_conversations (
+    id BIGSERIAL PRIMARY KEY,
+    "userAId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    "userBId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT direct_conversations_pair_key UNIQUE ("userAId", "userBId"),
+    CONSTRAINT direct_conversations_pair_order CHECK ("userAId" < "userBId")
+);
+
+CREATE INDEX IF NOT EXISTS direct_conversations_user_b_idx ON direct_conversations ("userBId");
+
+CREATE TABLE IF NOT EXISTS direct_messages (
+    id BIGSERIAL PRIMARY KEY,
+    "conversationId" BIGINT NOT NULL REFERENCES direct_conversations(id) ON DELETE CASCADE,
+    "userId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message TEXT NOT NULL,
+    attachment VARCHAR,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS direct_messages_conversation_created_at_idx
+    ON direct_messages ("conversationId", created_at);
@@ -0,0 +1,12 @@
+-- Previous versions of edited messages, newest last
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS edited BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS chat_revisions (
+    id BIGSERIAL PRIMARY KEY,
+    "chatId" BIGINT NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
+    "editorId" BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS chat_revisions_chat_id_idx ON chat_revisions ("chatId", created_at);
@@ -0,0 +1,28 @@
+-- Roles: 'moderator' and 'admin' may delete any message. Assign them directly in SQL.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role VARCHAR(16) NOT NULL DEFAULT 'user';
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'users_role_check') THEN
+        ALTER TABLE users ADD CONSTRAINT users_role_check CHECK (role IN ('user', 'moderator', 'admin'));
+    END IF;
+END $$;
+
+-- Deleted messages stay as tombstones so replies keep pointing at a real row
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS "deletedBy" BIGINT REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS chats_deleted_at_idx ON chats (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Generic audit trail; targets are plain IDs so records outlive purged rows
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id BIGSERIAL PRIMARY KEY,
+    "actorId" BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(64) NOT NULL,
+    "targetType" VARCHAR(32) NOT NULL,
+    "targetId" BIGINT,
+    metadata JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_logs_target_idx ON audit_logs ("targetType", "targetId");
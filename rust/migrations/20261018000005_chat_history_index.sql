@@ -0,0 +1,2 @@
+-- Keyset pagination walks messages by (created_at, id) inside a room
+CREATE INDEX IF NOT EXISTS chats_room_created_id_idx ON chats ("roomId", created_at DESC, id DESC);
@@ -0,0 +1,6 @@
+-- Full-text search over message bodies. The 'simple' configuration avoids
+-- language-specific stemming since rooms mix languages.
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('simple', COALESCE(message, ''))) STORED;
+
+CREATE INDEX IF NOT EXISTS chats_search_vector_idx ON chats USING GIN (search_vector);
@@ -0,0 +1,14 @@
+-- Files uploaded through /v1/attachments; the bytes live in the storage backend
+CREATE TABLE IF NOT EXISTS attachments (
+    id UUID PRIMARY KEY,
+    "ownerId" BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    storage_key TEXT NOT NULL,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(127) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS attachments_owner_idx ON attachments ("ownerId");
+
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS "attachmentId" UUID REFERENCES attachments(id) ON DELETE SET NULL;
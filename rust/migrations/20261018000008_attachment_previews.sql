@@ -0,0 +1,7 @@
+-- Image metadata extracted on upload so chat bubbles can render before the full file loads
+ALTER TABLE attachments
+    ADD COLUMN IF NOT EXISTS width INTEGER,
+    ADD COLUMN IF NOT EXISTS height INTEGER,
+    ADD COLUMN IF NOT EXISTS blurhash VARCHAR(64),
+    ADD COLUMN IF NOT EXISTS thumbnail_key TEXT,
+    ADD COLUMN IF NOT EXISTS thumbnail_content_type VARCHAR(127);
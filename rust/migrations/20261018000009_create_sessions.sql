@@ -0,0 +1,30 @@
+-- One row per signed-in device. Every refresh token issued for a device belongs to
+-- its session, so the session is also the rotation family.
+CREATE TABLE IF NOT EXISTS sessions (
+    id UUID PRIMARY KEY,
+    "userId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_agent TEXT,
+    ip VARCHAR(64),
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    revoked_reason VARCHAR(32),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sessions_user_idx ON sessions ("userId") WHERE revoked_at IS NULL;
+
+-- Only SHA-256 digests are stored. A token whose used_at is set has been rotated;
+-- presenting it again means it leaked.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    "sessionId" UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_session_idx ON refresh_tokens ("sessionId");
+
+-- users.refresh_token is superseded by sessions; old tokens stop working
+UPDATE users SET refresh_token = NULL WHERE refresh_token IS NOT NULL;
@@ -0,0 +1,15 @@
+-- Access tokens revoked before their exp. Rows are useless once the token expires
+-- and are purged after that.
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti VARCHAR(64) PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS revoked_tokens_created_idx ON revoked_tokens (created_at);
+
+-- The access token issued together with each refresh token, so revoking a
+-- session can deny its still-valid access tokens too
+ALTER TABLE refresh_tokens
+    ADD COLUMN IF NOT EXISTS access_jti VARCHAR(64),
+    ADD COLUMN IF NOT EXISTS access_expires_at TIMESTAMPTZ;
@@ -0,0 +1,21 @@
+-- TOTP second factor. The row exists from enrollment on; enabled_at is set once
+-- the user has proven their app works. last_used_step stops a code from being
+-- accepted twice.
+CREATE TABLE IF NOT EXISTS user_totp (
+    "userId" BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret VARCHAR(64) NOT NULL,
+    enabled_at TIMESTAMPTZ,
+    last_used_step BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Single-use fallback codes, SHA-256 digests of the normalized code
+CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+    id BIGSERIAL PRIMARY KEY,
+    "userId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS mfa_recovery_codes_user_idx ON mfa_recovery_codes ("userId") WHERE used_at IS NULL;
@@ -0,0 +1,25 @@
+-- Passkeys. credential_id is the base64url ID browsers use; public_key is the
+-- COSE_Key the authenticator returned at registration.
+CREATE TABLE IF NOT EXISTS webauthn_credentials (
+    id BIGSERIAL PRIMARY KEY,
+    "userId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    credential_id TEXT NOT NULL UNIQUE,
+    public_key BYTEA NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    name VARCHAR(64),
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS webauthn_credentials_user_idx ON webauthn_credentials ("userId");
+
+-- Outstanding ceremony challenges; each is taken (deleted) by the response that answers it.
+-- "userId" is set for registrations and NULL for passkey sign-in.
+CREATE TABLE IF NOT EXISTS webauthn_challenges (
+    id UUID PRIMARY KEY,
+    "userId" BIGINT REFERENCES users(id) ON DELETE CASCADE,
+    ceremony VARCHAR(16) NOT NULL CHECK (ceremony IN ('registration', 'authentication')),
+    challenge VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,16 @@
+-- Accounts created through an identity provider have no password, and only get an
+-- email when the provider shares one. "authId" is "<provider>:<subject>".
+ALTER TABLE users ALTER COLUMN password DROP NOT NULL;
+ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS users_auth_id_key ON users ("authId");
+
+-- Logins waiting at the provider. The row is taken (deleted) by the callback that
+-- answers it, so a state value works once.
+CREATE TABLE IF NOT EXISTS oidc_login_states (
+    state VARCHAR(64) PRIMARY KEY,
+    provider VARCHAR(32) NOT NULL,
+    code_verifier VARCHAR(128) NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,23 @@
+-- Recent failed sign-ins per key: "user:<id>", "login:<identifier>" for names that
+-- match no account (so they behave the same), and "ip:<address>". blocked_until is
+-- when the next attempt is allowed, set by backoff or by a lockout.
+CREATE TABLE IF NOT EXISTS login_failures (
+    key VARCHAR(320) PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    blocked_until TIMESTAMPTZ,
+    last_failure_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS login_failures_last_failure_idx ON login_failures (last_failure_at);
+
+-- Single-use links mailed when an account is locked. Only SHA-256 digests are stored.
+CREATE TABLE IF NOT EXISTS account_unlock_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    "userId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS account_unlock_tokens_user_idx ON account_unlock_tokens ("userId") WHERE used_at IS NULL;
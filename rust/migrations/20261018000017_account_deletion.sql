@@ -0,0 +1,8 @@
+-- Deleting an account only schedules it: until deletion_scheduled_at the owner can
+-- sign in and cancel. After that the row is scrubbed rather than removed, so chats,
+-- direct messages and revisions that join on users keep a (now anonymous) author.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deletion_scheduled_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS users_deletion_scheduled_idx ON users (deletion_scheduled_at)
+    WHERE deletion_scheduled_at IS NOT NULL AND deleted_at IS NULL;
@@ -0,0 +1,7 @@
+-- Optional profile fields shown next to the nickname. nickname_changed_at enforces the
+-- cooldown between nickname changes; NULL means it was never changed.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name VARCHAR(64);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS bio VARCHAR(280);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS nickname_changed_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS users_nickname_lower_idx ON users (LOWER(nickname));
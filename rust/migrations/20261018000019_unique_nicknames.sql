@@ -0,0 +1,9 @@
+-- Nicknames are unique ignoring case, so two renames can't race past the check in
+-- save_profile. Older accounts that only differ in case keep the first one; the
+-- others get their ID appended.
+UPDATE users SET nickname = users.nickname || '-' || users.id
+FROM users earlier
+WHERE LOWER(earlier.nickname) = LOWER(users.nickname) AND earlier.id < users.id;
+
+DROP INDEX IF EXISTS users_nickname_lower_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS users_nickname_lower_key ON users (LOWER(nickname));
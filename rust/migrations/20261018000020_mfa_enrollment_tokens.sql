@@ -0,0 +1,12 @@
+-- Single-use codes mailed to accounts with neither a password nor a second factor,
+-- so turning on TOTP needs more than an access token. Only SHA-256 digests are stored.
+CREATE TABLE IF NOT EXISTS mfa_enrollment_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    "userId" BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS mfa_enrollment_tokens_user_idx ON mfa_enrollment_tokens ("userId") WHERE used_at IS NULL;
@@ -0,0 +1,10 @@
+-- Emails are unique ignoring case, since every lookup matches them with LOWER().
+-- Of older accounts that only differ in case, the verified one (or else the oldest)
+-- keeps the address; the others lose it and sign in with their nickname until they
+-- set a new one.
+UPDATE users SET email = NULL, email_verified_at = NULL
+FROM users keeper
+WHERE LOWER(keeper.email) = LOWER(users.email) AND keeper.id <> users.id
+  AND (keeper.email_verified_at IS NOT NULL, -keeper.id) > (users.email_verified_at IS NOT NULL, -users.id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_key ON users (LOWER(email));
@@ -36,6 +36,12 @@ pub struct ComposeService {
     users: Vec<ComposeUser>,
 }
 
+impl Default for ComposeService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ComposeService {
     pub fn new() -> Self {
         Self {
@@ -258,6 +264,7 @@ mod tests {
 
         let user1_rooms = service.get_user_rooms(&user1.id);
         assert_eq!(user1_rooms.len(), 2);
+        assert!(user1_rooms.iter().any(|room| room.id == room1.id));
 
         let user2_rooms = service.get_user_rooms(&user2.id);
         assert_eq!(user2_rooms.len(), 1);
@@ -0,0 +1,142 @@
+use crate::AppState;
+use crate::controllers::account_controller::deletion::{
+    DeletionTrigger, anonymize_account, cancel_deletion, deletion_grace_days, schedule_deletion,
+};
+use crate::controllers::lockout_controller::reauth::{ReauthError, confirm_identity};
+use crate::controllers::session_controller::session::{ClientInfo, revoke_all_sessions};
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use crate::mailer::{Email, send_in_background};
+use crate::socket::handlers::disconnect_sessions;
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+#[derive(Deserialize)]
+pub struct DeleteAccount {
+    pub password: Option<String>,
+    // TOTP or recovery code, when two-factor authentication is on
+    pub code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DeletionScheduled {
+    pub deletion_scheduled_at: DateTime<Utc>,
+}
+
+// Schedules the account for deletion and signs it out everywhere. Signing in again and
+// cancelling within the grace period keeps it; after that it is anonymized for good.
+pub async fn delete_account(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<DeleteAccount>,
+) -> impl IntoResponse {
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) if user.deleted_at.is_none() => user,
+        _ => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    };
+    if user.deletion_scheduled_at.is_some() {
+        return (
+            StatusCode::CONFLICT,
+            Resp::error("Account is already scheduled for deletion"),
+        );
+    }
+
+    let client = ClientInfo::from_request(&headers, peer);
+    let (password, code) = (params.password.as_deref(), params.code.as_deref());
+    match confirm_identity(&state.db, &state.mailer, &user, password, code, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(e)) => {
+            eprintln!("Failed to confirm identity: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to delete account"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    let grace_days = deletion_grace_days();
+    if grace_days == 0 {
+        return match anonymize_account(&state.db, &state.storage, user.id, DeletionTrigger::Owner).await {
+            Ok(Some(revoked)) => {
+                disconnect_sessions(&state.user_sockets, user.id, &revoked).await;
+                Resp::success("Account deleted", None)
+            }
+            Ok(None) => (StatusCode::NOT_FOUND, Resp::error("User not found")),
+            Err(e) => {
+                eprintln!("Failed to delete account: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Resp::error("Failed to delete account"),
+                )
+            }
+        };
+    }
+
+    let scheduled = match schedule_deletion(&state.db, user.id, grace_days).await {
+        Ok(Some(scheduled)) => scheduled,
+        Ok(None) => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+        Err(e) => {
+            eprintln!("Failed to schedule account deletion: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to delete account"),
+            );
+        }
+    };
+
+    match revoke_all_sessions(&state.db, user.id, None, "account_deletion").await {
+        Ok(revoked) => disconnect_sessions(&state.user_sockets, user.id, &revoked).await,
+        Err(e) => eprintln!("Failed to revoke sessions after deletion request: {}", e),
+    }
+
+    if let Some(email) = user.email {
+        send_in_background(
+            state.mailer.clone(),
+            Email {
+                to: email,
+                subject: "Your account will be deleted".to_string(),
+                body: format!(
+                    "Your account is scheduled for deletion on {} and was signed out everywhere.\n\n\
+                     To keep it, sign in before then and cancel the deletion.",
+                    scheduled.format("%Y-%m-%d %H:%M UTC")
+                ),
+            },
+        );
+    }
+
+    Resp::success(
+        format!("Account will be deleted in {} days", grace_days),
+        Some(DeletionScheduled {
+            deletion_scheduled_at: scheduled,
+        }),
+    )
+}
+
+// Keeps an account whose deletion is still in its grace period
+pub async fn cancel_account_deletion(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+) -> impl IntoResponse {
+    match cancel_deletion(&state.db, user_id).await {
+        Ok(true) => Resp::success("Account deletion cancelled", None::<()>),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Resp::error("Account is not scheduled for deletion"),
+        ),
+        Err(e) => {
+            eprintln!("Failed to cancel account deletion: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to cancel account deletion"),
+            )
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::controllers::lockout_controller::guard::LoginKey;
+use crate::controllers::session_controller::session::revoke_all_sessions;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::avatar::{AvatarType, generate_avatar};
+use crate::storage::SharedStorage;
+
+pub const DEFAULT_DELETION_GRACE_DAYS: i32 = 14;
+
+// Days between asking for deletion and the account being scrubbed; 0 deletes right away
+pub fn deletion_grace_days() -> i32 {
+    env::var("ACCOUNT_DELETION_GRACE_DAYS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_DELETION_GRACE_DAYS)
+}
+
+// When the account will be deleted; None when it is already gone
+pub async fn schedule_deletion(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    grace_days: i32,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let scheduled = sqlx::query_scalar::<_, DateTime<Utc>>(
+        r#"
+        UPDATE users SET deletion_scheduled_at = NOW() + make_interval(days => $2), updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING deletion_scheduled_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(grace_days)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(scheduled) = scheduled {
+        record_audit(
+            &mut *tx,
+            AuditEntry {
+                actor_id: Some(user_id),
+                action: "user.deletion_scheduled",
+                target_type: "user",
+                target_id: Some(user_id),
+                metadata: serde_json::json!({ "scheduledAt": scheduled }),
+            },
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(scheduled)
+}
+
+// False when no deletion was pending
+pub async fn cancel_deletion(db: &Pool<Postgres>, user_id: i64) -> Result<bool, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let result = sqlx::query(
+        r#"
+        UPDATE users SET deletion_scheduled_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND deletion_scheduled_at IS NOT NULL AND deleted_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.deletion_cancelled",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+// Why an account is being deleted. The purge job only deletes accounts that are still
+// scheduled and due, since the owner may have cancelled after the job listed them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DeletionTrigger {
+    Owner,
+    GracePeriodOver,
+}
+
+const DELETABLE: &str = r#"id = $1 AND deleted_at IS NULL AND ($2 OR deletion_scheduled_at <= NOW())"#;
+
+// Scrubs everything that identifies the user or lets anyone sign in as them. The row
+// itself stays, because chats and direct messages INNER JOIN users: their messages
+// are kept but now show a placeholder author. Their uploads are removed from storage.
+// Returns the sessions that were revoked, or None when the account was no longer deletable.
+pub async fn anonymize_account(
+    db: &Pool<Postgres>,
+    storage: &SharedStorage,
+    user_id: i64,
+    trigger: DeletionTrigger,
+) -> Result<Option<Vec<Uuid>>, sqlx::Error> {
+    let immediate = trigger == DeletionTrigger::Owner;
+    let deletable = sqlx::query_scalar::<_, bool>(&format!("SELECT EXISTS (SELECT 1 FROM users WHERE {})", DELETABLE))
+        .bind(user_id)
+        .bind(immediate)
+        .fetch_one(db)
+        .await?;
+    if !deletable {
+        return Ok(None);
+    }
+    let revoked = revoke_all_sessions(db, user_id, None, "account_deleted").await?;
+
+    // No signup path can claim `deleted-<id>` any more, but an account from before the
+    // nickname rules might hold it; the unique index would then fail the purge for good
+    let mut nickname = format!("deleted-{}", user_id);
+    let taken = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM users WHERE LOWER(nickname) = LOWER($1) AND id <> $2)",
+    )
+    .bind(&nickname)
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+    if taken {
+        nickname = format!("deleted-{}-{}", user_id, &Uuid::new_v4().simple().to_string()[..8]);
+    }
+    let avatar = generate_avatar(nickname.clone(), AvatarType::Identicon);
+    let mut tx = db.begin().await?;
+
+    // Checked again here: a cancellation since the check above wins over the purge
+    let result = sqlx::query(&format!(
+        r#"
+        UPDATE users SET
+            email = NULL, password = NULL, nickname = $3, avatar = $4, "authId" = NULL,
+            display_name = NULL, bio = NULL,
+            refresh_token = NULL, "socketId" = NULL, email_verified_at = NULL, role = 'user',
+            deletion_scheduled_at = NULL, deleted_at = NOW(), updated_at = NOW()
+        WHERE {}
+        "#,
+        DELETABLE
+    ))
+    .bind(user_id)
+    .bind(immediate)
+    .bind(&nickname)
+    .bind(&avatar)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(Some(revoked));
+    }
+
+    // Credentials and pending links go entirely
+    for table in [
+        "user_totp",
+        "mfa_recovery_codes",
+        "webauthn_credentials",
+        "webauthn_challenges",
+        "password_reset_tokens",
+        "email_verification_tokens",
+        "account_unlock_tokens",
+        "mfa_enrollment_tokens",
+    ] {
+        sqlx::query(&format!(r#"DELETE FROM {} WHERE "userId" = $1"#, table))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("DELETE FROM login_failures WHERE key = $1")
+        .bind(LoginKey::User(user_id).key())
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(r#"UPDATE rooms SET "ownerId" = NULL WHERE "ownerId" = $1"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Messages keep their text but lose the link to the file; chats."attachmentId" is
+    // cleared by its foreign key
+    sqlx::query(
+        r#"UPDATE chats SET attachment = NULL WHERE "attachmentId" IN (SELECT id FROM attachments WHERE "ownerId" = $1)"#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    let uploads = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"DELETE FROM attachments WHERE "ownerId" = $1 RETURNING storage_key, thumbnail_key"#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // Session history keeps when and how often, but not from where
+    sqlx::query(r#"UPDATE sessions SET ip = NULL, user_agent = NULL WHERE "userId" = $1"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Audit records stay, without what they kept about the user: old and new emails and
+    // nicknames, addresses and user agents, and the text of their deleted messages
+    sqlx::query(r#"UPDATE audit_logs SET metadata = '{}'::jsonb WHERE "targetType" = 'user' AND "targetId" = $1"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        UPDATE audit_logs SET metadata = metadata - 'message' - 'attachment'
+        WHERE "targetType" = 'chat' AND metadata->'authorId' = to_jsonb($1::BIGINT)
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: None,
+            action: "user.deleted",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Only once the rows are gone, so a failed purge never points at missing files.
+    // A blob that can't be deleted is left behind rather than failing the deletion.
+    for (key, thumbnail_key) in uploads {
+        for key in std::iter::once(key).chain(thumbnail_key) {
+            if let Err(err) = storage.delete(&key).await {
+                eprintln!("Failed to delete attachment {} of account {}: {}", key, user_id, err);
+            }
+        }
+    }
+    Ok(Some(revoked))
+}
+
+// Accounts whose grace period is over
+pub async fn due_deletions(db: &Pool<Postgres>) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM users WHERE deletion_scheduled_at <= NOW() AND deleted_at IS NULL",
+    )
+    .fetch_all(db)
+    .await
+}
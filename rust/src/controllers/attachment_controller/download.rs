@@ -0,0 +1,73 @@
+use crate::AppState;
+use crate::controllers::attachment_controller::get_attachment::get_attachment;
+use crate::libs::Resp;
+use crate::storage::StorageError;
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Resp::<()>::error("Attachment not found"),
+    )
+        .into_response()
+}
+
+async fn serve(state: &AppState, key: &str, content_type: String, file_name: &str) -> Response {
+    let bytes = match state.storage.get(key).await {
+        Ok(bytes) => bytes,
+        Err(StorageError::NotFound) => return not_found(),
+        Err(err) => {
+            eprintln!("Failed to read attachment {}: {}", key, err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::<()>::error("Failed to read attachment"),
+            )
+                .into_response();
+        }
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            // file_name is sanitized on upload, so it is safe to quote here
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}\"", file_name),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (header::CACHE_CONTROL, "private, max-age=3600".to_string()),
+        ],
+        bytes,
+    )
+        .into_response()
+}
+
+pub async fn download_attachment(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match get_attachment(id, &state.db).await {
+        Some(attachment) => {
+            serve(&state, &attachment.storage_key, attachment.content_type, &attachment.file_name).await
+        }
+        None => not_found(),
+    }
+}
+
+pub async fn download_thumbnail(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    let attachment = match get_attachment(id, &state.db).await {
+        Some(attachment) => attachment,
+        None => return not_found(),
+    };
+
+    match (attachment.thumbnail_key, attachment.thumbnail_content_type) {
+        (Some(key), Some(content_type)) => serve(&state, &key, content_type, &attachment.file_name).await,
+        _ => not_found(),
+    }
+}
@@ -0,0 +1,35 @@
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::db::model::Attachment;
+
+// Path clients use to download an attachment; it requires the same bearer token as the API
+pub fn attachment_url(id: Uuid) -> String {
+    format!("/v1/attachments/{}", id)
+}
+
+pub fn thumbnail_url(id: Uuid) -> String {
+    format!("/v1/attachments/{}/thumbnail", id)
+}
+
+pub async fn get_attachment(id: Uuid, db: &Pool<Postgres>) -> Option<Attachment> {
+    let result = sqlx::query_as::<_, Attachment>("SELECT * FROM attachments WHERE id = $1")
+        .bind(id)
+        .fetch_optional(db)
+        .await;
+
+    match result {
+        Ok(attachment) => attachment,
+        Err(e) => {
+            eprintln!("Error loading attachment: {}", e);
+            None
+        }
+    }
+}
+
+// Messages may only reference files their author uploaded
+pub async fn get_owned_attachment(id: Uuid, user_id: i64, db: &Pool<Postgres>) -> Option<Attachment> {
+    get_attachment(id, db)
+        .await
+        .filter(|attachment| attachment.owner_id == Some(user_id))
+}
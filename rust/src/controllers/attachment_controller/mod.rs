@@ -0,0 +1,5 @@
+pub mod download;
+pub mod get_attachment;
+pub mod thumbnail;
+pub mod upload;
+pub mod validate;
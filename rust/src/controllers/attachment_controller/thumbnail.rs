@@ -0,0 +1,144 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader, ImageResult, Limits};
+
+pub const THUMBNAIL_SIZE: u32 = 320;
+const MAX_DIMENSION: u32 = 8192;
+const MAX_DECODE_BYTES: u64 = 256 * 1024 * 1024;
+
+pub struct ProcessedImage {
+    // Re-encoded file without EXIF or other metadata; None keeps the upload as is
+    pub sanitized: Option<Vec<u8>>,
+    pub width: u32,
+    pub height: u32,
+    pub blurhash: Option<String>,
+    pub thumbnail: Vec<u8>,
+    pub thumbnail_content_type: &'static str,
+}
+
+fn decode(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(ImageError::IoError)?;
+
+    // Refuse decompression bombs before allocating the pixel buffer
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder()?;
+    // The orientation lives in the EXIF block we are about to drop, so bake it into the pixels
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+// Encoders never write metadata, so re-encoding is what strips EXIF (GPS, device, ...)
+fn sanitize(image: &DynamicImage, content_type: &str) -> ImageResult<Option<Vec<u8>>> {
+    let mut out = Vec::new();
+    match content_type {
+        "image/jpeg" => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, 90))?,
+        "image/png" => image.write_to(Cursor::new(&mut out), ImageFormat::Png)?,
+        "image/webp" => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut out))?,
+        // GIF has no EXIF, and re-encoding would drop the animation
+        _ => return Ok(None),
+    }
+    Ok(Some(out))
+}
+
+fn thumbnail(image: &DynamicImage) -> ImageResult<(Vec<u8>, &'static str)> {
+    let thumb = if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image.clone()
+    };
+
+    let mut out = Vec::new();
+    if thumb.color().has_alpha() {
+        thumb.write_to(Cursor::new(&mut out), ImageFormat::Png)?;
+        Ok((out, "image/png"))
+    } else {
+        DynamicImage::ImageRgb8(thumb.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, 80))?;
+        Ok((out, "image/jpeg"))
+    }
+}
+
+// CPU bound; run it on the blocking pool
+pub fn process_image(bytes: &[u8], content_type: &str) -> ImageResult<ProcessedImage> {
+    let image = decode(bytes)?;
+    let sanitized = sanitize(&image, content_type)?;
+    let (thumbnail, thumbnail_content_type) = thumbnail(&image)?;
+
+    // The hash only encodes a handful of colour components, a tiny copy is plenty
+    let small = image.thumbnail(32, 32).to_rgba8();
+    let blurhash = blurhash::encode(4, 3, small.width(), small.height(), small.as_raw()).ok();
+
+    Ok(ProcessedImage {
+        sanitized,
+        width: image.width(),
+        height: image.height(),
+        blurhash,
+        thumbnail,
+        thumbnail_content_type,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+        let mut out = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, 90))
+            .unwrap();
+        out
+    }
+
+    // Splices an APP1 Exif segment right after the SOI marker
+    fn with_exif(jpeg: &[u8]) -> Vec<u8> {
+        let payload = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0secret-gps";
+        let len = (payload.len() + 2) as u16;
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(payload);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    #[test]
+    fn strips_exif_and_reports_dimensions() {
+        let upload = with_exif(&jpeg(640, 480));
+        let processed = process_image(&upload, "image/jpeg").unwrap();
+
+        let sanitized = processed.sanitized.unwrap();
+        assert!(!sanitized.windows(4).any(|w| w == b"Exif"));
+        assert_eq!((processed.width, processed.height), (640, 480));
+        assert!(processed.blurhash.is_some());
+    }
+
+    #[test]
+    fn thumbnails_fit_the_bounding_box() {
+        let processed = process_image(&jpeg(1000, 500), "image/jpeg").unwrap();
+        let thumb = image::load_from_memory(&processed.thumbnail).unwrap();
+
+        assert_eq!(processed.thumbnail_content_type, "image/jpeg");
+        assert_eq!((thumb.width(), thumb.height()), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+    }
+
+    #[test]
+    fn rejects_undecodable_images() {
+        assert!(process_image(b"\x89PNG\r\n\x1a\nnot really", "image/png").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::AppState;
+use crate::controllers::attachment_controller::get_attachment::{attachment_url, thumbnail_url};
+use crate::controllers::attachment_controller::thumbnail::process_image;
+use crate::controllers::attachment_controller::validate::{detect_mime, max_upload_bytes, sanitize_file_name};
+use crate::libs::Resp;
+use crate::extract::UserId;
+use axum::extract::{Multipart, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct UploadResponse {
+    pub id: Uuid,
+    pub url: String,
+    pub thumbnail_url: Option<String>,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub blurhash: Option<String>,
+}
+
+// Expects a multipart form with a single `file` field
+pub async fn upload_attachment(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let max_bytes = max_upload_bytes();
+
+    let mut field = loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("file") => break field,
+            Ok(Some(_)) => continue,
+            Ok(None) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Resp::error("Missing `file` field"),
+                );
+            }
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Resp::error(format!("Invalid upload: {}", err)),
+                );
+            }
+        }
+    };
+
+    let file_name = sanitize_file_name(field.file_name().unwrap_or(""));
+
+    // Read in chunks so an oversized file is rejected without buffering all of it
+    let mut bytes = Vec::new();
+    loop {
+        match field.chunk().await {
+            Ok(Some(chunk)) => {
+                if bytes.len() + chunk.len() > max_bytes {
+                    return (
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        Resp::error(format!("File exceeds the {} byte limit", max_bytes)),
+                    );
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            Ok(None) => break,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Resp::error(format!("Invalid upload: {}", err)),
+                );
+            }
+        }
+    }
+
+    if bytes.is_empty() {
+        return (StatusCode::BAD_REQUEST, Resp::error("File is empty"));
+    }
+
+    let content_type = match detect_mime(&bytes) {
+        Some(content_type) => content_type,
+        None => {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Resp::error("File type is not allowed"),
+            );
+        }
+    };
+
+    // Images are decoded so EXIF is stripped and a preview exists before anyone downloads them
+    let processed = if content_type.starts_with("image/") {
+        let result = tokio::task::spawn_blocking(move || {
+            let processed = process_image(&bytes, content_type);
+            (bytes, processed)
+        })
+        .await;
+        match result {
+            Ok((original, Ok(processed))) => {
+                bytes = processed.sanitized.clone().unwrap_or(original);
+                Some(processed)
+            }
+            Ok((_, Err(err))) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Resp::error(format!("Image could not be processed: {}", err)),
+                );
+            }
+            Err(err) => {
+                eprintln!("Image processing task failed: {}", err);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Resp::error("Failed to store attachment"),
+                );
+            }
+        }
+    } else {
+        None
+    };
+
+    let id = Uuid::new_v4();
+    let storage_key = format!("attachments/{}", id);
+    let thumbnail_key = processed.as_ref().map(|_| format!("thumbnails/{}", id));
+
+    if let Err(err) = state.storage.put(&storage_key, &bytes, content_type).await {
+        eprintln!("Failed to store attachment: {}", err);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error("Failed to store attachment"),
+        );
+    }
+
+    if let (Some(processed), Some(key)) = (&processed, &thumbnail_key) {
+        if let Err(err) = state
+            .storage
+            .put(key, &processed.thumbnail, processed.thumbnail_content_type)
+            .await
+        {
+            eprintln!("Failed to store thumbnail: {}", err);
+            state.storage.delete(&storage_key).await.ok();
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to store attachment"),
+            );
+        }
+    }
+
+    let width = processed.as_ref().map(|p| p.width as i32);
+    let height = processed.as_ref().map(|p| p.height as i32);
+    let blurhash = processed.as_ref().and_then(|p| p.blurhash.clone());
+
+    let query = r#"
+        INSERT INTO attachments (
+            id, "ownerId", storage_key, file_name, content_type, size_bytes,
+            width, height, blurhash, thumbnail_key, thumbnail_content_type, created_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
+    "#;
+    let result = sqlx::query(query)
+        .bind(id)
+        .bind(user_id)
+        .bind(&storage_key)
+        .bind(&file_name)
+        .bind(content_type)
+        .bind(bytes.len() as i64)
+        .bind(width)
+        .bind(height)
+        .bind(&blurhash)
+        .bind(&thumbnail_key)
+        .bind(processed.as_ref().map(|p| p.thumbnail_content_type))
+        .execute(&state.db)
+        .await;
+
+    if let Err(err) = result {
+        eprintln!("Failed to save attachment: {}", err);
+        // Don't leave orphaned objects behind
+        state.storage.delete(&storage_key).await.ok();
+        if let Some(key) = &thumbnail_key {
+            state.storage.delete(key).await.ok();
+        }
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error("Failed to store attachment"),
+        );
+    }
+
+    Resp::success(
+        "Attachment uploaded",
+        Some(UploadResponse {
+            id,
+            url: attachment_url(id),
+            thumbnail_url: thumbnail_key.map(|_| thumbnail_url(id)),
+            file_name,
+            content_type: content_type.to_string(),
+            size_bytes: bytes.len() as i64,
+            width,
+            height,
+            blurhash,
+        }),
+    )
+}
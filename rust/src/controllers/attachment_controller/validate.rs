@@ -0,0 +1,71 @@
+use std::env;
+
+pub const DEFAULT_MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+// Types are detected from the file contents; the client's Content-Type is not trusted
+pub const ALLOWED_MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "audio/mpeg",
+    "audio/ogg",
+    "video/mp4",
+    "video/webm",
+];
+
+pub fn max_upload_bytes() -> usize {
+    env::var("UPLOAD_MAX_BYTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES)
+}
+
+pub fn detect_mime(bytes: &[u8]) -> Option<&'static str> {
+    let mime = infer::get(bytes)?.mime_type();
+    ALLOWED_MIME_TYPES.iter().copied().find(|allowed| *allowed == mime)
+}
+
+// Keeps only the final path segment and characters that are safe in a Content-Disposition header
+pub fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ' '))
+        .take(255)
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.');
+    if cleaned.is_empty() {
+        "file".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_allowed_types_from_magic_bytes() {
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D];
+        assert_eq!(detect_mime(&png), Some("image/png"));
+        assert_eq!(detect_mime(b"%PDF-1.7\n"), Some("application/pdf"));
+    }
+
+    #[test]
+    fn rejects_unknown_and_disallowed_types() {
+        assert_eq!(detect_mime(b"just some text"), None);
+        // ELF executables are recognised by infer but not allowed
+        assert_eq!(detect_mime(&[0x7F, b'E', b'L', b'F', 2, 1, 1, 0]), None);
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name("C:\\photos\\cat pic.png"), "cat pic.png");
+        assert_eq!(sanitize_file_name("a\"b;\r\n.jpg"), "ab.jpg");
+        assert_eq!(sanitize_file_name(".."), "file");
+    }
+}
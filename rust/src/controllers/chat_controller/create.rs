@@ -1,29 +1,38 @@
 use crate::AppState;
-use crate::db::model::Chat;
+use crate::controllers::attachment_controller::get_attachment::{attachment_url, get_owned_attachment};
+use crate::controllers::chat_controller::reply_message::reply_target_room;
+use crate::controllers::email_controller::verification::may_post_messages;
+use crate::controllers::room_controller::get_room::get_active_room;
 use crate::libs::Resp;
 use crate::extract::UserId;
+use crate::socket::events::{MessageEvent, emit_new_message};
 use axum::extract::{Json, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::Pool;
-use sqlx::Postgres;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct CreateChatRequest {
     pub message: String,
     pub attachment: Option<String>,
+    // An upload from POST /v1/attachments; takes precedence over `attachment`
+    pub attachment_id: Option<Uuid>,
     pub reply_id: Option<i64>,
+    pub room_id: i64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, sqlx::FromRow)]
 pub struct CreateChatResponse {
     pub id: i64,
     pub message: String,
     pub attachment: Option<String>,
+    pub attachment_id: Option<Uuid>,
     pub user_id: i64,
     pub reply_id: Option<i64>,
-    pub created_at: String,
+    pub room_id: i64,
+    pub created_at: DateTime<Utc>,
 }
 
 pub async fn create_chat(
@@ -41,24 +50,81 @@ pub async fn create_chat(
 
     let connection = &state.db;
 
+    if !may_post_messages(user_id, connection).await {
+        return (
+            StatusCode::FORBIDDEN,
+            Resp::error("Verify your email address before posting messages"),
+        );
+    }
+
+    if get_active_room(params.room_id, connection).await.is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Resp::error("Room not found or archived"),
+        );
+    }
+
+    if let Some(reply_id) = params.reply_id {
+        match reply_target_room(reply_id, connection).await {
+            Ok(Some(room_id)) if room_id == params.room_id => {}
+            Ok(_) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Resp::error("Original message not found"),
+                );
+            }
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Resp::error(format!("Failed to load original message: {}", err)),
+                );
+            }
+        }
+    }
+
+    let (attachment, attachment_id) = match params.attachment_id {
+        Some(id) => match get_owned_attachment(id, user_id, connection).await {
+            Some(uploaded) => (Some(attachment_url(uploaded.id)), Some(uploaded.id)),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Resp::error("Attachment not found"),
+                );
+            }
+        },
+        None => (params.attachment.clone(), None),
+    };
+
     // Insert new chat message
     let query = r#"
-        INSERT INTO chats (message, attachment, "userId", "replyId", created_at, updated_at)
-        VALUES ($1, $2, $3, $4, NOW(), NOW())
-        RETURNING id, message, attachment, "userId" as user_id, "replyId" as reply_id, created_at
+        INSERT INTO chats (message, attachment, "userId", "replyId", "roomId", "attachmentId", created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+        RETURNING id, message, attachment, "attachmentId" as attachment_id, "userId" as user_id, "replyId" as reply_id, "roomId" as room_id, created_at
     "#;
 
     let result = sqlx::query_as::<_, CreateChatResponse>(query)
         .bind(&params.message)
-        .bind(params.attachment.clone())
+        .bind(attachment)
         .bind(user_id) // Use actual user ID from JWT
         .bind(params.reply_id)
+        .bind(params.room_id)
+        .bind(attachment_id)
         .fetch_one(connection)
         .await;
 
     match result {
         Ok(chat) => {
-            // TODO: Emit Socket.IO event for new message
+            let event = MessageEvent {
+                id: chat.id,
+                message: Some(&chat.message),
+                attachment: chat.attachment.as_deref(),
+                user_id: chat.user_id,
+                reply_id: chat.reply_id,
+                room_id: chat.room_id,
+                created_at: Some(chat.created_at),
+            };
+            emit_new_message(&state.io, connection, &event).await;
+
             Resp::success("Message created successfully", Some(chat))
         }
         Err(err) => (
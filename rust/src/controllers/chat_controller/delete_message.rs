@@ -1,11 +1,25 @@
 use crate::AppState;
+use crate::controllers::user_controller::get_user::is_moderator;
 use crate::libs::Resp;
+use crate::libs::audit::{AuditEntry, record_audit};
 use crate::extract::UserId;
+use crate::socket::events::emit_message_deleted;
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use sqlx::Pool;
-use sqlx::Postgres;
+
+// Text left in place of a deleted message so threads still render
+pub const TOMBSTONE_MESSAGE: &str = "message deleted";
+
+#[derive(sqlx::FromRow)]
+struct DeletableChat {
+    #[sqlx(rename = "userId")]
+    user_id: i64,
+    #[sqlx(rename = "roomId")]
+    room_id: i64,
+    message: String,
+    attachment: Option<String>,
+}
 
 pub async fn delete_chat(
     State(state): State<AppState>,
@@ -14,35 +28,132 @@ pub async fn delete_chat(
 ) -> impl IntoResponse {
     let connection = &state.db;
 
-    // First check if the message exists and belongs to the user (TODO: Add user ID check from JWT)
-    let check_query = "SELECT id FROM chats WHERE id = $1 AND \"userId\" = $2";
-    let exists = sqlx::query_scalar(check_query)
+    let mut tx = match connection.begin().await {
+        Ok(tx) => tx,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(format!("Failed to delete message: {}", err)),
+            );
+        }
+    };
+
+    // The row stays locked until commit, so a concurrent edit can't change the text the
+    // audit record keeps, and a concurrent delete waits and then finds nothing live
+    let check_query = r#"
+        SELECT "userId", "roomId", message, attachment FROM chats
+        WHERE id = $1 AND deleted_at IS NULL
+        FOR UPDATE
+    "#;
+    let chat = sqlx::query_as::<_, DeletableChat>(check_query)
         .bind(chat_id)
-        .bind(user_id) // Use actual user ID from JWT
-        .fetch_optional(connection)
+        .fetch_optional(&mut *tx)
         .await;
 
-    match exists {
-        None => (
+    let chat = match chat {
+        Ok(Some(chat)) => chat,
+        Ok(None) => {
+            tx.rollback().await.ok();
+            return (
+                StatusCode::NOT_FOUND,
+                Resp::error("Message not found or access denied"),
+            );
+        }
+        Err(err) => {
+            tx.rollback().await.ok();
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(format!("Failed to delete message: {}", err)),
+            );
+        }
+    };
+
+    // Authors delete their own messages, moderators and admins anyone's
+    let moderated = chat.user_id != user_id;
+    if moderated && !is_moderator(user_id, connection).await {
+        tx.rollback().await.ok();
+        return (
             StatusCode::NOT_FOUND,
             Resp::error("Message not found or access denied"),
-        ),
-        Some(_) => {
-            // Delete the message
-            let delete_query = "DELETE FROM chats WHERE id = $1 AND \"userId\" = $2";
-            let result = sqlx::query(delete_query)
-                .bind(chat_id)
-                .bind(user_id) // Use actual user ID from JWT
-                .execute(connection)
-                .await;
-
-            match result {
-                Ok(_) => Resp::success("Message deleted successfully", None::<()>),
-                Err(err) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Resp::error(format!("Failed to delete message: {}", err)),
-                ),
-            }
+        );
+    }
+
+    // Soft delete: the row stays as a tombstone so replies keep their target
+    let delete_query = r#"
+        UPDATE chats
+        SET message = $1, attachment = NULL, "attachmentId" = NULL, deleted_at = NOW(), "deletedBy" = $2, updated_at = NOW()
+        WHERE id = $3 AND deleted_at IS NULL
+    "#;
+    let result = sqlx::query(delete_query)
+        .bind(TOMBSTONE_MESSAGE)
+        .bind(user_id) // Use actual user ID from JWT
+        .bind(chat_id)
+        .execute(&mut *tx)
+        .await;
+
+    match result {
+        Ok(result) if result.rows_affected() == 0 => {
+            tx.rollback().await.ok();
+            return (
+                StatusCode::NOT_FOUND,
+                Resp::error("Message not found or access denied"),
+            );
+        }
+        Ok(_) => {}
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(format!("Failed to delete message: {}", err)),
+            );
+        }
+    }
+
+    // Earlier versions would otherwise still expose the deleted text
+    let result = sqlx::query("DELETE FROM chat_revisions WHERE \"chatId\" = $1")
+        .bind(chat_id)
+        .execute(&mut *tx)
+        .await;
+
+    if let Err(err) = result {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to delete message: {}", err)),
+        );
+    }
+
+    // The audit record keeps the removed content for review
+    let audit = record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: if moderated { "chat.moderator_delete" } else { "chat.delete" },
+            target_type: "chat",
+            target_id: Some(chat_id),
+            metadata: serde_json::json!({
+                "authorId": chat.user_id,
+                "roomId": chat.room_id,
+                "message": chat.message,
+                "attachment": chat.attachment,
+            }),
+        },
+    )
+    .await;
+
+    if let Err(err) = audit {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to record deletion: {}", err)),
+        );
+    }
+
+    match tx.commit().await {
+        Ok(_) => {
+            emit_message_deleted(&state.io, chat_id, chat.room_id).await;
+            Resp::success("Message deleted successfully", None::<()>)
         }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to delete message: {}", err)),
+        ),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,142 @@
+use crate::AppState;
+use crate::libs::Resp;
+use crate::extract::UserId;
+use crate::socket::events::emit_message_edited;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct EditChatRequest {
+    pub message: String,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct EditChatResponse {
+    pub id: i64,
+    pub message: String,
+    pub attachment: Option<String>,
+    pub user_id: i64,
+    pub reply_id: Option<i64>,
+    pub room_id: i64,
+    pub edited: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub async fn edit_chat(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Path(chat_id): Path<i64>,
+    Json(params): Json<EditChatRequest>,
+) -> impl IntoResponse {
+    // Validate message
+    if params.message.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Resp::error("Message cannot be empty"),
+        );
+    }
+
+    let connection = &state.db;
+
+    let mut tx = match connection.begin().await {
+        Ok(tx) => tx,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(format!("Failed to edit message: {}", err)),
+            );
+        }
+    };
+
+    // Same ownership rule as delete_chat: only the author may edit. The row stays locked
+    // until commit, so a concurrent edit or delete can't slip in between and the revision
+    // always holds the text this update replaces.
+    let check_query = "SELECT message FROM chats WHERE id = $1 AND \"userId\" = $2 AND deleted_at IS NULL FOR UPDATE";
+    let previous = sqlx::query_scalar::<_, String>(check_query)
+        .bind(chat_id)
+        .bind(user_id) // Use actual user ID from JWT
+        .fetch_optional(&mut *tx)
+        .await;
+
+    let previous = match previous {
+        Ok(Some(previous)) => previous,
+        Ok(None) => {
+            tx.rollback().await.ok();
+            return (
+                StatusCode::NOT_FOUND,
+                Resp::error("Message not found or access denied"),
+            );
+        }
+        Err(err) => {
+            tx.rollback().await.ok();
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(format!("Failed to edit message: {}", err)),
+            );
+        }
+    };
+
+    if previous == params.message {
+        tx.rollback().await.ok();
+        return (
+            StatusCode::BAD_REQUEST,
+            Resp::error("Message is unchanged"),
+        );
+    }
+
+    let update_query = r#"
+        UPDATE chats SET message = $1, edited = TRUE, updated_at = NOW()
+        WHERE id = $2 AND "userId" = $3 AND deleted_at IS NULL
+        RETURNING id, message, attachment, "userId" as user_id, "replyId" as reply_id, "roomId" as room_id, edited, updated_at
+    "#;
+
+    let result = sqlx::query_as::<_, EditChatResponse>(update_query)
+        .bind(&params.message)
+        .bind(chat_id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await;
+
+    let chat = match result {
+        Ok(chat) => chat,
+        Err(err) => {
+            tx.rollback().await.ok();
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(format!("Failed to edit message: {}", err)),
+            );
+        }
+    };
+
+    // Keep the text that was just replaced as a revision
+    let revision = sqlx::query(
+        "INSERT INTO chat_revisions (\"chatId\", \"editorId\", message, created_at) VALUES ($1, $2, $3, NOW())",
+    )
+    .bind(chat_id)
+    .bind(user_id)
+    .bind(&previous)
+    .execute(&mut *tx)
+    .await;
+
+    if let Err(err) = revision {
+        tx.rollback().await.ok();
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to save revision: {}", err)),
+        );
+    }
+
+    if let Err(err) = tx.commit().await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to edit message: {}", err)),
+        );
+    }
+
+    emit_message_edited(&state.io, chat.id, chat.room_id, &chat.message, chat.updated_at).await;
+
+    Resp::success("Message edited successfully", Some(chat))
+}
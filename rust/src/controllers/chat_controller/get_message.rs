@@ -1,12 +1,9 @@
 use crate::AppState;
 use crate::db::model::Chat;
 use crate::libs::Resp;
-use crate::extract::UserId;
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use sqlx::Pool;
-use sqlx::Postgres;
 
 pub async fn get_chat_by_id(
     State(state): State<AppState>,
@@ -27,10 +24,17 @@ pub async fn get_chat_by_id(
                 'id', r.id,
                 'message', r.message,
                 'attachment', r.attachment
-            ) as reply
+            ) as reply,
+            CASE WHEN a.id IS NULL THEN NULL ELSE json_build_object(
+                'id', a.id, 'url', '/v1/attachments/' || a.id,
+                'thumbnailUrl', CASE WHEN a.thumbnail_key IS NULL THEN NULL ELSE '/v1/attachments/' || a.id || '/thumbnail' END,
+                'contentType', a.content_type, 'width', a.width,
+                'height', a.height, 'blurhash', a.blurhash
+            ) END as preview
         FROM chats as c
         INNER JOIN users as u on c."userId" = u.id
         LEFT JOIN chats as r on r.id = c."replyId"
+        LEFT JOIN attachments as a on a.id = c."attachmentId"
         WHERE c.id = $1
     "#;
 
@@ -40,10 +44,17 @@ pub async fn get_chat_by_id(
         .await;
 
     match result {
-        Some(chat) => Resp::success("Chat message retrieved", Some(chat)),
-        None => (
+        Ok(Some(chat)) => Resp::success("Chat message retrieved", Some(chat)),
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Resp::error("Chat message not found"),
         ),
+        Err(err) => {
+            eprintln!("Failed to load chat message: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to load chat message"),
+            )
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+use crate::AppState;
+use crate::db::model::Chat;
+use crate::libs::Resp;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+pub const DEFAULT_HISTORY_LIMIT: i64 = 50;
+pub const MAX_HISTORY_LIMIT: i64 = 100;
+
+// Cursors are message IDs; the *_time variants accept an RFC 3339 timestamp instead
+#[derive(Deserialize, Default)]
+pub struct HistoryQuery {
+    pub room_id: Option<i64>,
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    pub before_time: Option<DateTime<Utc>>,
+    pub after_time: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl HistoryQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY_LIMIT)
+    }
+
+    // Only an `after` cursor walks towards newer messages
+    fn forward(&self) -> bool {
+        (self.after.is_some() || self.after_time.is_some())
+            && self.before.is_none()
+            && self.before_time.is_none()
+    }
+}
+
+// Always newest first. `next_cursor` loads older messages (pass it as `before`),
+// `prev_cursor` loads newer ones (pass it as `after`).
+#[derive(Serialize)]
+pub struct HistoryPage {
+    pub data: Vec<Chat>,
+    pub limit: i64,
+    pub next_cursor: Option<i64>,
+    pub prev_cursor: Option<i64>,
+}
+
+pub async fn fetch_history(db: &Pool<Postgres>, params: &HistoryQuery) -> Result<HistoryPage, sqlx::Error> {
+    let limit = params.limit();
+    let forward = params.forward();
+
+    // (created_at, id) is unique, so ties on the timestamp can't skip or repeat rows
+    let query = format!(
+        r#"
+    select 
+    c.*, 
+    json_build_object(
+        'nickname', u.nickname, 'userId', 
+        u.id, 'avatar', u.avatar, 'email', 
+        u.email
+    ) as user, 
+    json_build_object(
+        'id', r.id, 'message', r.message, 'attachment', 
+        r.attachment
+    ) as reply,
+    CASE WHEN a.id IS NULL THEN NULL ELSE json_build_object(
+        'id', a.id, 'url', '/v1/attachments/' || a.id,
+        'thumbnailUrl', CASE WHEN a.thumbnail_key IS NULL THEN NULL ELSE '/v1/attachments/' || a.id || '/thumbnail' END,
+        'contentType', a.content_type, 'width', a.width,
+        'height', a.height, 'blurhash', a.blurhash
+    ) END as preview
+    FROM 
+    chats as c 
+    INNER JOIN users as u on c."userId" = u.id 
+    LEFT JOIN chats as r on r.id = c."replyId" 
+    LEFT JOIN attachments as a on a.id = c."attachmentId"
+    WHERE ($1::BIGINT IS NULL OR c."roomId" = $1)
+    AND ($2::BIGINT IS NULL OR (c.created_at, c.id) < (SELECT created_at, id FROM chats WHERE id = $2))
+    AND ($3::BIGINT IS NULL OR (c.created_at, c.id) > (SELECT created_at, id FROM chats WHERE id = $3))
+    AND ($4::TIMESTAMPTZ IS NULL OR c.created_at < $4)
+    AND ($5::TIMESTAMPTZ IS NULL OR c.created_at > $5)
+    ORDER BY c.created_at {order}, c.id {order}
+    LIMIT $6;"#,
+        order = if forward { "ASC" } else { "DESC" }
+    );
+
+    // One extra row tells us whether another page exists without a COUNT(*)
+    let mut rows = sqlx::query_as::<_, Chat>(&query)
+        .bind(params.room_id)
+        .bind(params.before)
+        .bind(params.after)
+        .bind(params.before_time)
+        .bind(params.after_time)
+        .bind(limit + 1)
+        .fetch_all(db)
+        .await?;
+
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    if forward {
+        rows.reverse();
+    }
+
+    let (has_older, has_newer) = if forward {
+        (true, has_more)
+    } else {
+        (has_more, params.before.is_some() || params.before_time.is_some())
+    };
+
+    Ok(HistoryPage {
+        next_cursor: rows.last().filter(|_| has_older).map(|chat| chat.id),
+        prev_cursor: rows.first().filter(|_| has_newer).map(|chat| chat.id),
+        data: rows,
+        limit,
+    })
+}
+
+pub async fn get_history(
+    State(state): State<AppState>,
+    Query(params): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    match fetch_history(&state.db, &params).await {
+        Ok(page) => Resp::success("fetching success", Some(page)),
+        Err(err) => {
+            eprintln!("Failed to fetch chat history: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to fetch chat history"),
+            )
+        }
+    }
+}
@@ -1,5 +1,9 @@
 pub mod create;
 pub mod get_message;
 pub mod delete_message;
+pub mod edit_message;
+pub mod history;
 pub mod reply_message;
 pub mod pagination;
+pub mod revisions;
+pub mod search;
@@ -1,7 +1,7 @@
 use crate::AppState;
 use crate::db::model::Chat;
 use crate::libs::Resp;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use serde::{Deserialize, Serialize};
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Deserialize)]
 pub struct PaginationQuery {
     pub page: Option<i64>,
+    pub room_id: Option<i64>,
 }
 
 // Response structure with pagination metadata
@@ -43,30 +44,25 @@ impl PaginationMeta {
     }
 }
 
-// Original handler with path parameter (improved)
-pub async fn get_chat(State(state): State<AppState>, Path(page): Path<i64>) -> impl IntoResponse {
+// Offset pages, kept for existing clients; prefer history::get_history for scrolling
+pub async fn get_chat(
+    State(state): State<AppState>,
+    Path(page): Path<i64>,
+    Query(params): Query<PaginationQuery>,
+) -> impl IntoResponse {
     let page = page.max(1); // Ensure minimum page is 1
     let limit: i64 = 100;
 
     let connection = &state.db;
 
-    let total = match sqlx::query_scalar!("SELECT COUNT(*) FROM chats")
-        .fetch_one(connection)
-        .await
+    let total = match sqlx::query_scalar::<_, i64>(
+        r#"SELECT COUNT(*) FROM chats WHERE $1::BIGINT IS NULL OR "roomId" = $1"#,
+    )
+    .bind(params.room_id)
+    .fetch_one(connection)
+    .await
     {
-        Ok(count) => {
-            if count.is_none() {
-                return Resp::success(
-                    "No chats available",
-                    Some(PaginatedResponse {
-                        data: vec![],
-                        pagination: PaginationMeta::new(page, 100, 0),
-                    }),
-                );
-            }
-            let count = count.unwrap_or(0);
-            count
-        }
+        Ok(count) => count,
         Err(err) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -88,34 +84,40 @@ pub async fn get_chat(State(state): State<AppState>, Path(page): Path<i64>) -> i
     json_build_object(
         'id', r.id, 'message', r.message, 'attachment', 
         r.attachment
-    ) as reply 
+    ) as reply,
+    CASE WHEN a.id IS NULL THEN NULL ELSE json_build_object(
+        'id', a.id, 'url', '/v1/attachments/' || a.id,
+        'thumbnailUrl', CASE WHEN a.thumbnail_key IS NULL THEN NULL ELSE '/v1/attachments/' || a.id || '/thumbnail' END,
+        'contentType', a.content_type, 'width', a.width,
+        'height', a.height, 'blurhash', a.blurhash
+    ) END as preview
     FROM 
     chats as c 
     INNER JOIN users as u on c."userId" = u.id 
     LEFT JOIN chats as r on r.id = c."replyId" 
+    LEFT JOIN attachments as a on a.id = c."attachmentId"
+    WHERE $3::BIGINT IS NULL OR c."roomId" = $3
+    ORDER BY c.created_at DESC, c.id DESC
     limit $1 offset $2;"#;
 
     let results = sqlx::query_as::<_, Chat>(query)
         .bind(limit)
         .bind(offset)
+        .bind(params.room_id)
         .fetch_all(connection)
         .await;
 
     match results {
-        Ok(rows) => {
-            return Resp::success(
-                "fetching success",
-                Some(PaginatedResponse {
-                    data: rows,
-                    pagination: PaginationMeta::new(page, 1, total),
-                }),
-            );
-        }
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Resp::error(err.to_string()),
-            );
-        }
+        Ok(rows) => Resp::success(
+            "fetching success",
+            Some(PaginatedResponse {
+                data: rows,
+                pagination: PaginationMeta::new(page, limit, total),
+            }),
+        ),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(err.to_string()),
+        ),
     }
 }
@@ -1,28 +1,46 @@
 use crate::AppState;
-use crate::db::model::Chat;
+use crate::controllers::attachment_controller::get_attachment::{attachment_url, get_owned_attachment};
+use crate::controllers::email_controller::verification::may_post_messages;
+use crate::controllers::room_controller::get_room::get_active_room;
 use crate::libs::Resp;
 use crate::extract::UserId;
+use crate::socket::events::{MessageEvent, emit_new_message};
 use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::Pool;
 use sqlx::Postgres;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct ReplyChatRequest {
     pub message: String,
     pub attachment: Option<String>,
+    // An upload from POST /v1/attachments; takes precedence over `attachment`
+    pub attachment_id: Option<Uuid>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, sqlx::FromRow)]
 pub struct ReplyChatResponse {
     pub id: i64,
     pub message: String,
     pub attachment: Option<String>,
+    pub attachment_id: Option<Uuid>,
     pub user_id: i64,
     pub reply_id: i64,
-    pub created_at: String,
+    pub room_id: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+// Room of a message that can still be replied to: it exists and isn't deleted.
+// Anything posting a replyId must check the reply goes to this same room.
+pub async fn reply_target_room(original_id: i64, db: &Pool<Postgres>) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>(r#"SELECT "roomId" FROM chats WHERE id = $1 AND deleted_at IS NULL"#)
+        .bind(original_id)
+        .fetch_optional(db)
+        .await
 }
 
 pub async fn reply_to_chat(
@@ -41,40 +59,87 @@ pub async fn reply_to_chat(
 
     let connection = &state.db;
 
-    // Check if the original message exists
-    let original_exists = sqlx::query_scalar("SELECT id FROM chats WHERE id = $1")
-        .bind(original_id)
-        .fetch_optional(connection)
-        .await;
+    if !may_post_messages(user_id, connection).await {
+        return (
+            StatusCode::FORBIDDEN,
+            Resp::error("Verify your email address before posting messages"),
+        );
+    }
 
-    match original_exists {
-        None => (
-            StatusCode::NOT_FOUND,
-            Resp::error("Original message not found"),
-        ),
-        Some(_) => {
-            // Insert reply message
-            let query = r#"
-                INSERT INTO chats (message, attachment, "userId", "replyId", created_at, updated_at)
-                VALUES ($1, $2, $3, $4, NOW(), NOW())
-                RETURNING id, message, attachment, "userId" as user_id, "replyId" as reply_id, created_at
-            "#;
+    // The reply lives in the same room as the message it answers
+    let original_room = reply_target_room(original_id, connection).await;
+
+    let room_id = match original_room {
+        Ok(Some(room_id)) => room_id,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Resp::error("Original message not found"),
+            );
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(format!("Failed to load original message: {}", err)),
+            );
+        }
+    };
 
-            let result = sqlx::query_as::<_, ReplyChatResponse>(query)
-                .bind(&params.message)
-                .bind(params.attachment.clone())
-                .bind(user_id) // Use actual user ID from JWT
-                .bind(original_id)
-                .fetch_one(connection)
-                .await;
+    if get_active_room(room_id, connection).await.is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Resp::error("Room not found or archived"),
+        );
+    }
 
-            match result {
-                Ok(reply) => Resp::success("Reply created successfully", Some(reply)),
-                Err(err) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Resp::error(format!("Failed to create reply: {}", err)),
-                ),
+    let (attachment, attachment_id) = match params.attachment_id {
+        Some(id) => match get_owned_attachment(id, user_id, connection).await {
+            Some(uploaded) => (Some(attachment_url(uploaded.id)), Some(uploaded.id)),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Resp::error("Attachment not found"),
+                );
             }
+        },
+        None => (params.attachment.clone(), None),
+    };
+
+    // Insert reply message
+    let query = r#"
+        INSERT INTO chats (message, attachment, "userId", "replyId", "roomId", "attachmentId", created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+        RETURNING id, message, attachment, "attachmentId" as attachment_id, "userId" as user_id, "replyId" as reply_id, "roomId" as room_id, created_at
+    "#;
+
+    let result = sqlx::query_as::<_, ReplyChatResponse>(query)
+        .bind(&params.message)
+        .bind(attachment)
+        .bind(user_id) // Use actual user ID from JWT
+        .bind(original_id)
+        .bind(room_id)
+        .bind(attachment_id)
+        .fetch_one(connection)
+        .await;
+
+    match result {
+        Ok(reply) => {
+            let event = MessageEvent {
+                id: reply.id,
+                message: Some(&reply.message),
+                attachment: reply.attachment.as_deref(),
+                user_id: reply.user_id,
+                reply_id: Some(reply.reply_id),
+                room_id: reply.room_id,
+                created_at: Some(reply.created_at),
+            };
+            emit_new_message(&state.io, connection, &event).await;
+
+            Resp::success("Reply created successfully", Some(reply))
         }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to create reply: {}", err)),
+        ),
     }
 }
\ No newline at end of file
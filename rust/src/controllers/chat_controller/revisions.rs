@@ -0,0 +1,51 @@
+use crate::AppState;
+use crate::db::model::ChatRevision;
+use crate::libs::Resp;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+// Rooms are open to every authenticated user, so anyone who can read the
+// message (its author included) may read how it changed
+pub async fn get_chat_revisions(
+    State(state): State<AppState>,
+    Path(chat_id): Path<i64>,
+) -> impl IntoResponse {
+    let connection = &state.db;
+
+    let exists = sqlx::query_scalar::<_, i64>("SELECT id FROM chats WHERE id = $1")
+        .bind(chat_id)
+        .fetch_optional(connection)
+        .await;
+
+    match exists {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Resp::error("Chat message not found"),
+            );
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error(err.to_string()),
+            );
+        }
+    }
+
+    let result = sqlx::query_as::<_, ChatRevision>(
+        "SELECT * FROM chat_revisions WHERE \"chatId\" = $1 ORDER BY created_at, id",
+    )
+    .bind(chat_id)
+    .fetch_all(connection)
+    .await;
+
+    match result {
+        Ok(revisions) => Resp::success("Revisions retrieved", Some(revisions)),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(err.to_string()),
+        ),
+    }
+}
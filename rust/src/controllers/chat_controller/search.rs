@@ -0,0 +1,160 @@
+use crate::AppState;
+use crate::db::model::Chat;
+use crate::libs::Resp;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 50;
+// Private-use characters ts_headline wraps matches in; they become <mark> tags only
+// after the message text around them has been escaped
+const MATCH_START: &str = "\u{E000}";
+const MATCH_STOP: &str = "\u{E001}";
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub user_id: Option<i64>,
+    pub room_id: Option<i64>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub has_attachment: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub chat: Chat,
+    pub rank: f32,
+    // HTML-escaped message text with matched terms wrapped in <mark>…</mark>
+    pub snippet: String,
+}
+
+// Escapes the snippet so a message's own markup shows as text, then turns the match
+// delimiters into <mark> tags
+fn render_snippet(headline: &str) -> String {
+    let mut snippet = String::with_capacity(headline.len());
+    for ch in headline.chars() {
+        match ch {
+            '&' => snippet.push_str("&amp;"),
+            '<' => snippet.push_str("&lt;"),
+            '>' => snippet.push_str("&gt;"),
+            '"' => snippet.push_str("&quot;"),
+            _ => snippet.push(ch),
+        }
+    }
+    snippet.replace(MATCH_START, "<mark>").replace(MATCH_STOP, "</mark>")
+}
+
+pub async fn search_chats(
+    State(state): State<AppState>,
+    Query(params): Query<SearchQuery>,
+) -> impl IntoResponse {
+    let terms = params.q.trim();
+    if terms.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Resp::error("Search query cannot be empty"),
+        );
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    // websearch_to_tsquery accepts free user input ("quoted phrases", -exclusions, or)
+    // without raising syntax errors
+    let query = r#"
+        SELECT
+            c.*,
+            json_build_object(
+                'nickname', u.nickname,
+                'userId', u.id,
+                'avatar', u.avatar,
+                'email', u.email
+            ) as user,
+            json_build_object(
+                'id', r.id,
+                'message', r.message,
+                'attachment', r.attachment
+            ) as reply,
+            CASE WHEN a.id IS NULL THEN NULL ELSE json_build_object(
+                'id', a.id, 'url', '/v1/attachments/' || a.id,
+                'thumbnailUrl', CASE WHEN a.thumbnail_key IS NULL THEN NULL ELSE '/v1/attachments/' || a.id || '/thumbnail' END,
+                'contentType', a.content_type, 'width', a.width,
+                'height', a.height, 'blurhash', a.blurhash
+            ) END as preview,
+            ts_rank(c.search_vector, q.query) as rank,
+            ts_headline('simple', translate(c.message, $9 || $10, ''), q.query,
+                'StartSel=' || $9 || ', StopSel=' || $10 || ', MaxFragments=2, MaxWords=20, MinWords=5') as snippet
+        FROM chats as c
+        CROSS JOIN websearch_to_tsquery('simple', $1) as q(query)
+        INNER JOIN users as u on c."userId" = u.id
+        LEFT JOIN chats as r on r.id = c."replyId"
+        LEFT JOIN attachments as a on a.id = c."attachmentId"
+        WHERE c.search_vector @@ q.query
+          AND c.deleted_at IS NULL
+          AND ($2::BIGINT IS NULL OR c."userId" = $2)
+          AND ($3::BIGINT IS NULL OR c."roomId" = $3)
+          AND ($4::TIMESTAMPTZ IS NULL OR c.created_at >= $4)
+          AND ($5::TIMESTAMPTZ IS NULL OR c.created_at < $5)
+          AND ($6::BOOLEAN IS NULL OR (c.attachment IS NOT NULL AND c.attachment <> '') = $6)
+        ORDER BY rank DESC, c.created_at DESC, c.id DESC
+        LIMIT $7 OFFSET $8
+    "#;
+
+    let results = sqlx::query_as::<_, SearchResult>(query)
+        .bind(terms)
+        .bind(params.user_id)
+        .bind(params.room_id)
+        .bind(params.from)
+        .bind(params.to)
+        .bind(params.has_attachment)
+        .bind(limit)
+        .bind(offset)
+        .bind(MATCH_START)
+        .bind(MATCH_STOP)
+        .fetch_all(&state.db)
+        .await;
+
+    match results {
+        Ok(mut rows) => {
+            for row in &mut rows {
+                row.snippet = render_snippet(&row.snippet);
+            }
+            Resp::success("search success", Some(rows))
+        }
+        Err(err) => {
+            eprintln!("Failed to search chats: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to search messages"),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_escapes_markup_from_the_message() {
+        let headline = format!("<script>alert(\"{}hi{}\")</script> & more", MATCH_START, MATCH_STOP);
+        assert_eq!(
+            render_snippet(&headline),
+            "&lt;script&gt;alert(&quot;<mark>hi</mark>&quot;)&lt;/script&gt; &amp; more"
+        );
+    }
+
+    #[test]
+    fn snippet_keeps_plain_text_and_marks() {
+        let headline = format!("say {}hello{} to {}everyone{}", MATCH_START, MATCH_STOP, MATCH_START, MATCH_STOP);
+        assert_eq!(render_snippet(&headline), "say <mark>hello</mark> to <mark>everyone</mark>");
+    }
+}
@@ -0,0 +1,93 @@
+use sqlx::{Pool, Postgres};
+
+use crate::db::model::{DirectConversation, DirectMessage};
+
+// Conversations are keyed on the ordered pair, so (a, b) and (b, a) share one row
+fn ordered_pair(user_id: i64, peer_id: i64) -> (i64, i64) {
+    if user_id < peer_id {
+        (user_id, peer_id)
+    } else {
+        (peer_id, user_id)
+    }
+}
+
+pub fn peer_of(conversation: &DirectConversation, user_id: i64) -> i64 {
+    if conversation.user_a_id == user_id {
+        conversation.user_b_id
+    } else {
+        conversation.user_a_id
+    }
+}
+
+// Get or create the conversation between two users
+pub async fn open_conversation(
+    user_id: i64,
+    peer_id: i64,
+    db: &Pool<Postgres>,
+) -> Result<DirectConversation, sqlx::Error> {
+    let (user_a_id, user_b_id) = ordered_pair(user_id, peer_id);
+
+    // The no-op update makes RETURNING yield the existing row on conflict
+    let query = r#"
+        INSERT INTO direct_conversations ("userAId", "userBId", created_at, updated_at)
+        VALUES ($1, $2, NOW(), NOW())
+        ON CONFLICT ("userAId", "userBId") DO UPDATE SET "userAId" = EXCLUDED."userAId"
+        RETURNING *
+    "#;
+
+    sqlx::query_as::<_, DirectConversation>(query)
+        .bind(user_a_id)
+        .bind(user_b_id)
+        .fetch_one(db)
+        .await
+}
+
+// Only the two participants may see a conversation
+pub async fn get_member_conversation(
+    conversation_id: i64,
+    user_id: i64,
+    db: &Pool<Postgres>,
+) -> Option<DirectConversation> {
+    let query = r#"
+        SELECT * FROM direct_conversations
+        WHERE id = $1 AND ("userAId" = $2 OR "userBId" = $2)
+    "#;
+
+    let result = sqlx::query_as::<_, DirectConversation>(query)
+        .bind(conversation_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await;
+
+    match result {
+        Ok(conversation) => conversation,
+        Err(e) => {
+            eprintln!("Error loading conversation: {}", e);
+            None
+        }
+    }
+}
+
+pub async fn save_direct_message(
+    conversation_id: i64,
+    user_id: i64,
+    message: &str,
+    db: &Pool<Postgres>,
+) -> Result<DirectMessage, sqlx::Error> {
+    // Bump the conversation so listings stay ordered by latest activity
+    let query = r#"
+        WITH bumped AS (
+            UPDATE direct_conversations SET updated_at = NOW() WHERE id = $1
+        )
+        INSERT INTO direct_messages ("conversationId", "userId", message, created_at, updated_at)
+        VALUES ($1, $2, $3, NOW(), NOW())
+        RETURNING *
+    "#;
+
+    sqlx::query_as::<_, DirectMessage>(query)
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(message)
+        .fetch_one(db)
+        .await
+}
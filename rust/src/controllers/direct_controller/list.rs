@@ -0,0 +1,65 @@
+use crate::AppState;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::Json;
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct ConversationSummary {
+    pub id: i64,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub user: Json<serde_json::Value>,
+    pub last_message: Option<Json<serde_json::Value>>,
+}
+
+pub async fn list_direct_conversations(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+) -> impl IntoResponse {
+    let query = r#"
+        SELECT
+            d.id,
+            d.updated_at,
+            d.created_at,
+            json_build_object(
+                'nickname', u.nickname,
+                'userId', u.id,
+                'avatar', u.avatar
+            ) as user,
+            (
+                SELECT json_build_object(
+                    'id', m.id,
+                    'message', m.message,
+                    'userId', m."userId",
+                    'created_at', m.created_at
+                )
+                FROM direct_messages as m
+                WHERE m."conversationId" = d.id
+                ORDER BY m.created_at DESC, m.id DESC
+                LIMIT 1
+            ) as last_message
+        FROM direct_conversations as d
+        INNER JOIN users as u
+            on u.id = CASE WHEN d."userAId" = $1 THEN d."userBId" ELSE d."userAId" END
+        WHERE d."userAId" = $1 OR d."userBId" = $1
+        ORDER BY d.updated_at DESC
+    "#;
+
+    let result = sqlx::query_as::<_, ConversationSummary>(query)
+        .bind(user_id)
+        .fetch_all(&state.db)
+        .await;
+
+    match result {
+        Ok(conversations) => Resp::success("Conversations retrieved", Some(conversations)),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(err.to_string()),
+        ),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::AppState;
+use crate::controllers::direct_controller::conversation::get_member_conversation;
+use crate::db::model::DirectMessage;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+pub async fn get_direct_messages(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Path(conversation_id): Path<i64>,
+) -> impl IntoResponse {
+    let connection = &state.db;
+
+    if get_member_conversation(conversation_id, user_id, connection)
+        .await
+        .is_none()
+    {
+        return (
+            StatusCode::NOT_FOUND,
+            Resp::error("Conversation not found or access denied"),
+        );
+    }
+
+    let query = r#"
+        SELECT * FROM direct_messages
+        WHERE "conversationId" = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT 100
+    "#;
+
+    let result = sqlx::query_as::<_, DirectMessage>(query)
+        .bind(conversation_id)
+        .fetch_all(connection)
+        .await;
+
+    match result {
+        Ok(messages) => Resp::success("Messages retrieved", Some(messages)),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(err.to_string()),
+        ),
+    }
+}
@@ -0,0 +1,4 @@
+pub mod conversation;
+pub mod list;
+pub mod messages;
+pub mod open;
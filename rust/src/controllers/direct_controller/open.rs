@@ -0,0 +1,40 @@
+use crate::AppState;
+use crate::controllers::direct_controller::conversation::open_conversation;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct OpenConversationRequest {
+    pub user_id: i64,
+}
+
+pub async fn open_direct_conversation(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Json(params): Json<OpenConversationRequest>,
+) -> impl IntoResponse {
+    if params.user_id == user_id {
+        return (
+            StatusCode::BAD_REQUEST,
+            Resp::error("Cannot open a conversation with yourself"),
+        );
+    }
+
+    match get_user_by_auth_id(params.user_id, state.db.clone()).await {
+        Some(peer) if peer.deleted_at.is_none() => {}
+        _ => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    }
+
+    match open_conversation(user_id, params.user_id, &state.db).await {
+        Ok(conversation) => Resp::success("Conversation opened", Some(conversation)),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to open conversation: {}", err)),
+        ),
+    }
+}
@@ -0,0 +1,159 @@
+use crate::AppState;
+use crate::controllers::email_controller::verification::{resend_wait_secs, send_changed_email_verification};
+use crate::controllers::lockout_controller::reauth::{ReauthError, confirm_identity};
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::mailer::{Email, send_in_background};
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+use std::net::SocketAddr;
+
+#[derive(Deserialize)]
+pub struct ChangeEmail {
+    pub email: String,
+    pub password: Option<String>,
+    // TOTP or recovery code, when two-factor authentication is on
+    pub code: Option<String>,
+}
+
+// Switches the account to a new address, which stays unverified until its link is opened.
+// The old address is told, in case it wasn't the owner asking.
+pub async fn change_email(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<ChangeEmail>,
+) -> impl IntoResponse {
+    let email = params.email.trim().to_lowercase();
+    if email.len() > 255 || !email.contains('@') {
+        return (StatusCode::BAD_REQUEST, Resp::error("A valid email address is required"));
+    }
+
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) => user,
+        None => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    };
+    if user.email.as_deref().is_some_and(|current| current.eq_ignore_ascii_case(&email)) {
+        return (StatusCode::BAD_REQUEST, Resp::error("That is already your email address"));
+    }
+
+    let client = ClientInfo::from_request(&headers, peer);
+    let (password, code) = (params.password.as_deref(), params.code.as_deref());
+    match confirm_identity(&state.db, &state.mailer, &user, password, code, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(e)) => {
+            eprintln!("Failed to confirm identity: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to change email"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    // Each change mails a link, so it shares the resend limits
+    match resend_wait_secs(&state.db, user.id).await {
+        Ok(None) => {}
+        Ok(Some(secs)) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                Resp::error(format!("Try again in {} seconds", secs)),
+            );
+        }
+        Err(e) => {
+            eprintln!("Failed to check verification mails: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to change email"),
+            );
+        }
+    }
+
+    match update_email(&state.db, user.id, user.email.as_deref(), &email).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::CONFLICT, Resp::error("Email is already in use")),
+        Err(e) => {
+            eprintln!("Failed to change email: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to change email"),
+            );
+        }
+    }
+
+    if let Err(e) = send_changed_email_verification(&state.db, &state.mailer, user.id, &email).await {
+        eprintln!("Failed to send verification email: {}", e);
+    }
+
+    if let Some(previous) = user.email {
+        send_in_background(
+            state.mailer.clone(),
+            Email {
+                to: previous,
+                subject: "Your email address was changed".to_string(),
+                body: format!(
+                    "The email address of your account was just changed to {}.\n\n\
+                     If it wasn't you, contact support right away.",
+                    email
+                ),
+            },
+        );
+    }
+
+    Resp::success("Email changed, check your inbox to verify it", None::<()>)
+}
+
+// False when another account already uses the address
+async fn update_email(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    previous: Option<&str>,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let taken = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM users WHERE LOWER(email) = LOWER($1) AND id <> $2)",
+    )
+    .bind(email)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if taken {
+        return Ok(false);
+    }
+
+    // Tokens mailed to the old address stop working once it no longer matches. The unique
+    // index catches a concurrent change to the same address that got past the check.
+    let updated = sqlx::query("UPDATE users SET email = $1, email_verified_at = NULL, updated_at = NOW() WHERE id = $2")
+        .bind(email)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await;
+    match updated {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => return Ok(false),
+        Err(err) => return Err(err),
+    }
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.email_changed",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({ "from": previous, "to": email }),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
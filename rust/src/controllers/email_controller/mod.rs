@@ -0,0 +1,4 @@
+pub mod change;
+pub mod resend;
+pub mod verification;
+pub mod verify;
@@ -0,0 +1,63 @@
+use crate::AppState;
+use crate::controllers::email_controller::verification::{resend_wait_secs, send_verification_email};
+use crate::libs::Resp;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ResendVerification {
+    pub email: String,
+}
+
+// Same answer for unknown, already verified and throttled addresses, so it can't be
+// used to probe for accounts. Public, since the login policy may lock unverified users out.
+const RESEND_RESPONSE: &str = "If that email is awaiting verification, a new link has been sent";
+
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(params): Json<ResendVerification>,
+) -> impl IntoResponse {
+    let email = params.email.trim();
+    if email.is_empty() {
+        return (StatusCode::BAD_REQUEST, Resp::error("Email is required"));
+    }
+
+    // Matched like everywhere else; the mail goes to the address as stored, since the
+    // verification token is tied to it
+    let account = sqlx::query_as::<_, (i64, String)>(
+        "SELECT id, email FROM users WHERE LOWER(email) = LOWER($1) AND email_verified_at IS NULL",
+    )
+    .bind(email)
+    .fetch_optional(&state.db)
+    .await;
+
+    let (user_id, email) = match account {
+        Ok(Some(account)) => account,
+        Ok(None) => return Resp::success(RESEND_RESPONSE, None::<()>),
+        Err(err) => {
+            eprintln!("Error loading user: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to process request"),
+            );
+        }
+    };
+
+    let sent = match resend_wait_secs(&state.db, user_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => send_verification_email(&state.db, &state.mailer, user_id, &email).await,
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = sent {
+        eprintln!("Failed to resend verification email: {}", err);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error("Failed to process request"),
+        );
+    }
+
+    Resp::success(RESEND_RESPONSE, None::<()>)
+}
@@ -0,0 +1,205 @@
+use std::env;
+
+use crate::libs::crypto::{generate_opaque_token, hash_token};
+use crate::mailer::{Email, SharedMailer, send_in_background};
+use sqlx::{Pool, Postgres};
+
+pub const DEFAULT_VERIFICATION_TTL_HOURS: i32 = 48;
+pub const DEFAULT_RESEND_INTERVAL_SECS: i64 = 60;
+pub const MAX_SENDS_PER_DAY: i64 = 10;
+
+// What an unverified account may not do, from EMAIL_VERIFICATION_POLICY
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationPolicy {
+    // Unverified accounts work normally (default)
+    Off,
+    // They can sign in and read, but not post messages
+    Chat,
+    // They can't sign in at all
+    Login,
+}
+
+impl VerificationPolicy {
+    pub fn from_env() -> Self {
+        match env::var("EMAIL_VERIFICATION_POLICY").as_deref() {
+            Ok("chat") => Self::Chat,
+            Ok("login") => Self::Login,
+            _ => Self::Off,
+        }
+    }
+
+    pub fn blocks_login(self) -> bool {
+        self == Self::Login
+    }
+
+    // Login also covers sessions that were started before the policy was switched on
+    pub fn blocks_chat(self) -> bool {
+        matches!(self, Self::Chat | Self::Login)
+    }
+}
+
+pub async fn is_email_verified(user_id: i64, db: &Pool<Postgres>) -> bool {
+    let result = sqlx::query_scalar::<_, bool>("SELECT email_verified_at IS NOT NULL FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(db)
+        .await;
+
+    match result {
+        Ok(verified) => verified.unwrap_or(false),
+        Err(e) => {
+            eprintln!("Error checking email verification: {}", e);
+            false
+        }
+    }
+}
+
+// Whether the policy lets this user send chat or direct messages
+pub async fn may_post_messages(user_id: i64, db: &Pool<Postgres>) -> bool {
+    !VerificationPolicy::from_env().blocks_chat() || is_email_verified(user_id, db).await
+}
+
+fn verification_ttl_hours() -> i32 {
+    env::var("EMAIL_VERIFICATION_TTL_HOURS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_VERIFICATION_TTL_HOURS)
+}
+
+fn resend_interval_secs() -> i64 {
+    env::var("EMAIL_VERIFICATION_RESEND_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_RESEND_INTERVAL_SECS)
+}
+
+// Link mailed to the user; the frontend posts the token back to /v1/email/verify
+pub fn verification_link(token: &str) -> String {
+    let base = env::var("EMAIL_VERIFICATION_URL").unwrap_or_else(|_| "http://localhost:8081/verify-email".to_string());
+    format!("{}?token={}", base, token)
+}
+
+// Seconds until another mail may be sent to this user, or None when one may go out now.
+// Spaces mails out by the resend interval and caps them per day.
+pub async fn resend_wait_secs(db: &Pool<Postgres>, user_id: i64) -> Result<Option<i64>, sqlx::Error> {
+    let (last_sent, sent_today) = sqlx::query_as::<_, (Option<i64>, i64)>(
+        r#"
+        SELECT EXTRACT(EPOCH FROM NOW() - MAX(created_at))::BIGINT,
+               COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '1 day')
+        FROM email_verification_tokens WHERE "userId" = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+
+    if sent_today >= MAX_SENDS_PER_DAY {
+        return Ok(Some(24 * 60 * 60));
+    }
+    Ok(last_sent
+        .map(|elapsed| resend_interval_secs() - elapsed)
+        .filter(|wait| *wait > 0))
+}
+
+// Stores a new token for the address and returns it. Earlier tokens stay valid until
+// they expire; any one of them verifies the address.
+async fn issue_verification_token(db: &Pool<Postgres>, user_id: i64, email: &str) -> Result<String, sqlx::Error> {
+    let token = generate_opaque_token();
+    sqlx::query(
+        r#"
+        INSERT INTO email_verification_tokens ("userId", email, token_hash, expires_at, created_at)
+        VALUES ($1, $2, $3, NOW() + make_interval(hours => $4), NOW())
+        "#,
+    )
+    .bind(user_id)
+    .bind(email)
+    .bind(hash_token(&token))
+    .bind(verification_ttl_hours())
+    .execute(db)
+    .await?;
+    Ok(token)
+}
+
+// Issues a token for the user's current address and mails the link
+pub async fn send_verification_email(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    user_id: i64,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    let token = issue_verification_token(db, user_id, email).await?;
+    send_in_background(
+        mailer.clone(),
+        Email {
+            to: email.to_string(),
+            subject: "Confirm your email address".to_string(),
+            body: format!(
+                "Welcome to Cheetah!\n\nOpen this link to confirm your email address:\n{}\n\n\
+                 If you didn't create an account, ignore this mail.",
+                verification_link(&token)
+            ),
+        },
+    );
+    Ok(())
+}
+
+// Same as send_verification_email, worded for an address that replaced the old one
+pub async fn send_changed_email_verification(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    user_id: i64,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    let token = issue_verification_token(db, user_id, email).await?;
+    send_in_background(
+        mailer.clone(),
+        Email {
+            to: email.to_string(),
+            subject: "Confirm your new email address".to_string(),
+            body: format!(
+                "Your Cheetah account now uses this email address.\n\n\
+                 Open this link to confirm it:\n{}\n\n\
+                 If you didn't ask for this, ignore this mail.",
+                verification_link(&token)
+            ),
+        },
+    );
+    Ok(())
+}
+
+// Marks the address verified. None when the token is unknown, expired, used,
+// or was sent to an address the account no longer has.
+pub async fn verify_email_token(db: &Pool<Postgres>, token: &str) -> Result<Option<i64>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let user_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE email_verification_tokens t SET used_at = NOW()
+        FROM users u
+        WHERE t.token_hash = $1 AND t.used_at IS NULL AND t.expires_at > NOW()
+          AND u.id = t."userId" AND u.email = t.email
+        RETURNING t."userId"
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(None),
+    };
+
+    sqlx::query("UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(r#"UPDATE email_verification_tokens SET used_at = NOW() WHERE "userId" = $1 AND used_at IS NULL"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(Some(user_id))
+}
@@ -0,0 +1,36 @@
+use crate::AppState;
+use crate::controllers::email_controller::verification::verify_email_token;
+use crate::libs::Resp;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(params): Json<VerifyEmail>,
+) -> impl IntoResponse {
+    if params.token.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, Resp::error("Token is required"));
+    }
+
+    match verify_email_token(&state.db, params.token.trim()).await {
+        Ok(Some(_)) => Resp::success("Email address verified", None::<()>),
+        Ok(None) => (
+            StatusCode::BAD_REQUEST,
+            Resp::error("Invalid or expired verification link"),
+        ),
+        Err(err) => {
+            eprintln!("Failed to verify email: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to verify email"),
+            )
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use crate::libs::Resp;
+use crate::libs::jwt::key_set;
+use axum::Json;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+
+// Public verification keys; served bare (not wrapped in Resp) so standard JWT libraries can consume it
+pub async fn get_jwks() -> impl IntoResponse {
+    match key_set() {
+        Ok(keys) => (
+            [(header::CACHE_CONTROL, "public, max-age=300")],
+            Json(keys.jwks()),
+        )
+            .into_response(),
+        Err(err) => {
+            eprintln!("Failed to load JWT keys: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::<()>::error("Signing keys are unavailable"),
+            )
+                .into_response()
+        }
+    }
+}
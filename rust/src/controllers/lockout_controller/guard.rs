@@ -0,0 +1,281 @@
+use std::env;
+use std::sync::OnceLock;
+
+use crate::controllers::lockout_controller::unlock_token::{issue_unlock_token, unlock_link};
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::crypto::{hash_password, hash_token};
+use crate::mailer::{Email, SharedMailer, send_in_background};
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use sqlx::{Pool, Postgres};
+
+pub const DEFAULT_LOCKOUT_THRESHOLD: i32 = 10;
+pub const DEFAULT_LOCKOUT_MINUTES: i32 = 15;
+
+// Failures that cost nothing; after them each one doubles the wait. An address can
+// be shared by many people (NAT, office), so it gets more.
+const FREE_ACCOUNT_FAILURES: i32 = 3;
+const FREE_IP_FAILURES: i32 = 10;
+const MAX_BACKOFF_SECS: i64 = 15 * 60;
+// A key's failures are forgotten after this long without a new one
+const FAILURE_WINDOW_MINUTES: i32 = 60;
+
+// Failures in a row that lock an account, and for how long
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    pub threshold: i32,
+    pub lockout_minutes: i32,
+}
+
+impl LockoutPolicy {
+    pub fn from_env() -> Self {
+        let var = |key: &str, default: i32| {
+            env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|value: &i32| *value > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            threshold: var("LOGIN_LOCKOUT_THRESHOLD", DEFAULT_LOCKOUT_THRESHOLD),
+            lockout_minutes: var("LOGIN_LOCKOUT_MINUTES", DEFAULT_LOCKOUT_MINUTES),
+        }
+    }
+}
+
+// Who a sign-in attempt was for. Identifiers that match no account are tracked too,
+// so throttling and lockout look the same whether or not the account exists.
+pub enum LoginKey {
+    User(i64),
+    Unknown(String),
+}
+
+impl LoginKey {
+    // Kept as the SHA-256 of the normalized identifier, so an identifier of any length
+    // fits login_failures.key and is throttled like a real account
+    pub fn unknown(identifier: &str) -> Self {
+        LoginKey::Unknown(hash_token(&identifier.trim().to_lowercase()))
+    }
+
+    pub fn key(&self) -> String {
+        match self {
+            LoginKey::User(user_id) => format!("user:{}", user_id),
+            LoginKey::Unknown(digest) => format!("login:{}", digest),
+        }
+    }
+}
+
+fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+// Wait after the n-th failure in a row: 1s, 2s, 4s, ... once the free ones are used up
+pub fn backoff_secs(failures: i32, free: i32) -> i64 {
+    if failures <= free {
+        return 0;
+    }
+    let doublings = (failures - free - 1).min(20) as u32;
+    (1i64 << doublings).min(MAX_BACKOFF_SECS)
+}
+
+// Seconds until the account and address may try again, None when they may now. The address
+// comes from ClientInfo, which only believes X-Forwarded-For from trusted proxies; a
+// spoofable one would let anyone dodge the per-address count or lock out someone else's.
+pub async fn login_retry_after(
+    db: &Pool<Postgres>,
+    account: &LoginKey,
+    client: &ClientInfo,
+) -> Result<Option<i64>, sqlx::Error> {
+    let keys: Vec<String> = std::iter::once(account.key())
+        .chain(client.ip.as_deref().map(ip_key))
+        .collect();
+    sqlx::query_scalar::<_, Option<i64>>(
+        r#"
+        SELECT CEIL(EXTRACT(EPOCH FROM MAX(blocked_until) - NOW()))::BIGINT
+        FROM login_failures
+        WHERE key = ANY($1) AND blocked_until > NOW()
+        "#,
+    )
+    .bind(&keys)
+    .fetch_one(db)
+    .await
+}
+
+// Counts one more failure for the key and returns the new run length
+async fn bump_failures(db: &Pool<Postgres>, key: &str) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO login_failures (key, failures, last_failure_at)
+        VALUES ($1, 1, NOW())
+        ON CONFLICT (key) DO UPDATE SET
+            failures = CASE
+                WHEN login_failures.last_failure_at < NOW() - make_interval(mins => $2) THEN 1
+                ELSE login_failures.failures + 1
+            END,
+            last_failure_at = NOW()
+        RETURNING failures
+        "#,
+    )
+    .bind(key)
+    .bind(FAILURE_WINDOW_MINUTES)
+    .fetch_one(db)
+    .await
+}
+
+async fn block_for(db: &Pool<Postgres>, key: &str, secs: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE login_failures SET blocked_until = NOW() + make_interval(secs => $2) WHERE key = $1")
+        .bind(key)
+        .bind(secs as f64)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Records a failed password or second-factor check against the account and the address.
+// Reaching the threshold locks the account: the lockout is audited and the owner gets an unlock link.
+pub async fn record_failed_login(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    account: &LoginKey,
+    client: &ClientInfo,
+) -> Result<(), sqlx::Error> {
+    let ip = client.ip.as_deref();
+    sqlx::query(
+        r#"
+        DELETE FROM login_failures
+        WHERE last_failure_at < NOW() - make_interval(mins => $1)
+          AND (blocked_until IS NULL OR blocked_until < NOW())
+        "#,
+    )
+    .bind(FAILURE_WINDOW_MINUTES)
+    .execute(db)
+    .await?;
+
+    if let Some(ip) = ip {
+        let key = ip_key(ip);
+        let failures = bump_failures(db, &key).await?;
+        let wait = backoff_secs(failures, FREE_IP_FAILURES);
+        if wait > 0 {
+            block_for(db, &key, wait).await?;
+        }
+    }
+
+    let policy = LockoutPolicy::from_env();
+    let key = account.key();
+    let failures = bump_failures(db, &key).await?;
+    if failures < policy.threshold {
+        let wait = backoff_secs(failures, FREE_ACCOUNT_FAILURES);
+        if wait > 0 {
+            block_for(db, &key, wait).await?;
+        }
+        return Ok(());
+    }
+
+    block_for(db, &key, i64::from(policy.lockout_minutes) * 60).await?;
+    let user_id = match account {
+        LoginKey::User(user_id) => Some(*user_id),
+        LoginKey::Unknown(_) => None,
+    };
+    record_audit(
+        db,
+        AuditEntry {
+            actor_id: None,
+            action: "user.locked_out",
+            target_type: "user",
+            target_id: user_id,
+            metadata: serde_json::json!({ "key": key, "failures": failures, "ip": ip, "minutes": policy.lockout_minutes }),
+        },
+    )
+    .await?;
+
+    if let Some(user_id) = user_id {
+        let email = sqlx::query_scalar::<_, Option<String>>("SELECT email FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(db)
+            .await?
+            .flatten();
+        if let Some(email) = email {
+            let token = issue_unlock_token(db, user_id).await?;
+            send_in_background(
+                mailer.clone(),
+                Email {
+                    to: email,
+                    subject: "Your account was locked".to_string(),
+                    body: format!(
+                        "There were {} failed attempts to sign in to your account, so it is locked for {} minutes.\n\n\
+                         If that was you, open this link to unlock it now:\n{}\n\n\
+                         If it wasn't, consider changing your password.",
+                        failures,
+                        policy.lockout_minutes,
+                        unlock_link(&token)
+                    ),
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// A successful sign-in, reset or unlock starts the account's count over; the address keeps its own
+pub async fn clear_failed_logins(db: &Pool<Postgres>, account: &LoginKey) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM login_failures WHERE key = $1")
+        .bind(account.key())
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Spends an Argon2 verification when there is no hash to check, so unknown
+// accounts take as long to reject as wrong passwords
+pub fn verify_password_or_dummy(hash: Option<&str>, password: &str) -> bool {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let dummy = DUMMY_HASH.get_or_init(|| hash_password("not-a-real-password").unwrap_or_default());
+
+    let (hash, real) = match hash {
+        Some(hash) => (hash, true),
+        None => (dummy.as_str(), false),
+    };
+    let valid = PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false);
+    real && valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_starts_after_free_failures_and_doubles() {
+        assert_eq!(backoff_secs(1, 3), 0);
+        assert_eq!(backoff_secs(3, 3), 0);
+        assert_eq!(backoff_secs(4, 3), 1);
+        assert_eq!(backoff_secs(5, 3), 2);
+        assert_eq!(backoff_secs(8, 3), 16);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(backoff_secs(50, 3), MAX_BACKOFF_SECS);
+        assert_eq!(backoff_secs(i32::MAX, 3), MAX_BACKOFF_SECS);
+    }
+
+    #[test]
+    fn unknown_identifiers_are_normalized() {
+        assert_eq!(
+            LoginKey::unknown(" Alice@Example.com ").key(),
+            LoginKey::unknown("alice@example.com").key()
+        );
+        assert_ne!(LoginKey::unknown("alice").key(), LoginKey::unknown("bob").key());
+        assert_eq!(LoginKey::User(7).key(), "user:7");
+    }
+
+    #[test]
+    fn unknown_identifier_keys_have_a_fixed_length() {
+        let short = LoginKey::unknown("a").key();
+        let long = LoginKey::unknown(&"a".repeat(10_000)).key();
+        assert_eq!(short.len(), "login:".len() + 64);
+        assert_eq!(long.len(), short.len());
+    }
+}
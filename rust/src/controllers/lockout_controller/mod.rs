@@ -0,0 +1,4 @@
+pub mod guard;
+pub mod reauth;
+pub mod unlock;
+pub mod unlock_token;
@@ -0,0 +1,273 @@
+use crate::controllers::lockout_controller::guard::{
+    LoginKey, login_retry_after, record_failed_login, verify_password_or_dummy,
+};
+use crate::controllers::mfa_controller::enrollment_token::{
+    enrollment_wait_secs, is_enrollment_token_valid, issue_enrollment_token,
+};
+use crate::controllers::mfa_controller::totp::{is_mfa_enabled, verify_second_factor};
+use crate::controllers::passkey_controller::reauth::{PasskeyProof, verify_passkey_proof};
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::db::model::User;
+use crate::mailer::{Email, SharedMailer, send_in_background};
+use axum::http::StatusCode;
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReauthError {
+    #[error("Too many failed attempts, try again in {0} seconds")]
+    Blocked(i64),
+    #[error("Current password is incorrect")]
+    WrongPassword,
+    #[error("Invalid code")]
+    WrongCode,
+    #[error("A code from your authenticator app or a recovery code is required")]
+    CodeRequired,
+    #[error("Turn on two-factor authentication to make this change")]
+    NoSecondFactor,
+    #[error("Current password is required")]
+    PasswordRequired,
+    #[error("Confirm with one of your passkeys to make this change")]
+    PasskeyRequired,
+    #[error("Passkey rejected")]
+    PasskeyRejected,
+    #[error("A confirmation code was mailed to you, send it back as email_token")]
+    EmailTokenSent,
+    #[error("Invalid or expired confirmation code")]
+    WrongEmailToken,
+    #[error("Two-factor authentication is already enabled")]
+    MfaEnabled,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl ReauthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Blocked(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::WrongPassword => StatusCode::FORBIDDEN,
+            Self::WrongCode | Self::WrongEmailToken => StatusCode::BAD_REQUEST,
+            Self::CodeRequired
+            | Self::NoSecondFactor
+            | Self::PasswordRequired
+            | Self::PasskeyRequired
+            | Self::EmailTokenSent => StatusCode::FORBIDDEN,
+            Self::PasskeyRejected => StatusCode::UNAUTHORIZED,
+            Self::MfaEnabled => StatusCode::CONFLICT,
+            Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+// The current password; wrong guesses count towards the same lockout as /login
+async fn confirm_password(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    user_id: i64,
+    hash: &str,
+    password: Option<&str>,
+    client: &ClientInfo,
+) -> Result<(), ReauthError> {
+    let account = LoginKey::User(user_id);
+    if let Some(secs) = login_retry_after(db, &account, client).await? {
+        return Err(ReauthError::Blocked(secs));
+    }
+
+    if verify_password_or_dummy(Some(hash), password.unwrap_or_default()) {
+        return Ok(());
+    }
+    record_failed_login(db, mailer, &account, client).await?;
+    Err(ReauthError::WrongPassword)
+}
+
+// The same for changes to the second factor itself: a current TOTP or recovery code,
+// guessed under the lockout that /login/mfa uses
+pub async fn confirm_second_factor(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    user_id: i64,
+    code: &str,
+    client: &ClientInfo,
+) -> Result<(), ReauthError> {
+    let account = LoginKey::User(user_id);
+    if let Some(secs) = login_retry_after(db, &account, client).await? {
+        return Err(ReauthError::Blocked(secs));
+    }
+
+    if verify_second_factor(db, user_id, code).await?.is_some() {
+        return Ok(());
+    }
+    record_failed_login(db, mailer, &account, client).await?;
+    Err(ReauthError::WrongCode)
+}
+
+// Asked before account changes, so a stolen access token alone can't take the account
+// over: the current password, and with two-factor authentication on a current code as
+// well. Accounts that only sign in through a provider or passkey have no password, so
+// for them the code is the only proof and without 2FA the change is refused.
+pub async fn confirm_identity(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    user: &User,
+    password: Option<&str>,
+    code: Option<&str>,
+    client: &ClientInfo,
+) -> Result<(), ReauthError> {
+    let mfa_enabled = is_mfa_enabled(db, user.id).await?;
+    match user.password.as_deref() {
+        Some(hash) => confirm_password(db, mailer, user.id, hash, password, client).await?,
+        None if !mfa_enabled => return Err(ReauthError::NoSecondFactor),
+        None => {}
+    }
+    if !mfa_enabled {
+        return Ok(());
+    }
+    match code {
+        Some(code) => confirm_second_factor(db, mailer, user.id, code, client).await,
+        None => Err(ReauthError::CodeRequired),
+    }
+}
+
+// Proof asked before turning on TOTP. Without it a stolen access token could enroll the
+// attacker's authenticator, and on a passwordless account that code alone would then
+// pass confirm_identity.
+#[derive(Deserialize)]
+pub struct EnrollmentProof {
+    pub password: Option<String>,
+    // For accounts without a password
+    pub passkey: Option<PasskeyProof>,
+    pub email_token: Option<String>,
+}
+
+pub enum EnrollmentCheck<'a> {
+    Password,
+    Passkey(&'a PasskeyProof),
+    EmailToken(&'a str),
+    // Nothing to check yet: mail a code to the account's address
+    SendEmailToken,
+}
+
+// Which proof this account owes for an enrollment, given what was sent
+pub fn enrollment_check(
+    has_password: bool,
+    has_email: bool,
+    proof: &EnrollmentProof,
+) -> Result<EnrollmentCheck<'_>, ReauthError> {
+    if has_password {
+        if proof.password.as_deref().is_none_or(str::is_empty) {
+            return Err(ReauthError::PasswordRequired);
+        }
+        return Ok(EnrollmentCheck::Password);
+    }
+    if let Some(passkey) = &proof.passkey {
+        return Ok(EnrollmentCheck::Passkey(passkey));
+    }
+    match proof.email_token.as_deref().map(str::trim).filter(|token| !token.is_empty()) {
+        Some(token) => Ok(EnrollmentCheck::EmailToken(token)),
+        None if has_email => Ok(EnrollmentCheck::SendEmailToken),
+        None => Err(ReauthError::PasskeyRequired),
+    }
+}
+
+// Asked on both enrollment steps. Accounts without a password show a passkey or the
+// code mailed to their address; failures count towards the login lockout.
+pub async fn confirm_enrollment_identity(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    user: &User,
+    proof: &EnrollmentProof,
+    client: &ClientInfo,
+) -> Result<(), ReauthError> {
+    if is_mfa_enabled(db, user.id).await? {
+        return Err(ReauthError::MfaEnabled);
+    }
+    let check = enrollment_check(user.password.is_some(), user.email.is_some(), proof)?;
+
+    let account = LoginKey::User(user.id);
+    if let Some(secs) = login_retry_after(db, &account, client).await? {
+        return Err(ReauthError::Blocked(secs));
+    }
+
+    let failure = match check {
+        EnrollmentCheck::Password => {
+            return confirm_identity(db, mailer, user, proof.password.as_deref(), None, client).await;
+        }
+        EnrollmentCheck::Passkey(passkey) => {
+            if verify_passkey_proof(db, user.id, passkey).await? {
+                return Ok(());
+            }
+            ReauthError::PasskeyRejected
+        }
+        EnrollmentCheck::EmailToken(token) => {
+            if is_enrollment_token_valid(db, user.id, token).await? {
+                return Ok(());
+            }
+            ReauthError::WrongEmailToken
+        }
+        EnrollmentCheck::SendEmailToken => {
+            // A throttled request gets the same answer, it just doesn't send another mail
+            if let (Some(email), None) = (&user.email, enrollment_wait_secs(db, user.id).await?) {
+                let token = issue_enrollment_token(db, user.id).await?;
+                send_in_background(
+                    mailer.clone(),
+                    Email {
+                        to: email.clone(),
+                        subject: "Confirm two-factor authentication".to_string(),
+                        body: format!(
+                            "Someone asked to turn on two-factor authentication for your account.\n\n\
+                             If it was you, enter this confirmation code in the app:\n{}\n\n\
+                             If it wasn't, ignore this mail and sign out your other sessions.",
+                            token
+                        ),
+                    },
+                );
+            }
+            return Err(ReauthError::EmailTokenSent);
+        }
+    };
+    record_failed_login(db, mailer, &account, client).await?;
+    Err(failure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof(password: Option<&str>, email_token: Option<&str>) -> EnrollmentProof {
+        EnrollmentProof {
+            password: password.map(str::to_string),
+            passkey: None,
+            email_token: email_token.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn enrolling_without_reauth_is_rejected() {
+        let empty = proof(None, None);
+        assert!(matches!(enrollment_check(true, true, &empty), Err(ReauthError::PasswordRequired)));
+        assert!(matches!(
+            enrollment_check(true, true, &proof(Some(""), None)),
+            Err(ReauthError::PasswordRequired)
+        ));
+        assert!(matches!(enrollment_check(false, false, &empty), Err(ReauthError::PasskeyRequired)));
+        // Passwordless accounts only get a mailed code, not the enrollment
+        assert!(matches!(enrollment_check(false, true, &empty), Ok(EnrollmentCheck::SendEmailToken)));
+    }
+
+    #[test]
+    fn enrollment_takes_the_proof_the_account_has() {
+        assert!(matches!(
+            enrollment_check(true, true, &proof(Some("secret"), None)),
+            Ok(EnrollmentCheck::Password)
+        ));
+        assert!(matches!(
+            enrollment_check(false, true, &proof(None, Some(" token "))),
+            Ok(EnrollmentCheck::EmailToken("token"))
+        ));
+        // A mailed code doesn't stand in for the password
+        assert!(matches!(
+            enrollment_check(true, true, &proof(None, Some("token"))),
+            Err(ReauthError::PasswordRequired)
+        ));
+    }
+}
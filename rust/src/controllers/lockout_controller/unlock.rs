@@ -0,0 +1,37 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::unlock_token::unlock_with_token;
+use crate::libs::Resp;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct UnlockAccount {
+    pub token: String,
+}
+
+// Lifts a lockout early with the link mailed when it happened
+pub async fn unlock_account(
+    State(state): State<AppState>,
+    Json(params): Json<UnlockAccount>,
+) -> impl IntoResponse {
+    if params.token.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, Resp::error("Token is required"));
+    }
+
+    match unlock_with_token(&state.db, params.token.trim()).await {
+        Ok(Some(_)) => Resp::success("Account unlocked, you can sign in again", None::<()>),
+        Ok(None) => (
+            StatusCode::BAD_REQUEST,
+            Resp::error("Invalid or expired unlock link"),
+        ),
+        Err(err) => {
+            eprintln!("Failed to unlock account: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to unlock account"),
+            )
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::env;
+
+use crate::controllers::lockout_controller::guard::LoginKey;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::crypto::{generate_opaque_token, hash_token};
+use sqlx::{Pool, Postgres};
+
+// Outlives any lockout, so the link still works after the lock has lapsed and come back
+const UNLOCK_TTL_HOURS: i32 = 24;
+
+// Link mailed to the user; the frontend posts the token back to /v1/unlock
+pub fn unlock_link(token: &str) -> String {
+    let base = env::var("ACCOUNT_UNLOCK_URL").unwrap_or_else(|_| "http://localhost:8081/unlock-account".to_string());
+    format!("{}?token={}", base, token)
+}
+
+// Issues a new unlock token and spends any earlier one, so only the latest mail works
+pub async fn issue_unlock_token(db: &Pool<Postgres>, user_id: i64) -> Result<String, sqlx::Error> {
+    let token = generate_opaque_token();
+    let mut tx = db.begin().await?;
+
+    sqlx::query(r#"UPDATE account_unlock_tokens SET used_at = NOW() WHERE "userId" = $1 AND used_at IS NULL"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO account_unlock_tokens ("userId", token_hash, expires_at, created_at)
+        VALUES ($1, $2, NOW() + make_interval(hours => $3), NOW())
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(UNLOCK_TTL_HOURS)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(token)
+}
+
+// Spends the token and lifts the account's lock. None when the token is unknown, expired or used.
+pub async fn unlock_with_token(db: &Pool<Postgres>, token: &str) -> Result<Option<i64>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let user_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE account_unlock_tokens SET used_at = NOW()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING "userId"
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(None),
+    };
+
+    sqlx::query("DELETE FROM login_failures WHERE key = $1")
+        .bind(LoginKey::User(user_id).key())
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.unlocked",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(user_id))
+}
@@ -0,0 +1,95 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::guard::{LoginKey, login_retry_after, record_failed_login};
+use crate::controllers::lockout_controller::reauth::{EnrollmentProof, ReauthError, confirm_enrollment_identity};
+use crate::controllers::mfa_controller::totp::confirm_enrollment;
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    // Shown once; only their hashes are kept
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotp {
+    // From the authenticator app being enrolled
+    pub code: String,
+    #[serde(flatten)]
+    pub proof: EnrollmentProof,
+}
+
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<ConfirmTotp>,
+) -> impl IntoResponse {
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) if user.deleted_at.is_none() => user,
+        _ => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    };
+
+    let client = ClientInfo::from_request(&headers, peer);
+    match confirm_enrollment_identity(&state.db, &state.mailer, &user, &params.proof, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(err)) => {
+            eprintln!("Failed to confirm identity: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to enable two-factor authentication"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    // Code guesses share the login lockout, like the other second-factor checks
+    let account = LoginKey::User(user_id);
+    match login_retry_after(&state.db, &account, &client).await {
+        Ok(None) => {}
+        Ok(Some(secs)) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                Resp::error(format!("Too many failed attempts, try again in {} seconds", secs)),
+            );
+        }
+        Err(err) => {
+            eprintln!("Failed to check login failures: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to enable two-factor authentication"),
+            );
+        }
+    }
+
+    match confirm_enrollment(&state.db, user_id, &params.code).await {
+        Ok(Some(recovery_codes)) => Resp::success(
+            "Two-factor authentication enabled, store the recovery codes somewhere safe",
+            Some(RecoveryCodes { recovery_codes }),
+        ),
+        Ok(None) => {
+            if let Err(err) = record_failed_login(&state.db, &state.mailer, &account, &client).await {
+                eprintln!("Failed to record login failure: {}", err);
+            }
+            (
+                StatusCode::BAD_REQUEST,
+                Resp::error("Invalid code or no enrollment in progress"),
+            )
+        }
+        Err(err) => {
+            eprintln!("Failed to confirm TOTP enrollment: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to enable two-factor authentication"),
+            )
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::reauth::{ReauthError, confirm_second_factor};
+use crate::controllers::mfa_controller::totp::{CodeRequest, disable_mfa};
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use std::net::SocketAddr;
+
+// Needs a current code so a stolen access token alone can't strip the second factor
+pub async fn disable_totp(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<CodeRequest>,
+) -> impl IntoResponse {
+    let client = ClientInfo::from_request(&headers, peer);
+    match confirm_second_factor(&state.db, &state.mailer, user_id, &params.code, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(err)) => {
+            eprintln!("Failed to verify second factor: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to disable two-factor authentication"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    match disable_mfa(&state.db, user_id).await {
+        Ok(()) => Resp::success("Two-factor authentication disabled", None::<()>),
+        Err(err) => {
+            eprintln!("Failed to disable TOTP: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to disable two-factor authentication"),
+            )
+        }
+    }
+}
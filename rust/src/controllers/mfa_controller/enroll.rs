@@ -0,0 +1,78 @@
+use std::env;
+
+use crate::AppState;
+use crate::controllers::lockout_controller::reauth::{EnrollmentProof, ReauthError, confirm_enrollment_identity};
+use crate::controllers::mfa_controller::totp::start_enrollment;
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use crate::libs::totp::otpauth_uri;
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::Serialize;
+use std::net::SocketAddr;
+
+#[derive(Serialize)]
+pub struct TotpEnrollment {
+    // For manual entry; the URI is meant to be shown as a QR code
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<EnrollmentProof>,
+) -> impl IntoResponse {
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) if user.deleted_at.is_none() => user,
+        _ => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    };
+
+    let client = ClientInfo::from_request(&headers, peer);
+    match confirm_enrollment_identity(&state.db, &state.mailer, &user, &params, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(err)) => {
+            eprintln!("Failed to confirm identity: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start enrollment"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    let account = user
+        .email
+        .clone()
+        .or_else(|| user.nickname.clone())
+        .unwrap_or_else(|| user_id.to_string());
+
+    match start_enrollment(&state.db, user_id).await {
+        Ok(Some(secret)) => {
+            let issuer = env::var("TOTP_ISSUER").unwrap_or_else(|_| "Cheetah".to_string());
+            Resp::success(
+                "Scan the code with your authenticator app, then confirm with a code from it",
+                Some(TotpEnrollment {
+                    otpauth_uri: otpauth_uri(&issuer, &account, &secret),
+                    secret,
+                }),
+            )
+        }
+        Ok(None) => (
+            StatusCode::CONFLICT,
+            Resp::error("Two-factor authentication is already enabled"),
+        ),
+        Err(err) => {
+            eprintln!("Failed to start TOTP enrollment: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start enrollment"),
+            )
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::libs::crypto::{generate_opaque_token, hash_token};
+use sqlx::{Pool, Postgres};
+
+// Long enough to set up the authenticator app between enrolling and confirming
+const ENROLLMENT_TTL_MINUTES: i32 = 30;
+const ENROLLMENT_INTERVAL_SECS: i64 = 60;
+const MAX_ENROLLMENT_MAILS_PER_DAY: i64 = 5;
+
+// Seconds until another confirmation mail may go to this user, or None when one may go now
+pub async fn enrollment_wait_secs(db: &Pool<Postgres>, user_id: i64) -> Result<Option<i64>, sqlx::Error> {
+    let (last_sent, sent_today) = sqlx::query_as::<_, (Option<i64>, i64)>(
+        r#"
+        SELECT EXTRACT(EPOCH FROM NOW() - MAX(created_at))::BIGINT,
+               COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '1 day')
+        FROM mfa_enrollment_tokens WHERE "userId" = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+
+    if sent_today >= MAX_ENROLLMENT_MAILS_PER_DAY {
+        return Ok(Some(24 * 60 * 60));
+    }
+    Ok(last_sent
+        .map(|elapsed| ENROLLMENT_INTERVAL_SECS - elapsed)
+        .filter(|wait| *wait > 0))
+}
+
+// Issues a new code and spends any earlier one, so only the latest mail works
+pub async fn issue_enrollment_token(db: &Pool<Postgres>, user_id: i64) -> Result<String, sqlx::Error> {
+    let token = generate_opaque_token();
+    let mut tx = db.begin().await?;
+
+    sqlx::query(r#"UPDATE mfa_enrollment_tokens SET used_at = NOW() WHERE "userId" = $1 AND used_at IS NULL"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO mfa_enrollment_tokens ("userId", token_hash, expires_at, created_at)
+        VALUES ($1, $2, NOW() + make_interval(mins => $3), NOW())
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(ENROLLMENT_TTL_MINUTES)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(token)
+}
+
+// Whether the code is the user's latest and still usable. It is not spent here: the same
+// code covers starting and confirming the enrollment, and confirm_enrollment spends it.
+pub async fn is_enrollment_token_valid(db: &Pool<Postgres>, user_id: i64, token: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM mfa_enrollment_tokens
+            WHERE "userId" = $1 AND token_hash = $2 AND used_at IS NULL AND expires_at > NOW()
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(token))
+    .fetch_one(db)
+    .await
+}
@@ -0,0 +1,7 @@
+pub mod confirm;
+pub mod disable;
+pub mod enroll;
+pub mod enrollment_token;
+pub mod recovery;
+pub mod totp;
+pub mod verify_login;
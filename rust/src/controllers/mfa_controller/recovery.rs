@@ -0,0 +1,47 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::reauth::{ReauthError, confirm_second_factor};
+use crate::controllers::mfa_controller::confirm::RecoveryCodes;
+use crate::controllers::mfa_controller::totp::{CodeRequest, regenerate_recovery_codes};
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use std::net::SocketAddr;
+
+// Replaces all recovery codes, used or not
+pub async fn regenerate_codes(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<CodeRequest>,
+) -> impl IntoResponse {
+    let client = ClientInfo::from_request(&headers, peer);
+    match confirm_second_factor(&state.db, &state.mailer, user_id, &params.code, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(err)) => {
+            eprintln!("Failed to verify second factor: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to regenerate recovery codes"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    match regenerate_recovery_codes(&state.db, user_id).await {
+        Ok(recovery_codes) => Resp::success(
+            "Recovery codes regenerated, the old ones no longer work",
+            Some(RecoveryCodes { recovery_codes }),
+        ),
+        Err(err) => {
+            eprintln!("Failed to regenerate recovery codes: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to regenerate recovery codes"),
+            )
+        }
+    }
+}
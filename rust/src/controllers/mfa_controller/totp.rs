@@ -0,0 +1,229 @@
+use chrono::Utc;
+use sqlx::{Pool, Postgres, Transaction};
+
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::crypto::hash_token;
+use crate::libs::totp::{generate_recovery_codes, generate_secret, normalize_recovery_code, verify_code};
+
+#[derive(sqlx::FromRow)]
+struct UserTotp {
+    secret: String,
+    enabled: bool,
+}
+
+// Which second factor a code turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+#[derive(serde::Deserialize)]
+pub struct CodeRequest {
+    pub code: String,
+}
+
+async fn load_totp(db: &Pool<Postgres>, user_id: i64) -> Result<Option<UserTotp>, sqlx::Error> {
+    sqlx::query_as::<_, UserTotp>(
+        r#"SELECT secret, enabled_at IS NOT NULL as enabled FROM user_totp WHERE "userId" = $1"#,
+    )
+    .bind(user_id)
+    .fetch_optional(db)
+    .await
+}
+
+pub async fn is_mfa_enabled(db: &Pool<Postgres>, user_id: i64) -> Result<bool, sqlx::Error> {
+    Ok(load_totp(db, user_id).await?.is_some_and(|totp| totp.enabled))
+}
+
+// Stores a fresh secret awaiting confirmation. None when 2FA is already on;
+// starting over before confirming replaces the unconfirmed secret.
+pub async fn start_enrollment(db: &Pool<Postgres>, user_id: i64) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        INSERT INTO user_totp ("userId", secret, created_at) VALUES ($1, $2, NOW())
+        ON CONFLICT ("userId") DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+        WHERE user_totp.enabled_at IS NULL
+        RETURNING secret
+        "#,
+    )
+    .bind(user_id)
+    .bind(generate_secret())
+    .fetch_optional(db)
+    .await
+}
+
+// Accepts a TOTP code once per time step; the conditional UPDATE closes the replay window
+async fn accept_totp(db: &Pool<Postgres>, user_id: i64, secret: &str, code: &str) -> Result<bool, sqlx::Error> {
+    let step = match verify_code(secret, code, Utc::now().timestamp()) {
+        Some(step) => step,
+        None => return Ok(false),
+    };
+
+    let result = sqlx::query(
+        r#"UPDATE user_totp SET last_used_step = $2 WHERE "userId" = $1 AND (last_used_step IS NULL OR last_used_step < $2)"#,
+    )
+    .bind(user_id)
+    .bind(step)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+async fn consume_recovery_code(db: &Pool<Postgres>, user_id: i64, code: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"UPDATE mfa_recovery_codes SET used_at = NOW() WHERE "userId" = $1 AND code_hash = $2 AND used_at IS NULL"#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&normalize_recovery_code(code)))
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+// Checks a code against an enabled second factor: six digits are a TOTP code,
+// anything else is tried as a recovery code (and spent if it matches)
+pub async fn verify_second_factor(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    code: &str,
+) -> Result<Option<SecondFactor>, sqlx::Error> {
+    let totp = match load_totp(db, user_id).await? {
+        Some(totp) if totp.enabled => totp,
+        _ => return Ok(None),
+    };
+
+    let code = code.trim();
+    if code.len() == 6 && code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Ok(accept_totp(db, user_id, &totp.secret, code).await?.then_some(SecondFactor::Totp));
+    }
+
+    if !consume_recovery_code(db, user_id, code).await? {
+        return Ok(None);
+    }
+    record_audit(
+        db,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.mfa_recovery_code_used",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({ "remaining": remaining_recovery_codes(db, user_id).await? }),
+        },
+    )
+    .await?;
+    Ok(Some(SecondFactor::RecoveryCode))
+}
+
+pub async fn remaining_recovery_codes(db: &Pool<Postgres>, user_id: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>(
+        r#"SELECT COUNT(*) FROM mfa_recovery_codes WHERE "userId" = $1 AND used_at IS NULL"#,
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+}
+
+// Replaces every recovery code; the plain codes are returned once and never stored
+async fn replace_recovery_codes(tx: &mut Transaction<'_, Postgres>, user_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query(r#"DELETE FROM mfa_recovery_codes WHERE "userId" = $1"#)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    let codes = generate_recovery_codes();
+    let hashes: Vec<String> = codes.iter().map(|code| hash_token(&normalize_recovery_code(code))).collect();
+    sqlx::query(
+        r#"INSERT INTO mfa_recovery_codes ("userId", code_hash, created_at) SELECT $1, UNNEST($2::TEXT[]), NOW()"#,
+    )
+    .bind(user_id)
+    .bind(&hashes)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(codes)
+}
+
+// Turns 2FA on once the user shows a working code for the pending secret.
+// None when there is no pending enrollment or the code is wrong.
+pub async fn confirm_enrollment(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    code: &str,
+) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let secret = match load_totp(db, user_id).await? {
+        Some(totp) if !totp.enabled => totp.secret,
+        _ => return Ok(None),
+    };
+    if !accept_totp(db, user_id, &secret, code).await? {
+        return Ok(None);
+    }
+
+    let mut tx = db.begin().await?;
+    sqlx::query(r#"UPDATE user_totp SET enabled_at = NOW() WHERE "userId" = $1"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(r#"UPDATE mfa_enrollment_tokens SET used_at = NOW() WHERE "userId" = $1 AND used_at IS NULL"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let codes = replace_recovery_codes(&mut tx, user_id).await?;
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.mfa_enabled",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Some(codes))
+}
+
+pub async fn regenerate_recovery_codes(db: &Pool<Postgres>, user_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let codes = replace_recovery_codes(&mut tx, user_id).await?;
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.mfa_recovery_codes_regenerated",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(codes)
+}
+
+pub async fn disable_mfa(db: &Pool<Postgres>, user_id: i64) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    sqlx::query(r#"DELETE FROM user_totp WHERE "userId" = $1"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(r#"DELETE FROM mfa_recovery_codes WHERE "userId" = $1"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.mfa_disabled",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::guard::{
+    LoginKey, clear_failed_logins, login_retry_after, record_failed_login,
+};
+use crate::controllers::mfa_controller::totp::verify_second_factor;
+use crate::controllers::session_controller::session::{ClientInfo, create_session};
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::controllers::user_controller::login::LoginResponse;
+use crate::libs::Resp;
+use crate::libs::crypto::verify_mfa_pending_token;
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use std::net::SocketAddr;
+
+#[derive(Deserialize)]
+pub struct MfaLogin {
+    pub mfa_token: String,
+    // A TOTP code or one of the recovery codes
+    pub code: String,
+}
+
+// Second login step: the pending token from /v1/login plus a code buys the real token pair
+pub async fn login_with_mfa(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<MfaLogin>,
+) -> impl IntoResponse {
+    let user_id = match verify_mfa_pending_token(params.mfa_token.trim()) {
+        Some(user_id) => user_id,
+        None => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Resp::error("Login expired, enter your password again"),
+            );
+        }
+    };
+
+    // Code guesses count against the account like wrong passwords, and share its lockout
+    let client = ClientInfo::from_request(&headers, peer);
+    let account = LoginKey::User(user_id);
+    match login_retry_after(&state.db, &account, &client).await {
+        Ok(None) => {}
+        Ok(Some(secs)) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                Resp::error(format!("Too many failed attempts, try again in {} seconds", secs)),
+            );
+        }
+        Err(err) => {
+            eprintln!("Failed to check login failures: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+    }
+
+    match verify_second_factor(&state.db, user_id, &params.code).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            if let Err(err) = record_failed_login(&state.db, &state.mailer, &account, &client).await {
+                eprintln!("Failed to record login failure: {}", err);
+            }
+            return (StatusCode::UNAUTHORIZED, Resp::error("Invalid code"));
+        }
+        Err(err) => {
+            eprintln!("Failed to verify second factor: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+    }
+
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, Resp::error("User not found")),
+    };
+
+    if let Err(err) = clear_failed_logins(&state.db, &account).await {
+        eprintln!("Failed to clear login failures: {}", err);
+    }
+
+    match create_session(&state.db, user.id, &client).await {
+        Ok(issued) => Resp::success(
+            "Login Success",
+            Some(LoginResponse {
+                user: Some(user),
+                session_id: issued.session_id,
+                token: issued.token,
+                refresh_token: issued.refresh_token,
+            }),
+        ),
+        Err(e) => {
+            eprintln!("Failed to create session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to create session"),
+            )
+        }
+    }
+}
@@ -1,2 +1,14 @@
+pub mod account_controller;
+pub mod attachment_controller;
 pub mod chat_controller;
+pub mod direct_controller;
+pub mod email_controller;
+pub mod jwks_controller;
+pub mod lockout_controller;
+pub mod mfa_controller;
+pub mod oauth_controller;
+pub mod passkey_controller;
+pub mod password_controller;
+pub mod room_controller;
+pub mod session_controller;
 pub mod user_controller;
@@ -0,0 +1,193 @@
+use crate::controllers::email_controller::verification::send_verification_email;
+use crate::db::model::User;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::avatar::{AvatarType, generate_avatar};
+use crate::libs::nickname::{MAX_NICKNAME_LEN, validate_nickname};
+use crate::libs::oidc::IdTokenClaims;
+use crate::mailer::SharedMailer;
+use rand::Rng;
+use sqlx::{Pool, Postgres};
+
+// Attempts at a free nickname before giving up
+const NICKNAME_ATTEMPTS: usize = 5;
+
+// The provider's name, with a random suffix after a clash. Names that break the nickname
+// rules (too short, reserved, or a `deleted-<id>` placeholder) become `user<digits>`.
+fn nickname_candidate(hint: &str, attempt: usize) -> String {
+    let mut rng = rand::thread_rng();
+    let candidate = if attempt == 0 {
+        hint.to_string()
+    } else {
+        let base: String = hint.chars().take(MAX_NICKNAME_LEN - 4).collect();
+        format!("{}{}", base, rng.gen_range(1000..10000))
+    };
+    match validate_nickname(&candidate) {
+        Ok(()) => candidate,
+        Err(_) => format!("user{}", rng.gen_range(100000..1000000)),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AccountError {
+    // Linking would hand whoever registered the address (without proving it) a way into this login
+    #[error("An account with this email exists but is not verified; sign in with your password and verify it first")]
+    UnverifiedEmailTaken,
+    #[error("An account with this email is already linked to another sign-in provider")]
+    LinkedElsewhere,
+    #[error("Could not find a free nickname")]
+    NicknameUnavailable,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+// The account for a provider identity: the one already linked to it, else an existing
+// account with the same verified email, else a new one
+pub async fn resolve_account(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    provider: &str,
+    claims: &IdTokenClaims,
+) -> Result<User, AccountError> {
+    let auth_id = format!("{}:{}", provider, claims.sub);
+
+    let linked = sqlx::query_as::<_, User>(r#"SELECT * FROM users WHERE "authId" = $1"#)
+        .bind(&auth_id)
+        .fetch_optional(db)
+        .await?;
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    if let Some(email) = claims.verified_email() {
+        let existing = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = $1")
+            .bind(&email)
+            .fetch_optional(db)
+            .await?;
+        if let Some(user) = existing {
+            return link_account(db, user, &auth_id, provider).await;
+        }
+    }
+
+    create_account(db, mailer, &auth_id, provider, claims).await
+}
+
+async fn link_account(db: &Pool<Postgres>, user: User, auth_id: &str, provider: &str) -> Result<User, AccountError> {
+    if user.auth_id.is_some() {
+        return Err(AccountError::LinkedElsewhere);
+    }
+    if user.email_verified_at.is_none() {
+        return Err(AccountError::UnverifiedEmailTaken);
+    }
+
+    let linked = sqlx::query_as::<_, User>(
+        r#"UPDATE users SET "authId" = $1, updated_at = NOW() WHERE id = $2 AND "authId" IS NULL RETURNING *"#,
+    )
+    .bind(auth_id)
+    .bind(user.id)
+    .fetch_optional(db)
+    .await?
+    // Another login linked it first
+    .ok_or(AccountError::LinkedElsewhere)?;
+
+    record_audit(
+        db,
+        AuditEntry {
+            actor_id: Some(linked.id),
+            action: "user.oauth_linked",
+            target_type: "user",
+            target_id: Some(linked.id),
+            metadata: serde_json::json!({ "provider": provider }),
+        },
+    )
+    .await
+    .ok();
+
+    Ok(linked)
+}
+
+async fn create_account(
+    db: &Pool<Postgres>,
+    mailer: &SharedMailer,
+    auth_id: &str,
+    provider: &str,
+    claims: &IdTokenClaims,
+) -> Result<User, AccountError> {
+    // The address is kept only if no other account has it; unverified ones still get a verification mail
+    let email = match claims.email.as_deref().map(|email| email.trim().to_lowercase()) {
+        Some(email) if !email.is_empty() => {
+            let taken = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(email) = $1)")
+                .bind(&email)
+                .fetch_one(db)
+                .await?;
+            (!taken).then_some(email)
+        }
+        _ => None,
+    };
+
+    let hint = claims.nickname_hint();
+    for attempt in 0..NICKNAME_ATTEMPTS {
+        let nickname = nickname_candidate(&hint, attempt);
+
+        let created = sqlx::query_as::<_, User>(
+            r#"
+            INSERT INTO users (email, nickname, avatar, "authId", email_verified_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, CASE WHEN $5 THEN NOW() END, NOW(), NOW())
+            ON CONFLICT DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(&email)
+        .bind(&nickname)
+        .bind(generate_avatar(nickname.clone(), AvatarType::Identicon))
+        .bind(auth_id)
+        .bind(claims.email_verified)
+        .fetch_optional(db)
+        .await?;
+
+        let Some(user) = created else { continue };
+
+        if let (Some(email), None) = (&email, user.email_verified_at) {
+            if let Err(e) = send_verification_email(db, mailer, user.id, email).await {
+                eprintln!("Failed to send verification email: {}", e);
+            }
+        }
+        record_audit(
+            db,
+            AuditEntry {
+                actor_id: Some(user.id),
+                action: "user.oauth_registered",
+                target_type: "user",
+                target_id: Some(user.id),
+                metadata: serde_json::json!({ "provider": provider }),
+            },
+        )
+        .await
+        .ok();
+
+        return Ok(user);
+    }
+
+    Err(AccountError::NicknameUnavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_names_follow_the_nickname_rules() {
+        assert_eq!(nickname_candidate("alice", 0), "alice");
+        for hint in ["admin", "Ad_Min", "ab", "deleted-7"] {
+            let nickname = nickname_candidate(hint, 0);
+            assert!(nickname.starts_with("user"), "{} became {}", hint, nickname);
+            assert!(validate_nickname(&nickname).is_ok());
+        }
+    }
+
+    #[test]
+    fn suffixed_names_stay_within_the_length_limit() {
+        let nickname = nickname_candidate(&"a".repeat(MAX_NICKNAME_LEN), 1);
+        assert!(nickname.starts_with("aaaa"));
+        assert!(validate_nickname(&nickname).is_ok());
+    }
+}
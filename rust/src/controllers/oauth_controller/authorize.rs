@@ -0,0 +1,65 @@
+use crate::AppState;
+use crate::controllers::oauth_controller::login_state::store_login_state;
+use crate::libs::Resp;
+use crate::libs::oidc::{Provider, pkce_challenge};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct AuthorizationRequest {
+    pub authorization_url: String,
+    pub state: String,
+}
+
+// First step: the client sends the browser to `authorization_url`, and the provider
+// sends it back to the redirect URI with `code` and `state`
+pub async fn authorize(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> impl IntoResponse {
+    let provider = match Provider::from_env(&provider) {
+        Some(provider) => provider,
+        None => return (StatusCode::NOT_FOUND, Resp::error("Unknown identity provider")),
+    };
+
+    let metadata = match provider.metadata().await {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            eprintln!("Failed to load OIDC metadata for {}: {}", provider.name, err);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Resp::error("Identity provider is unavailable"),
+            );
+        }
+    };
+
+    let (login_state, code_verifier, nonce) = match store_login_state(&state.db, &provider.name).await {
+        Ok(stored) => stored,
+        Err(err) => {
+            eprintln!("Failed to store OIDC login state: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start sign-in"),
+            );
+        }
+    };
+
+    match provider.authorization_url(&metadata, &login_state, &nonce, &pkce_challenge(&code_verifier)) {
+        Ok(authorization_url) => Resp::success(
+            "Continue at the identity provider",
+            Some(AuthorizationRequest {
+                authorization_url,
+                state: login_state,
+            }),
+        ),
+        Err(err) => {
+            eprintln!("Failed to build authorization URL for {}: {}", provider.name, err);
+            (
+                StatusCode::BAD_GATEWAY,
+                Resp::error("Identity provider is unavailable"),
+            )
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::AppState;
+use crate::controllers::email_controller::verification::VerificationPolicy;
+use crate::controllers::mfa_controller::totp::is_mfa_enabled;
+use crate::controllers::oauth_controller::account::{AccountError, resolve_account};
+use crate::controllers::oauth_controller::login_state::take_login_state;
+use crate::controllers::session_controller::session::{ClientInfo, create_session};
+use crate::controllers::user_controller::login::{LoginResponse, LoginResult, MfaChallenge};
+use crate::libs::Resp;
+use crate::libs::crypto::generate_mfa_pending_token;
+use crate::libs::oidc::{IdTokenClaims, OidcError, Provider};
+use axum::extract::{ConnectInfo, Json, Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use std::net::SocketAddr;
+
+#[derive(Deserialize)]
+pub struct OAuthCallback {
+    pub code: String,
+    pub state: String,
+}
+
+// Second step: the client posts the `code` and `state` the provider redirected back with.
+// Answers like /v1/login, so accounts with 2FA continue at /v1/login/mfa.
+pub async fn callback(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<OAuthCallback>,
+) -> impl IntoResponse {
+    let provider = match Provider::from_env(&provider) {
+        Some(provider) => provider,
+        None => return (StatusCode::NOT_FOUND, Resp::error("Unknown identity provider")),
+    };
+
+    let login = match take_login_state(&state.db, params.state.trim(), &provider.name).await {
+        Ok(Some(login)) => login,
+        Ok(None) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Resp::error("Sign-in expired, start again"),
+            );
+        }
+        Err(err) => {
+            eprintln!("Failed to load OIDC login state: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+    };
+
+    let claims = match exchange_and_verify(&provider, params.code.trim(), &login.code_verifier, &login.nonce).await {
+        Ok(claims) => claims,
+        Err(OidcError::Http(err)) => {
+            eprintln!("OIDC provider {} is unreachable: {}", provider.name, err);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Resp::error("Identity provider is unavailable"),
+            );
+        }
+        Err(err) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Resp::error(format!("Sign-in rejected: {}", err)),
+            );
+        }
+    };
+
+    let user = match resolve_account(&state.db, &state.mailer, &provider.name, &claims).await {
+        Ok(user) => user,
+        Err(AccountError::Database(err)) => {
+            eprintln!("Failed to resolve OIDC account: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+        Err(err) => return (StatusCode::CONFLICT, Resp::error(err.to_string())),
+    };
+
+    if user.email_verified_at.is_none() && VerificationPolicy::from_env().blocks_login() {
+        return (
+            StatusCode::FORBIDDEN,
+            Resp::error("Verify your email address before logging in"),
+        );
+    }
+
+    // The provider stands in for the password only; TOTP is still asked for
+    match is_mfa_enabled(&state.db, user.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return match generate_mfa_pending_token(user.id) {
+                Ok(mfa_token) => Resp::success(
+                    "Enter the code from your authenticator app",
+                    Some(LoginResult::MfaRequired(MfaChallenge {
+                        mfa_required: true,
+                        mfa_token,
+                    })),
+                ),
+                Err(e) => {
+                    eprintln!("Failed to issue MFA token: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Resp::error("Authentication error"),
+                    )
+                }
+            };
+        }
+        Err(e) => {
+            eprintln!("Failed to check two-factor status: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+    }
+
+    let client = ClientInfo::from_request(&headers, peer);
+    match create_session(&state.db, user.id, &client).await {
+        Ok(issued) => Resp::success(
+            "Login Success",
+            Some(LoginResult::Tokens(Box::new(LoginResponse {
+                user: Some(user),
+                session_id: issued.session_id,
+                token: issued.token,
+                refresh_token: issued.refresh_token,
+            }))),
+        ),
+        Err(e) => {
+            eprintln!("Failed to create session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to create session"),
+            )
+        }
+    }
+}
+
+async fn exchange_and_verify(
+    provider: &Provider,
+    code: &str,
+    code_verifier: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, OidcError> {
+    let metadata = provider.metadata().await?;
+    let id_token = provider.exchange_code(&metadata, code, code_verifier).await?;
+    provider.verify_id_token(&id_token, nonce).await
+}
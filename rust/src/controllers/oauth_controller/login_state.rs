@@ -0,0 +1,56 @@
+use crate::libs::oidc::{LOGIN_TIMEOUT_SECS, random_token};
+use sqlx::{Pool, Postgres};
+
+// What the callback needs to finish a login it started
+#[derive(sqlx::FromRow)]
+pub struct LoginState {
+    pub code_verifier: String,
+    pub nonce: String,
+}
+
+// Stores a fresh state, PKCE verifier and nonce for one login and drops expired ones.
+// Returns (state, code_verifier, nonce).
+pub async fn store_login_state(
+    db: &Pool<Postgres>,
+    provider: &str,
+) -> Result<(String, String, String), sqlx::Error> {
+    sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < NOW()")
+        .execute(db)
+        .await?;
+
+    let (state, code_verifier, nonce) = (random_token(), random_token(), random_token());
+    sqlx::query(
+        r#"
+        INSERT INTO oidc_login_states (state, provider, code_verifier, nonce, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5), NOW())
+        "#,
+    )
+    .bind(&state)
+    .bind(provider)
+    .bind(&code_verifier)
+    .bind(&nonce)
+    .bind(LOGIN_TIMEOUT_SECS as f64)
+    .execute(db)
+    .await?;
+
+    Ok((state, code_verifier, nonce))
+}
+
+// Deletes and returns the login, so a state value can be answered only once
+pub async fn take_login_state(
+    db: &Pool<Postgres>,
+    state: &str,
+    provider: &str,
+) -> Result<Option<LoginState>, sqlx::Error> {
+    sqlx::query_as::<_, LoginState>(
+        r#"
+        DELETE FROM oidc_login_states
+        WHERE state = $1 AND provider = $2 AND expires_at > NOW()
+        RETURNING code_verifier, nonce
+        "#,
+    )
+    .bind(state)
+    .bind(provider)
+    .fetch_optional(db)
+    .await
+}
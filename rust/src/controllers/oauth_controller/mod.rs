@@ -0,0 +1,4 @@
+pub mod account;
+pub mod authorize;
+pub mod callback;
+pub mod login_state;
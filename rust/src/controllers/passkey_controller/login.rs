@@ -0,0 +1,182 @@
+use crate::AppState;
+use crate::controllers::email_controller::verification::VerificationPolicy;
+use crate::controllers::passkey_controller::passkey::{
+    Ceremony, find_passkey, record_passkey_use, store_challenge, take_challenge, user_handle,
+};
+use crate::controllers::passkey_controller::register::CeremonyOptions;
+use crate::controllers::session_controller::session::{ClientInfo, create_session};
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::controllers::mfa_controller::totp::is_mfa_enabled;
+use crate::controllers::user_controller::login::{LoginResponse, LoginResult, MfaChallenge};
+use crate::libs::Resp;
+use crate::libs::crypto::generate_mfa_pending_token;
+use crate::libs::webauthn::{AssertionCredential, RelyingParty};
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use base64::{Engine, engine::general_purpose as b64};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+// Usernameless: the authenticator offers whichever passkey it holds for us
+pub async fn login_options(State(state): State<AppState>) -> impl IntoResponse {
+    match store_challenge(&state.db, None, Ceremony::Authentication).await {
+        Ok((challenge_id, challenge)) => Resp::success(
+            "Passkey sign-in started",
+            Some(CeremonyOptions {
+                challenge_id,
+                public_key: RelyingParty::from_env().request_options(&challenge, &[]),
+            }),
+        ),
+        Err(err) => {
+            eprintln!("Failed to store WebAuthn challenge: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start passkey sign-in"),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyLogin {
+    pub challenge_id: Uuid,
+    pub credential: AssertionCredential,
+}
+
+// A passkey proves possession of the authenticator, which stands in for the password.
+// With user verification it also proves a PIN or biometric and covers the TOTP step;
+// without it, accounts with two-factor authentication still owe a code.
+pub async fn login_with_passkey(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<PasskeyLogin>,
+) -> impl IntoResponse {
+    let challenge = match take_challenge(&state.db, params.challenge_id, None, Ceremony::Authentication).await {
+        Ok(Some(challenge)) => challenge,
+        Ok(None) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Resp::error("Sign-in expired, start again"),
+            );
+        }
+        Err(err) => {
+            eprintln!("Failed to load WebAuthn challenge: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+    };
+
+    let passkey = match find_passkey(&state.db, params.credential.raw_id.trim_end_matches('=')).await {
+        Ok(Some(passkey)) => passkey,
+        Ok(None) => return (StatusCode::UNAUTHORIZED, Resp::error("Unknown passkey")),
+        Err(err) => {
+            eprintln!("Failed to load passkey: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+    };
+
+    // When the authenticator names the account, it must be the passkey's owner
+    if let Some(handle) = &params.credential.response.user_handle {
+        if b64::URL_SAFE_NO_PAD.decode(handle.trim_end_matches('=')).ok().as_deref() != Some(&user_handle(passkey.user_id)[..]) {
+            return (StatusCode::UNAUTHORIZED, Resp::error("Passkey rejected"));
+        }
+    }
+
+    let verified = match RelyingParty::from_env().verify_assertion(
+        &challenge,
+        &params.credential,
+        &passkey.public_key,
+        passkey.sign_count as u32,
+    ) {
+        Ok(verified) => verified,
+        Err(err) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Resp::error(format!("Passkey rejected: {}", err)),
+            );
+        }
+    };
+
+    match record_passkey_use(&state.db, passkey.id, verified.sign_count).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::UNAUTHORIZED, Resp::error("Passkey rejected")),
+        Err(err) => {
+            eprintln!("Failed to update passkey: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
+    }
+
+    let user = match get_user_by_auth_id(passkey.user_id, state.db.clone()).await {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, Resp::error("User not found")),
+    };
+
+    if user.email_verified_at.is_none() && VerificationPolicy::from_env().blocks_login() {
+        return (
+            StatusCode::FORBIDDEN,
+            Resp::error("Verify your email address before logging in"),
+        );
+    }
+
+    if !verified.user_verified {
+        match is_mfa_enabled(&state.db, user.id).await {
+            Ok(false) => {}
+            Ok(true) => {
+                return match generate_mfa_pending_token(user.id) {
+                    Ok(mfa_token) => Resp::success(
+                        "Enter the code from your authenticator app",
+                        Some(LoginResult::MfaRequired(MfaChallenge {
+                            mfa_required: true,
+                            mfa_token,
+                        })),
+                    ),
+                    Err(e) => {
+                        eprintln!("Failed to issue MFA token: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Resp::error("Authentication error"),
+                        )
+                    }
+                };
+            }
+            Err(e) => {
+                eprintln!("Failed to check two-factor status: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Resp::error("Authentication error"),
+                );
+            }
+        }
+    }
+
+    let client = ClientInfo::from_request(&headers, peer);
+    match create_session(&state.db, user.id, &client).await {
+        Ok(issued) => Resp::success(
+            "Login Success",
+            Some(LoginResult::Tokens(Box::new(LoginResponse {
+                user: Some(user),
+                session_id: issued.session_id,
+                token: issued.token,
+                refresh_token: issued.refresh_token,
+            }))),
+        ),
+        Err(e) => {
+            eprintln!("Failed to create session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to create session"),
+            )
+        }
+    }
+}
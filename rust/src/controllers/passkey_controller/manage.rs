@@ -0,0 +1,59 @@
+use crate::AppState;
+use crate::controllers::passkey_controller::passkey::list_passkeys;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use crate::libs::audit::{AuditEntry, record_audit};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+pub async fn list_user_passkeys(State(state): State<AppState>, UserId(user_id): UserId) -> impl IntoResponse {
+    match list_passkeys(&state.db, user_id).await {
+        Ok(passkeys) => Resp::success("Passkeys retrieved successfully", Some(passkeys)),
+        Err(err) => {
+            eprintln!("Failed to load passkeys: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to load passkeys"),
+            )
+        }
+    }
+}
+
+pub async fn delete_passkey(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Path(passkey_id): Path<i64>,
+) -> impl IntoResponse {
+    let result = sqlx::query(r#"DELETE FROM webauthn_credentials WHERE id = $1 AND "userId" = $2"#)
+        .bind(passkey_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(result) if result.rows_affected() == 1 => {
+            record_audit(
+                &state.db,
+                AuditEntry {
+                    actor_id: Some(user_id),
+                    action: "user.passkey_removed",
+                    target_type: "user",
+                    target_id: Some(user_id),
+                    metadata: serde_json::json!({ "passkeyId": passkey_id }),
+                },
+            )
+            .await
+            .ok();
+            Resp::success("Passkey removed", None::<()>)
+        }
+        Ok(_) => (StatusCode::NOT_FOUND, Resp::error("Passkey not found")),
+        Err(err) => {
+            eprintln!("Failed to remove passkey: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to remove passkey"),
+            )
+        }
+    }
+}
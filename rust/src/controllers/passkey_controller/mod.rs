@@ -0,0 +1,5 @@
+pub mod login;
+pub mod manage;
+pub mod passkey;
+pub mod reauth;
+pub mod register;
@@ -0,0 +1,103 @@
+use crate::db::model::Passkey;
+use crate::libs::webauthn::{CEREMONY_TIMEOUT_MS, generate_challenge};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Ceremony {
+    Registration,
+    Authentication,
+}
+
+impl Ceremony {
+    fn as_str(self) -> &'static str {
+        match self {
+            Ceremony::Registration => "registration",
+            Ceremony::Authentication => "authentication",
+        }
+    }
+}
+
+// WebAuthn user handle: opaque to the authenticator and returned on sign-in
+pub fn user_handle(user_id: i64) -> [u8; 8] {
+    user_id.to_be_bytes()
+}
+
+// Stores a fresh challenge for one ceremony and drops expired ones
+pub async fn store_challenge(
+    db: &Pool<Postgres>,
+    user_id: Option<i64>,
+    ceremony: Ceremony,
+) -> Result<(Uuid, String), sqlx::Error> {
+    sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at < NOW()")
+        .execute(db)
+        .await?;
+
+    let id = Uuid::new_v4();
+    let challenge = generate_challenge();
+    sqlx::query(
+        r#"
+        INSERT INTO webauthn_challenges (id, "userId", ceremony, challenge, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5), NOW())
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(ceremony.as_str())
+    .bind(&challenge)
+    .bind((CEREMONY_TIMEOUT_MS / 1000) as f64)
+    .execute(db)
+    .await?;
+
+    Ok((id, challenge))
+}
+
+// Deletes and returns the challenge, so each one can be answered only once
+pub async fn take_challenge(
+    db: &Pool<Postgres>,
+    id: Uuid,
+    user_id: Option<i64>,
+    ceremony: Ceremony,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        DELETE FROM webauthn_challenges
+        WHERE id = $1 AND "userId" IS NOT DISTINCT FROM $2 AND ceremony = $3 AND expires_at > NOW()
+        RETURNING challenge
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(ceremony.as_str())
+    .fetch_optional(db)
+    .await
+}
+
+pub async fn list_passkeys(db: &Pool<Postgres>, user_id: i64) -> Result<Vec<Passkey>, sqlx::Error> {
+    sqlx::query_as::<_, Passkey>(r#"SELECT * FROM webauthn_credentials WHERE "userId" = $1 ORDER BY created_at"#)
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+}
+
+pub async fn find_passkey(db: &Pool<Postgres>, credential_id: &str) -> Result<Option<Passkey>, sqlx::Error> {
+    sqlx::query_as::<_, Passkey>("SELECT * FROM webauthn_credentials WHERE credential_id = $1")
+        .bind(credential_id)
+        .fetch_optional(db)
+        .await
+}
+
+// Moves the signature counter forward; false when a concurrent sign-in already used this count
+pub async fn record_passkey_use(db: &Pool<Postgres>, id: i64, sign_count: u32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE webauthn_credentials SET sign_count = $2, last_used_at = NOW()
+        WHERE id = $1 AND (sign_count < $2 OR $2 = 0)
+        "#,
+    )
+    .bind(id)
+    .bind(i64::from(sign_count))
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
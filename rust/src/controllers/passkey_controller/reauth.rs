@@ -0,0 +1,77 @@
+use crate::AppState;
+use crate::controllers::passkey_controller::passkey::{
+    Ceremony, find_passkey, list_passkeys, record_passkey_use, store_challenge, take_challenge,
+};
+use crate::controllers::passkey_controller::register::CeremonyOptions;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use crate::libs::webauthn::{AssertionCredential, RelyingParty};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+// A fresh assertion from one of the user's own passkeys, shown instead of a password
+#[derive(Deserialize)]
+pub struct PasskeyProof {
+    pub challenge_id: Uuid,
+    pub credential: AssertionCredential,
+}
+
+// Starts an assertion limited to the signed-in user's passkeys
+pub async fn reauth_options(State(state): State<AppState>, UserId(user_id): UserId) -> impl IntoResponse {
+    let allowed = match list_passkeys(&state.db, user_id).await {
+        Ok(passkeys) => passkeys.into_iter().map(|passkey| passkey.credential_id).collect::<Vec<_>>(),
+        Err(err) => {
+            eprintln!("Failed to load passkeys: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start passkey confirmation"),
+            );
+        }
+    };
+    if allowed.is_empty() {
+        return (StatusCode::NOT_FOUND, Resp::error("No passkeys registered"));
+    }
+
+    match store_challenge(&state.db, Some(user_id), Ceremony::Authentication).await {
+        Ok((challenge_id, challenge)) => Resp::success(
+            "Passkey confirmation started",
+            Some(CeremonyOptions {
+                challenge_id,
+                public_key: RelyingParty::from_env().request_options(&challenge, &allowed),
+            }),
+        ),
+        Err(err) => {
+            eprintln!("Failed to store WebAuthn challenge: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start passkey confirmation"),
+            )
+        }
+    }
+}
+
+// Spends the challenge and checks the assertion came from a passkey of this user
+pub async fn verify_passkey_proof(db: &Pool<Postgres>, user_id: i64, proof: &PasskeyProof) -> Result<bool, sqlx::Error> {
+    let challenge = match take_challenge(db, proof.challenge_id, Some(user_id), Ceremony::Authentication).await? {
+        Some(challenge) => challenge,
+        None => return Ok(false),
+    };
+    let passkey = match find_passkey(db, proof.credential.raw_id.trim_end_matches('=')).await? {
+        Some(passkey) if passkey.user_id == user_id => passkey,
+        _ => return Ok(false),
+    };
+
+    match RelyingParty::from_env().verify_assertion(
+        &challenge,
+        &proof.credential,
+        &passkey.public_key,
+        passkey.sign_count as u32,
+    ) {
+        Ok(verified) => record_passkey_use(db, passkey.id, verified.sign_count).await,
+        Err(_) => Ok(false),
+    }
+}
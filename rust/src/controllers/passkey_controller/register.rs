@@ -0,0 +1,194 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::reauth::{ReauthError, confirm_identity};
+use crate::controllers::passkey_controller::passkey::{
+    Ceremony, list_passkeys, store_challenge, take_challenge, user_handle,
+};
+use crate::controllers::session_controller::session::ClientInfo;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::db::model::Passkey;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::webauthn::{RegistrationCredential, RelyingParty};
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct CeremonyOptions {
+    // Sent back with the response so the server can find the challenge
+    pub challenge_id: Uuid,
+    // Pass to navigator.credentials.create() / get() as `publicKey`
+    #[serde(rename = "publicKey")]
+    pub public_key: serde_json::Value,
+}
+
+// A passkey is a new way into the account, so adding one asks for the same proof as
+// signing in: the password, and a code when two-factor authentication is on. It is
+// checked once, when the ceremony starts; the challenge it hands out is bound to the
+// user and single use, so finishing needs nothing more.
+#[derive(Deserialize)]
+pub struct PasskeyReauth {
+    pub password: Option<String>,
+    pub code: Option<String>,
+}
+
+pub async fn registration_options(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<PasskeyReauth>,
+) -> impl IntoResponse {
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) if user.deleted_at.is_none() => user,
+        _ => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    };
+
+    let client = ClientInfo::from_request(&headers, peer);
+    let (password, code) = (params.password.as_deref(), params.code.as_deref());
+    match confirm_identity(&state.db, &state.mailer, &user, password, code, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(err)) => {
+            eprintln!("Failed to confirm identity: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start passkey registration"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    let account = user
+        .email
+        .clone()
+        .or_else(|| user.nickname.clone())
+        .unwrap_or_else(|| user_id.to_string());
+
+    // Keeps an authenticator from registering a second passkey for the same account
+    let existing = match list_passkeys(&state.db, user_id).await {
+        Ok(passkeys) => passkeys.into_iter().map(|passkey| passkey.credential_id).collect::<Vec<_>>(),
+        Err(err) => {
+            eprintln!("Failed to load passkeys: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start passkey registration"),
+            );
+        }
+    };
+
+    match store_challenge(&state.db, Some(user_id), Ceremony::Registration).await {
+        Ok((challenge_id, challenge)) => Resp::success(
+            "Passkey registration started",
+            Some(CeremonyOptions {
+                challenge_id,
+                public_key: RelyingParty::from_env().creation_options(
+                    &challenge,
+                    &user_handle(user_id),
+                    &account,
+                    &existing,
+                ),
+            }),
+        ),
+        Err(err) => {
+            eprintln!("Failed to store WebAuthn challenge: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to start passkey registration"),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RegisterPasskey {
+    pub challenge_id: Uuid,
+    pub credential: RegistrationCredential,
+    // Label shown in the passkey list, e.g. "Work laptop"
+    pub name: Option<String>,
+}
+
+pub async fn register_passkey(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Json(params): Json<RegisterPasskey>,
+) -> impl IntoResponse {
+    let challenge = match take_challenge(&state.db, params.challenge_id, Some(user_id), Ceremony::Registration).await {
+        Ok(Some(challenge)) => challenge,
+        Ok(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error("Registration expired, start again"),
+            );
+        }
+        Err(err) => {
+            eprintln!("Failed to load WebAuthn challenge: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to register passkey"),
+            );
+        }
+    };
+
+    let verified = match RelyingParty::from_env().verify_registration(&challenge, &params.credential) {
+        Ok(verified) => verified,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error(format!("Passkey rejected: {}", err)),
+            );
+        }
+    };
+
+    let name = params
+        .name
+        .map(|name| name.trim().chars().take(64).collect::<String>())
+        .filter(|name| !name.is_empty());
+    let passkey = sqlx::query_as::<_, Passkey>(
+        r#"
+        INSERT INTO webauthn_credentials ("userId", credential_id, public_key, sign_count, name, created_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (credential_id) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(&verified.credential_id)
+    .bind(&verified.public_key)
+    .bind(i64::from(verified.sign_count))
+    .bind(name)
+    .fetch_optional(&state.db)
+    .await;
+
+    match passkey {
+        Ok(Some(passkey)) => {
+            record_audit(
+                &state.db,
+                AuditEntry {
+                    actor_id: Some(user_id),
+                    action: "user.passkey_added",
+                    target_type: "user",
+                    target_id: Some(user_id),
+                    metadata: serde_json::json!({ "passkeyId": passkey.id }),
+                },
+            )
+            .await
+            .ok();
+            Resp::success("Passkey registered", Some(passkey))
+        }
+        Ok(None) => (
+            StatusCode::CONFLICT,
+            Resp::error("This passkey is already registered"),
+        ),
+        Err(err) => {
+            eprintln!("Failed to save passkey: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to register passkey"),
+            )
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::guard::{LoginKey, clear_failed_logins};
+use crate::controllers::lockout_controller::reauth::{ReauthError, confirm_identity};
+use crate::controllers::session_controller::session::{ClientInfo, revoke_all_sessions};
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::extract::{SessionId, UserId};
+use crate::libs::Resp;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::crypto::hash_password;
+use crate::libs::password_policy::PasswordPolicy;
+use crate::mailer::{Email, send_in_background};
+use crate::socket::handlers::disconnect_sessions;
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+use std::net::SocketAddr;
+
+#[derive(Deserialize)]
+pub struct ChangePassword {
+    // Not needed by accounts that don't have a password yet
+    pub current_password: Option<String>,
+    pub new_password: String,
+    // TOTP or recovery code, when two-factor authentication is on
+    pub code: Option<String>,
+}
+
+// Replaces the password and signs every other device out; this one stays signed in
+pub async fn change_password(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    SessionId(current): SessionId,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<ChangePassword>,
+) -> impl IntoResponse {
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) => user,
+        None => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    };
+
+    let client = ClientInfo::from_request(&headers, peer);
+    let (password, code) = (params.current_password.as_deref(), params.code.as_deref());
+    match confirm_identity(&state.db, &state.mailer, &user, password, code, &client).await {
+        Ok(()) => {}
+        Err(ReauthError::Database(e)) => {
+            eprintln!("Failed to confirm identity: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to change password"),
+            );
+        }
+        Err(err) => return (err.status(), Resp::error(err.to_string())),
+    }
+
+    let inputs: Vec<&str> = [user.nickname.as_deref(), user.email.as_deref()].into_iter().flatten().collect();
+    if let Err(err) = PasswordPolicy::from_env().check(&params.new_password, &inputs) {
+        return (StatusCode::BAD_REQUEST, Resp::error(err.to_string()));
+    }
+
+    let password_hash = match hash_password(&params.new_password) {
+        Ok(hash) => hash,
+        Err(e) => {
+            eprintln!("Failed to hash password: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to hash password"),
+            );
+        }
+    };
+
+    let action = if user.password.is_some() { "user.password_changed" } else { "user.password_set" };
+    if let Err(e) = update_password(&state.db, user.id, &password_hash, action).await {
+        eprintln!("Failed to change password: {}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error("Failed to change password"),
+        );
+    }
+
+    match revoke_all_sessions(&state.db, user.id, current, "password_changed").await {
+        Ok(revoked) => disconnect_sessions(&state.user_sockets, user.id, &revoked).await,
+        Err(e) => eprintln!("Failed to revoke sessions after password change: {}", e),
+    }
+
+    if let Err(e) = clear_failed_logins(&state.db, &LoginKey::User(user.id)).await {
+        eprintln!("Failed to clear login failures after password change: {}", e);
+    }
+
+    if let Some(email) = user.email {
+        send_in_background(
+            state.mailer.clone(),
+            Email {
+                to: email,
+                subject: "Your password was changed".to_string(),
+                body: "The password of your account was just changed and your other devices were signed out.\n\n\
+                       If it wasn't you, reset it right away."
+                    .to_string(),
+            },
+        );
+    }
+
+    Resp::success("Password changed, other devices were signed out", None::<()>)
+}
+
+async fn update_password(db: &Pool<Postgres>, user_id: i64, password_hash: &str, action: &str) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    sqlx::query("UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2")
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action,
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+    tx.commit().await
+}
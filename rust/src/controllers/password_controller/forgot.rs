@@ -0,0 +1,77 @@
+use crate::AppState;
+use crate::controllers::password_controller::reset_token::{issue_reset_token, reset_link, reset_wait_secs};
+use crate::libs::Resp;
+use crate::mailer::{Email, send_in_background};
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+// Same answer whether or not the address is registered, so it can't be used to probe for accounts
+const FORGOT_RESPONSE: &str = "If that email is registered, a reset link has been sent";
+
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(params): Json<ForgotPassword>,
+) -> impl IntoResponse {
+    let email = params.email.trim();
+    if email.is_empty() {
+        return (StatusCode::BAD_REQUEST, Resp::error("Email is required"));
+    }
+
+    let account = sqlx::query_as::<_, (i64, String)>(
+        "SELECT id, email FROM users WHERE LOWER(email) = LOWER($1)",
+    )
+    .bind(email)
+    .fetch_optional(&state.db)
+    .await;
+
+    let (user_id, email) = match account {
+        Ok(Some(account)) => account,
+        Ok(None) => return Resp::success(FORGOT_RESPONSE, None::<()>),
+        Err(err) => {
+            eprintln!("Error loading user: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to process request"),
+            );
+        }
+    };
+
+    // A throttled request gets the same answer, it just doesn't send another mail
+    match reset_wait_secs(&state.db, user_id).await {
+        Ok(None) => {}
+        Ok(Some(_)) => return Resp::success(FORGOT_RESPONSE, None::<()>),
+        Err(err) => {
+            eprintln!("Error checking password reset throttle: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to process request"),
+            );
+        }
+    }
+
+    match issue_reset_token(&state.db, user_id).await {
+        Ok(token) => send_in_background(
+            state.mailer.clone(),
+            Email {
+                to: email,
+                subject: "Reset your password".to_string(),
+                body: format!(
+                    "Someone asked to reset the password of your account.\n\n\
+                     Open this link to choose a new one:\n{}\n\n\
+                     If it wasn't you, ignore this mail; your password stays the same.",
+                    reset_link(&token)
+                ),
+            },
+        ),
+        Err(err) => eprintln!("Failed to issue password reset token: {}", err),
+    }
+
+    Resp::success(FORGOT_RESPONSE, None::<()>)
+}
@@ -0,0 +1,4 @@
+pub mod change;
+pub mod forgot;
+pub mod reset;
+pub mod reset_token;
@@ -0,0 +1,108 @@
+use crate::AppState;
+use crate::controllers::lockout_controller::guard::{LoginKey, clear_failed_logins};
+use crate::controllers::password_controller::reset_token::{find_reset_account, reset_password_with_token};
+use crate::controllers::session_controller::session::revoke_all_sessions;
+use crate::libs::Resp;
+use crate::libs::crypto::hash_password;
+use crate::libs::password_policy::PasswordPolicy;
+use crate::mailer::{Email, send_in_background};
+use crate::socket::handlers::disconnect_sessions;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(params): Json<ResetPassword>,
+) -> impl IntoResponse {
+    if params.token.trim().is_empty() || params.password.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Resp::error("Token and password are required"),
+        );
+    }
+
+    let user = match find_reset_account(&state.db, params.token.trim()).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error("Invalid or expired reset token"),
+            );
+        }
+        Err(err) => {
+            eprintln!("Failed to load reset token: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to reset password"),
+            );
+        }
+    };
+
+    // Checked before the token is spent, so a rejected password can be retried with the same link
+    let inputs: Vec<&str> = [user.nickname.as_deref(), user.email.as_deref()].into_iter().flatten().collect();
+    if let Err(err) = PasswordPolicy::from_env().check(&params.password, &inputs) {
+        return (StatusCode::BAD_REQUEST, Resp::error(err.to_string()));
+    }
+
+    let password_hash = match hash_password(&params.password) {
+        Ok(hash) => hash,
+        Err(e) => {
+            eprintln!("Failed to hash password: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to hash password"),
+            );
+        }
+    };
+
+    let account = match reset_password_with_token(&state.db, params.token.trim(), &password_hash).await {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error("Invalid or expired reset token"),
+            );
+        }
+        Err(err) => {
+            eprintln!("Failed to reset password: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to reset password"),
+            );
+        }
+    };
+
+    // Whoever held the old password is signed out everywhere
+    match revoke_all_sessions(&state.db, account.user_id, None, "password_reset").await {
+        Ok(revoked) => disconnect_sessions(&state.user_sockets, account.user_id, &revoked).await,
+        Err(err) => eprintln!("Failed to revoke sessions after password reset: {}", err),
+    }
+
+    // Proving control of the mailbox also lifts a lockout
+    if let Err(err) = clear_failed_logins(&state.db, &LoginKey::User(account.user_id)).await {
+        eprintln!("Failed to clear login failures after password reset: {}", err);
+    }
+
+    if let Some(email) = account.email {
+        send_in_background(
+            state.mailer.clone(),
+            Email {
+                to: email,
+                subject: "Your password was changed".to_string(),
+                body: "The password of your account was just reset and all devices were signed out.\n\n\
+                       If it wasn't you, reset it again right away."
+                    .to_string(),
+            },
+        );
+    }
+
+    Resp::success("Password has been reset, please log in again", None::<()>)
+}
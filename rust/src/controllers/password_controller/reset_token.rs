@@ -0,0 +1,153 @@
+use std::env;
+
+use crate::db::model::User;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::crypto::{generate_opaque_token, hash_token};
+use sqlx::{Pool, Postgres};
+
+pub const DEFAULT_RESET_TTL_MINUTES: i32 = 30;
+pub const DEFAULT_RESET_INTERVAL_SECS: i64 = 60;
+pub const MAX_RESETS_PER_DAY: i64 = 5;
+
+fn reset_ttl_minutes() -> i32 {
+    env::var("PASSWORD_RESET_TTL_MINUTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(DEFAULT_RESET_TTL_MINUTES)
+}
+
+fn reset_interval_secs() -> i64 {
+    env::var("PASSWORD_RESET_RESEND_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_RESET_INTERVAL_SECS)
+}
+
+// Seconds until another reset mail may be sent to this user, or None when one may go out
+// now. Keeps /password/forgot from being used to flood someone's inbox.
+pub async fn reset_wait_secs(db: &Pool<Postgres>, user_id: i64) -> Result<Option<i64>, sqlx::Error> {
+    let (last_sent, sent_today) = sqlx::query_as::<_, (Option<i64>, i64)>(
+        r#"
+        SELECT EXTRACT(EPOCH FROM NOW() - MAX(created_at))::BIGINT,
+               COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '1 day')
+        FROM password_reset_tokens WHERE "userId" = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+
+    if sent_today >= MAX_RESETS_PER_DAY {
+        return Ok(Some(24 * 60 * 60));
+    }
+    Ok(last_sent
+        .map(|elapsed| reset_interval_secs() - elapsed)
+        .filter(|wait| *wait > 0))
+}
+
+// Link mailed to the user; the frontend posts the token back to /v1/password/reset
+pub fn reset_link(token: &str) -> String {
+    let base = env::var("PASSWORD_RESET_URL").unwrap_or_else(|_| "http://localhost:8081/reset-password".to_string());
+    format!("{}?token={}", base, token)
+}
+
+// Issues a new reset token and spends any earlier one, so only the latest mail works
+pub async fn issue_reset_token(db: &Pool<Postgres>, user_id: i64) -> Result<String, sqlx::Error> {
+    let token = generate_opaque_token();
+    let mut tx = db.begin().await?;
+
+    sqlx::query(r#"UPDATE password_reset_tokens SET used_at = NOW() WHERE "userId" = $1 AND used_at IS NULL"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO password_reset_tokens ("userId", token_hash, expires_at, created_at)
+        VALUES ($1, $2, NOW() + make_interval(mins => $3), NOW())
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(reset_ttl_minutes())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(token)
+}
+
+// The account a still usable token belongs to, without spending the token
+pub async fn find_reset_account(db: &Pool<Postgres>, token: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        r#"
+        SELECT users.* FROM password_reset_tokens
+        JOIN users ON users.id = password_reset_tokens."userId"
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(db)
+    .await
+}
+
+pub struct ResetAccount {
+    pub user_id: i64,
+    pub email: Option<String>,
+}
+
+// Spends the token and stores the new hash in one transaction.
+// None when the token is unknown, expired or already used.
+pub async fn reset_password_with_token(
+    db: &Pool<Postgres>,
+    token: &str,
+    password_hash: &str,
+) -> Result<Option<ResetAccount>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    // The conditional UPDATE is what makes the token single use under concurrent requests
+    let user_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE password_reset_tokens SET used_at = NOW()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING "userId"
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(None),
+    };
+
+    let email = sqlx::query_scalar::<_, Option<String>>(
+        "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2 RETURNING email",
+    )
+    .bind(password_hash)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(r#"UPDATE password_reset_tokens SET used_at = NOW() WHERE "userId" = $1 AND used_at IS NULL"#)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.password_reset",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: serde_json::json!({}),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(ResetAccount { user_id, email }))
+}
@@ -0,0 +1,42 @@
+use crate::AppState;
+use crate::controllers::user_controller::get_user::is_moderator;
+use crate::db::model::Room;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+pub async fn archive_room(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Path(room_id): Path<i64>,
+) -> impl IntoResponse {
+    // Archiving keeps the history readable but rejects new messages.
+    // Same rule as rename_room: the owner, or a moderator for ownerless rooms.
+    let moderator = is_moderator(user_id, &state.db).await;
+    let query = r#"
+        UPDATE rooms SET archived_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND ("ownerId" = $2 OR ("ownerId" IS NULL AND $3)) AND archived_at IS NULL
+        RETURNING *
+    "#;
+
+    let result = sqlx::query_as::<_, Room>(query)
+        .bind(room_id)
+        .bind(user_id)
+        .bind(moderator)
+        .fetch_optional(&state.db)
+        .await;
+
+    match result {
+        Ok(Some(room)) => Resp::success("Room archived successfully", Some(room)),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Resp::error("Room not found or access denied"),
+        ),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to archive room: {}", err)),
+        ),
+    }
+}
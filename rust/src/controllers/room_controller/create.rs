@@ -0,0 +1,57 @@
+use crate::AppState;
+use crate::db::model::Room;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+const MAX_ROOM_NAME_LEN: usize = 100;
+
+#[derive(Deserialize)]
+pub struct RoomNameRequest {
+    pub name: String,
+}
+
+pub fn validate_room_name(name: &str) -> Result<&str, &'static str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Room name cannot be empty");
+    }
+    if name.chars().count() > MAX_ROOM_NAME_LEN {
+        return Err("Room name must be at most 100 characters");
+    }
+    Ok(name)
+}
+
+pub async fn create_room(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Json(params): Json<RoomNameRequest>,
+) -> impl IntoResponse {
+    let name = match validate_room_name(&params.name) {
+        Ok(name) => name,
+        Err(reason) => return (StatusCode::BAD_REQUEST, Resp::error(reason)),
+    };
+
+    let query = r#"
+        INSERT INTO rooms (name, "ownerId", created_at, updated_at)
+        VALUES ($1, $2, NOW(), NOW())
+        RETURNING *
+    "#;
+
+    let result = sqlx::query_as::<_, Room>(query)
+        .bind(name)
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await;
+
+    match result {
+        Ok(room) => Resp::success("Room created successfully", Some(room)),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to create room: {}", err)),
+        ),
+    }
+}
@@ -0,0 +1,20 @@
+use sqlx::{Pool, Postgres};
+
+use crate::db::model::Room;
+
+// Archived rooms are read-only, so every write path goes through this lookup
+pub async fn get_active_room(room_id: i64, db: &Pool<Postgres>) -> Option<Room> {
+    let result =
+        sqlx::query_as::<_, Room>("SELECT * FROM rooms WHERE id = $1 AND archived_at IS NULL")
+            .bind(room_id)
+            .fetch_optional(db)
+            .await;
+
+    match result {
+        Ok(room) => room,
+        Err(e) => {
+            eprintln!("Error loading room: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::AppState;
+use crate::db::model::Room;
+use crate::libs::Resp;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ListRoomsQuery {
+    pub archived: Option<bool>,
+}
+
+pub async fn list_rooms(
+    State(state): State<AppState>,
+    Query(params): Query<ListRoomsQuery>,
+) -> impl IntoResponse {
+    let include_archived = params.archived.unwrap_or(false);
+
+    let result = sqlx::query_as::<_, Room>(
+        "SELECT * FROM rooms WHERE $1 OR archived_at IS NULL ORDER BY id",
+    )
+    .bind(include_archived)
+    .fetch_all(&state.db)
+    .await;
+
+    match result {
+        Ok(rooms) => Resp::success("Rooms retrieved", Some(rooms)),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(err.to_string()),
+        ),
+    }
+}
@@ -0,0 +1,5 @@
+pub mod archive;
+pub mod create;
+pub mod get_room;
+pub mod list;
+pub mod rename;
@@ -0,0 +1,50 @@
+use crate::AppState;
+use crate::controllers::room_controller::create::{RoomNameRequest, validate_room_name};
+use crate::controllers::user_controller::get_user::is_moderator;
+use crate::db::model::Room;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+pub async fn rename_room(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Path(room_id): Path<i64>,
+    Json(params): Json<RoomNameRequest>,
+) -> impl IntoResponse {
+    let name = match validate_room_name(&params.name) {
+        Ok(name) => name,
+        Err(reason) => return (StatusCode::BAD_REQUEST, Resp::error(reason)),
+    };
+
+    // Only the owner may rename, and archived rooms stay frozen. Rooms without an owner
+    // (the seeded `general`, or one whose owner deleted their account) are up to moderators.
+    let moderator = is_moderator(user_id, &state.db).await;
+    let query = r#"
+        UPDATE rooms SET name = $1, updated_at = NOW()
+        WHERE id = $2 AND ("ownerId" = $3 OR ("ownerId" IS NULL AND $4)) AND archived_at IS NULL
+        RETURNING *
+    "#;
+
+    let result = sqlx::query_as::<_, Room>(query)
+        .bind(name)
+        .bind(room_id)
+        .bind(user_id)
+        .bind(moderator)
+        .fetch_optional(&state.db)
+        .await;
+
+    match result {
+        Ok(Some(room)) => Resp::success("Room renamed successfully", Some(room)),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Resp::error("Room not found or access denied"),
+        ),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(format!("Failed to rename room: {}", err)),
+        ),
+    }
+}
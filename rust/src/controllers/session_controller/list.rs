@@ -0,0 +1,43 @@
+use crate::AppState;
+use crate::controllers::session_controller::session::list_active_sessions;
+use crate::db::model::Session;
+use crate::extract::{SessionId, UserId};
+use crate::libs::Resp;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub session: Session,
+    // True for the session making this request
+    pub current: bool,
+}
+
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    SessionId(current): SessionId,
+) -> impl IntoResponse {
+    match list_active_sessions(&state.db, user_id).await {
+        Ok(sessions) => {
+            let sessions: Vec<SessionSummary> = sessions
+                .into_iter()
+                .map(|session| SessionSummary {
+                    current: Some(session.id) == current,
+                    session,
+                })
+                .collect();
+            Resp::success("fetching success", Some(sessions))
+        }
+        Err(err) => {
+            eprintln!("Failed to list sessions: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to list sessions"),
+            )
+        }
+    }
+}
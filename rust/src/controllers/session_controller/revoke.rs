@@ -0,0 +1,72 @@
+use crate::AppState;
+use crate::controllers::session_controller::session::{revoke_all_sessions, revoke_session};
+use crate::extract::{SessionId, UserId};
+use crate::libs::Resp;
+use crate::socket::handlers::disconnect_sessions;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct RevokedSessions {
+    pub revoked: Vec<Uuid>,
+}
+
+// Signs one device out: its refresh token stops working and its sockets are dropped
+pub async fn revoke_one_session(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Path(session_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match revoke_session(&state.db, user_id, session_id, "revoked").await {
+        Ok(true) => {
+            disconnect_sessions(&state.user_sockets, user_id, &[session_id]).await;
+            Resp::success(
+                "Session revoked",
+                Some(RevokedSessions { revoked: vec![session_id] }),
+            )
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, Resp::error("Session not found")),
+        Err(err) => {
+            eprintln!("Failed to revoke session: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to revoke session"),
+            )
+        }
+    }
+}
+
+// "Log out everywhere else": keeps only the session making the request
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    SessionId(current): SessionId,
+) -> impl IntoResponse {
+    // Without a session in the token we can't tell which one to keep
+    let current = match current {
+        Some(current) => current,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error("Log in again to manage sessions"),
+            );
+        }
+    };
+
+    match revoke_all_sessions(&state.db, user_id, Some(current), "revoked_by_user").await {
+        Ok(revoked) => {
+            disconnect_sessions(&state.user_sockets, user_id, &revoked).await;
+            Resp::success("Other sessions revoked", Some(RevokedSessions { revoked }))
+        }
+        Err(err) => {
+            eprintln!("Failed to revoke sessions: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to revoke sessions"),
+            )
+        }
+    }
+}
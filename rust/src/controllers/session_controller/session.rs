@@ -0,0 +1,329 @@
+use std::net::SocketAddr;
+
+use axum::http::{HeaderMap, header};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::db::model::Session;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::client_ip::client_ip;
+use crate::libs::crypto::{
+    AccessToken, generate_access_token, generate_refresh_token, hash_token, is_refresh_token,
+};
+use crate::libs::revocation::revoke_session_tokens;
+
+// Sliding lifetime: every rotation pushes the session's expiry out again
+pub const SESSION_LIFETIME_DAYS: i64 = 30;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("Invalid refresh token")]
+    InvalidToken,
+    #[error("Expected a refresh token")]
+    WrongTokenType,
+    #[error("Session expired or revoked")]
+    Inactive,
+    // Carries the session that was revoked for it, so its sockets can be signed out too
+    #[error("Refresh token reuse detected")]
+    Reused { user_id: i64, session_id: Uuid },
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to issue token: {0}")]
+    Token(String),
+}
+
+// Device details recorded on the session at login and on every refresh
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl ClientInfo {
+    // Behind a trusted proxy the peer address is the proxy; see libs::client_ip
+    pub fn from_request(headers: &HeaderMap, peer: SocketAddr) -> Self {
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(512).collect());
+
+        Self {
+            user_agent,
+            ip: Some(client_ip(headers, peer).to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct IssuedTokens {
+    pub session_id: Uuid,
+    pub token: String,
+    pub refresh_token: String,
+}
+
+fn access_token(user_id: i64, session_id: Uuid) -> Result<AccessToken, SessionError> {
+    generate_access_token(user_id, &session_id.to_string()).map_err(|err| SessionError::Token(err.to_string()))
+}
+
+// The access token's jti is kept with the refresh token so revoking the session can deny it
+async fn insert_refresh_token(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    session_id: Uuid,
+    refresh_token: &str,
+    access: &AccessToken,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"INSERT INTO refresh_tokens ("sessionId", token_hash, access_jti, access_expires_at, created_at)
+           VALUES ($1, $2, $3, $4, NOW())"#,
+    )
+    .bind(session_id)
+    .bind(hash_token(refresh_token))
+    .bind(&access.jti)
+    .bind(access.expires_at)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// Starts a new device session and hands out its first refresh token
+pub async fn create_session(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    client: &ClientInfo,
+) -> Result<IssuedTokens, SessionError> {
+    let session_id = Uuid::new_v4();
+    let token = access_token(user_id, session_id)?;
+    let refresh_token = generate_refresh_token();
+    let expires_at = Utc::now() + Duration::days(SESSION_LIFETIME_DAYS);
+
+    let mut tx = db.begin().await?;
+
+    sqlx::query(
+        r#"INSERT INTO sessions (id, "userId", user_agent, ip, expires_at, last_used_at, created_at)
+           VALUES ($1, $2, $3, $4, $5, NOW(), NOW())"#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(&client.user_agent)
+    .bind(&client.ip)
+    .bind(expires_at)
+    .execute(&mut *tx)
+    .await?;
+
+    insert_refresh_token(&mut tx, session_id, &refresh_token, &token).await?;
+
+    tx.commit().await?;
+
+    Ok(IssuedTokens {
+        session_id,
+        token: token.token,
+        refresh_token,
+    })
+}
+
+#[derive(sqlx::FromRow)]
+struct PresentedToken {
+    id: i64,
+    session_id: Uuid,
+    user_id: i64,
+    used: bool,
+    active: bool,
+}
+
+// Exchanges a refresh token for a new pair. Presenting a token that was already
+// rotated revokes the whole session, since either it or its successor has leaked.
+pub async fn rotate_refresh_token(
+    db: &Pool<Postgres>,
+    refresh_token: &str,
+    client: &ClientInfo,
+) -> Result<IssuedTokens, SessionError> {
+    if !is_refresh_token(refresh_token) {
+        return Err(SessionError::WrongTokenType);
+    }
+
+    let mut tx = db.begin().await?;
+
+    // Row locks serialise concurrent refreshes of the same token
+    let presented = sqlx::query_as::<_, PresentedToken>(
+        r#"
+        SELECT t.id, t."sessionId" as session_id, s."userId" as user_id,
+               t.used_at IS NOT NULL as used,
+               (s.revoked_at IS NULL AND s.expires_at > NOW()) as active
+        FROM refresh_tokens as t
+        INNER JOIN sessions as s on s.id = t."sessionId"
+        WHERE t.token_hash = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(hash_token(refresh_token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(SessionError::InvalidToken)?;
+
+    if !presented.active {
+        return Err(SessionError::Inactive);
+    }
+
+    if presented.used {
+        sqlx::query(
+            "UPDATE sessions SET revoked_at = NOW(), revoked_reason = 'token_reuse' WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(presented.session_id)
+        .execute(&mut *tx)
+        .await?;
+        record_audit(
+            &mut *tx,
+            AuditEntry {
+                actor_id: Some(presented.user_id),
+                action: "session.refresh_reuse",
+                target_type: "user",
+                target_id: Some(presented.user_id),
+                metadata: serde_json::json!({
+                    "sessionId": presented.session_id,
+                    "ip": client.ip,
+                    "userAgent": client.user_agent,
+                }),
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        revoke_session_tokens(db, &[presented.session_id]).await?;
+        return Err(SessionError::Reused {
+            user_id: presented.user_id,
+            session_id: presented.session_id,
+        });
+    }
+
+    let token = access_token(presented.user_id, presented.session_id)?;
+    let next_refresh_token = generate_refresh_token();
+
+    sqlx::query("UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1")
+        .bind(presented.id)
+        .execute(&mut *tx)
+        .await?;
+
+    insert_refresh_token(&mut tx, presented.session_id, &next_refresh_token, &token).await?;
+
+    sqlx::query(
+        r#"UPDATE sessions
+           SET last_used_at = NOW(), expires_at = $2,
+               user_agent = COALESCE($3, user_agent), ip = COALESCE($4, ip)
+           WHERE id = $1"#,
+    )
+    .bind(presented.session_id)
+    .bind(Utc::now() + Duration::days(SESSION_LIFETIME_DAYS))
+    .bind(&client.user_agent)
+    .bind(&client.ip)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(IssuedTokens {
+        session_id: presented.session_id,
+        token: token.token,
+        refresh_token: next_refresh_token,
+    })
+}
+
+// Revokes the session the refresh token belongs to, denying its unexpired access tokens too.
+// None when the user owns no such active session
+pub async fn revoke_session_by_token(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    refresh_token: &str,
+    reason: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let revoked = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE sessions as s SET revoked_at = NOW(), revoked_reason = $3
+        FROM refresh_tokens as t
+        WHERE t."sessionId" = s.id AND t.token_hash = $1 AND s."userId" = $2 AND s.revoked_at IS NULL
+        RETURNING s.id
+        "#,
+    )
+    .bind(hash_token(refresh_token))
+    .bind(user_id)
+    .bind(reason)
+    .fetch_optional(db)
+    .await?;
+
+    if let Some(session_id) = revoked {
+        revoke_session_tokens(db, &[session_id]).await?;
+    }
+    Ok(revoked)
+}
+
+// Revokes one session of the user; false when it isn't theirs or is already gone
+pub async fn revoke_session(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    session_id: Uuid,
+    reason: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"UPDATE sessions SET revoked_at = NOW(), revoked_reason = $3 WHERE id = $1 AND "userId" = $2 AND revoked_at IS NULL"#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(reason)
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    revoke_session_tokens(db, &[session_id]).await?;
+    Ok(true)
+}
+
+// Revokes every session of the user except `keep`, returning the revoked IDs
+pub async fn revoke_all_sessions(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    keep: Option<Uuid>,
+    reason: &str,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let revoked = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE sessions SET revoked_at = NOW(), revoked_reason = $3
+        WHERE "userId" = $1 AND revoked_at IS NULL AND ($2::UUID IS NULL OR id <> $2)
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(keep)
+    .bind(reason)
+    .fetch_all(db)
+    .await?;
+
+    revoke_session_tokens(db, &revoked).await?;
+    Ok(revoked)
+}
+
+pub async fn list_active_sessions(db: &Pool<Postgres>, user_id: i64) -> Result<Vec<Session>, sqlx::Error> {
+    sqlx::query_as::<_, Session>(
+        r#"SELECT * FROM sessions WHERE "userId" = $1 AND revoked_at IS NULL AND expires_at > NOW() ORDER BY last_used_at DESC"#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn is_session_active(db: &Pool<Postgres>, session_id: Uuid) -> bool {
+    let result = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM sessions WHERE id = $1 AND revoked_at IS NULL AND expires_at > NOW())",
+    )
+    .bind(session_id)
+    .fetch_one(db)
+    .await;
+
+    match result {
+        Ok(active) => active,
+        Err(e) => {
+            eprintln!("Error checking session: {}", e);
+            false
+        }
+    }
+}
@@ -30,8 +30,8 @@ pub async fn check_available(
     let result = get_user_by_email(email, nickname, state.db).await;
 
     if result.is_none() {
-        return Resp::success("user not found", result);
+        Resp::success("user not found", result)
     } else {
-        return (StatusCode::OK, Resp::error("user already exist"));
-    };
+        (StatusCode::OK, Resp::error("user already exist"))
+    }
 }
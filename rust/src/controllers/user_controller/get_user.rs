@@ -27,7 +27,7 @@ pub async fn get_user_by_email(
     let connection = &db;
 
     let results =
-        sqlx::query_as::<_, User>("SELECT * FROM users where email = $1 OR nickname = $2")
+        sqlx::query_as::<_, User>("SELECT * FROM users where LOWER(email) = LOWER(TRIM($1)) OR LOWER(nickname) = LOWER($2)")
             .bind(email)
             .bind(nickname)
             .fetch_one(connection)
@@ -41,3 +41,20 @@ pub async fn get_user_by_email(
         }
     }
 }
+
+// Moderators and admins may act on other users' messages
+pub async fn is_moderator(user_id: i64, db: &Pool<Postgres>) -> bool {
+    let result = sqlx::query_scalar::<_, String>("SELECT role FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(db)
+        .await;
+
+    match result {
+        Ok(Some(role)) => role == "moderator" || role == "admin",
+        Ok(None) => false,
+        Err(e) => {
+            eprintln!("Error loading user role: {}", e);
+            false
+        }
+    }
+}
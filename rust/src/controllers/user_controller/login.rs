@@ -1,14 +1,21 @@
 use crate::AppState;
+use crate::controllers::email_controller::verification::VerificationPolicy;
+use crate::controllers::lockout_controller::guard::{
+    LoginKey, clear_failed_logins, login_retry_after, record_failed_login, verify_password_or_dummy,
+};
+use crate::controllers::mfa_controller::totp::is_mfa_enabled;
 use crate::controllers::user_controller::get_user::get_user_by_email;
 use crate::db::model::User;
 use crate::libs::Resp;
-use crate::libs::crypto::generate_jwt;
-use argon2::{Argon2, PasswordHash, PasswordVerifier};
-use axum::extract::{Json, State};
+use crate::libs::crypto::generate_mfa_pending_token;
+use crate::controllers::session_controller::session::{ClientInfo, create_session};
+use axum::extract::{ConnectInfo, Json, State};
+use axum::http::HeaderMap;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use serde::Deserialize;
-use sqlx::{Pool, Postgres};
+use std::net::SocketAddr;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct UserLogin {
@@ -19,62 +26,34 @@ pub struct UserLogin {
 #[derive(serde::Serialize)]
 pub struct LoginResponse {
     pub user: Option<User>,
+    pub session_id: Uuid,
     pub token: String,
     pub refresh_token: String,
 }
 
-fn update_user_refresh_token(user_id: i64, new_refresh_token: String, db: Pool<Postgres>) {
-    println!("Attempting to update refresh token for user: {}", user_id);
-    // This blocks the current thread - avoid in async contexts
-    let rt = tokio::runtime::Handle::current();
-    rt.block_on(async {
-        let connection = &db;
-
-        let update_query = sqlx::query("UPDATE users SET refresh_token = $1 WHERE id = $2") // Fixed SQL
-            .bind(new_refresh_token)
-            .bind(user_id)
-            .execute(connection)
-            .await;
-
-        match update_query {
-            Ok(row) => {
-                println!(
-                    "Success updating user {}, {} row affected",
-                    user_id,
-                    row.rows_affected()
-                )
-            }
-            Err(e) => {
-                println!(
-                    "Error while trying to update: user {}, reason : {}",
-                    user_id,
-                    e.to_string()
-                );
-            }
-        }
-    });
+// Accounts with 2FA get no tokens yet, only what /v1/login/mfa needs
+#[derive(serde::Serialize)]
+pub struct MfaChallenge {
+    pub mfa_required: bool,
+    pub mfa_token: String,
 }
 
-pub async fn save_refresh_token(user_id: i64, new_refresh_token: String, db: Pool<Postgres>) {
-    println!("Current thread: {:?}", std::thread::current().id());
-    match tokio::task::spawn_blocking(move || {
-        println!("Blocking task thread: {:?}", std::thread::current().id());
-        update_user_refresh_token(user_id, new_refresh_token, db);
-    })
-    .await
-    {
-        Ok(_) => {
-            println!("✅ [Handle] multi thread execution success");
-        }
-        Err(e) => {
-            eprintln!("❌ [Handle] multi thread execution error: {}", e);
-        }
-    }
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+pub enum LoginResult {
+    // Boxed since it is much larger than the challenge
+    Tokens(Box<LoginResponse>),
+    MfaRequired(MfaChallenge),
 }
 
+// One message for unknown accounts and wrong passwords, so it can't be used to probe for accounts
+const INVALID_CREDENTIALS: &str = "Invalid email or password";
+
 //main function
 pub async fn login_by_email(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(params): Json<UserLogin>,
 ) -> impl IntoResponse {
     if params.email.is_empty() || params.password.is_empty() {
@@ -84,61 +63,110 @@ pub async fn login_by_email(
         );
     }
 
+    let client = ClientInfo::from_request(&headers, peer);
     let user =
         get_user_by_email(params.email.clone(), params.email.clone(), state.db.clone()).await;
+    // Names that match no account are throttled like real ones, so the answers can't tell them apart
+    let account = match &user {
+        Some(user) => LoginKey::User(user.id),
+        None => LoginKey::unknown(&params.email),
+    };
 
-    if user.is_none() {
-        return (StatusCode::BAD_REQUEST, Resp::error("User not found"));
+    match login_retry_after(&state.db, &account, &client).await {
+        Ok(None) => {}
+        Ok(Some(secs)) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                Resp::error(format!("Too many failed attempts, try again in {} seconds", secs)),
+            );
+        }
+        Err(e) => {
+            eprintln!("Failed to check login failures: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Authentication error"),
+            );
+        }
     }
 
+    // Unknown accounts and accounts without a password still pay for a hash check
+    let password_valid = verify_password_or_dummy(
+        user.as_ref().and_then(|user| user.password.as_deref()),
+        &params.password,
+    );
     let user = match user {
-        Some(user) => user,
-        None => return (StatusCode::BAD_REQUEST, Resp::error("User not found")),
+        Some(user) if password_valid => user,
+        _ => {
+            if let Err(e) = record_failed_login(&state.db, &state.mailer, &account, &client).await {
+                eprintln!("Failed to record login failure: {}", e);
+            }
+            return (
+                StatusCode::UNAUTHORIZED,
+                Resp::error(INVALID_CREDENTIALS),
+            );
+        }
     };
 
-    let password_valid = match &user.password {
-        Some(hashed_password) => match PasswordHash::new(hashed_password) {
-            Ok(parsed_hash) => Argon2::default()
-                .verify_password(params.password.as_bytes(), &parsed_hash)
-                .is_ok(),
-            Err(_) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Resp::error("Authentication error"),
-                );
-            }
-        },
-        None => {
+    // Checked after the password so the answer doesn't reveal unverified accounts
+    if user.email_verified_at.is_none() && VerificationPolicy::from_env().blocks_login() {
+        return (
+            StatusCode::FORBIDDEN,
+            Resp::error("Verify your email address before logging in"),
+        );
+    }
+
+    match is_mfa_enabled(&state.db, user.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return match generate_mfa_pending_token(user.id) {
+                Ok(mfa_token) => Resp::success(
+                    "Enter the code from your authenticator app",
+                    Some(LoginResult::MfaRequired(MfaChallenge {
+                        mfa_required: true,
+                        mfa_token,
+                    })),
+                ),
+                Err(e) => {
+                    eprintln!("Failed to issue MFA token: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Resp::error("Authentication error"),
+                    )
+                }
+            };
+        }
+        Err(e) => {
+            eprintln!("Failed to check two-factor status: {}", e);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Resp::error("Authentication error"),
             );
         }
-    };
-    if password_valid {
-        let jwt = generate_jwt(user.id);
-        match jwt {
-            Ok(res) => {
-                tokio::spawn(save_refresh_token(
-                    user.id,
-                    res.refresh_token.clone(),
-                    state.db.clone(),
-                ));
-                return Resp::success(
-                    "Login Success",
-                    Some(LoginResponse {
-                        user: Some(user),
-                        token: res.token, // or jwt.token if it has a field named token
-                        refresh_token: res.refresh_token,
-                    }),
-                );
-            }
-            Err(e) => (StatusCode::UNAUTHORIZED, Resp::error(e.to_string())),
+    }
+
+    // Only a finished sign-in resets the count; with 2FA that happens at /login/mfa,
+    // so knowing the password doesn't buy fresh code guesses
+    if let Err(e) = clear_failed_logins(&state.db, &account).await {
+        eprintln!("Failed to clear login failures: {}", e);
+    }
+
+    // Each login is its own device session, so other devices stay signed in
+    match create_session(&state.db, user.id, &client).await {
+        Ok(issued) => Resp::success(
+            "Login Success",
+            Some(LoginResult::Tokens(Box::new(LoginResponse {
+                user: Some(user),
+                session_id: issued.session_id,
+                token: issued.token,
+                refresh_token: issued.refresh_token,
+            }))),
+        ),
+        Err(e) => {
+            eprintln!("Failed to create session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to create session"),
+            )
         }
-    } else {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Resp::error("Wrong password"),
-        )
     }
 }
@@ -1,36 +1,33 @@
 use crate::AppState;
+use crate::controllers::session_controller::session::{revoke_all_sessions, revoke_session_by_token};
 use crate::libs::Resp;
 use crate::extract::UserId;
+use crate::socket::handlers::disconnect_sessions;
 use axum::extract::{State, Json};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use serde::{Deserialize, Serialize};
-use sqlx::Pool;
-use sqlx::Postgres;
+use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct LogoutRequest {
     pub refresh_token: String,
 }
 
+// Ends the session (device) the refresh token belongs to
 pub async fn logout_user(
     State(state): State<AppState>,
     UserId(user_id): UserId,
     Json(params): Json<LogoutRequest>,
 ) -> impl IntoResponse {
-    let connection = &state.db;
-
-    // Invalidate the refresh token by setting it to NULL
-    let result = sqlx::query(
-        "UPDATE users SET refresh_token = NULL WHERE id = $1 AND refresh_token = $2"
-    )
-    .bind(user_id)
-    .bind(&params.refresh_token)
-    .execute(connection)
-    .await;
+    let result = revoke_session_by_token(&state.db, user_id, &params.refresh_token, "logout").await;
 
     match result {
-        Ok(_) => Resp::success("User logged out successfully", None::<()>),
+        Ok(revoked) => {
+            if let Some(session_id) = revoked {
+                disconnect_sessions(&state.user_sockets, user_id, &[session_id]).await;
+            }
+            Resp::success("User logged out successfully", None::<()>)
+        }
         Err(err) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Resp::error(format!("Failed to logout: {}", err)),
@@ -38,25 +35,21 @@ pub async fn logout_user(
     }
 }
 
-// Alternative logout without refresh token (just invalidate the user's refresh token)
+// Alternative logout without refresh token (ends every session of the user)
 pub async fn logout_user_simple(
     State(state): State<AppState>,
     UserId(user_id): UserId,
 ) -> impl IntoResponse {
-    let connection = &state.db;
-
-    let result = sqlx::query(
-        "UPDATE users SET refresh_token = NULL WHERE id = $1"
-    )
-    .bind(user_id)
-    .execute(connection)
-    .await;
+    let result = revoke_all_sessions(&state.db, user_id, None, "logout_all").await;
 
     match result {
-        Ok(_) => Resp::success("User logged out successfully", None::<()>),
+        Ok(revoked) => {
+            disconnect_sessions(&state.user_sockets, user_id, &revoked).await;
+            Resp::success("User logged out successfully", None::<()>)
+        }
         Err(err) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Resp::error(format!("Failed to logout: {}", err)),
         ),
     }
-}
\ No newline at end of file
+}
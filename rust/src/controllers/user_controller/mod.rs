@@ -2,6 +2,7 @@ pub mod check;
 pub mod get_user;
 pub mod login;
 pub mod logout;
+pub mod profile;
 pub mod refresh_token;
 pub mod register;
 pub mod tag;
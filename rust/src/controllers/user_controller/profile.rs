@@ -0,0 +1,236 @@
+use crate::AppState;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::extract::UserId;
+use crate::libs::Resp;
+use crate::libs::audit::{AuditEntry, record_audit};
+use crate::libs::avatar::{AvatarType, generate_avatar, generated_style};
+use crate::libs::nickname::{nickname_cooldown_days, validate_nickname};
+use crate::socket::auth::rename_socket_user;
+use crate::socket::events::emit_profile_updated;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+pub const MAX_DISPLAY_NAME_LEN: usize = 64;
+pub const MAX_BIO_LEN: usize = 280;
+
+// Missing fields are left alone; an empty display_name or bio clears it
+#[derive(Deserialize)]
+pub struct UpdateProfile {
+    pub nickname: Option<String>,
+    pub avatar_style: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Profile {
+    pub id: i64,
+    pub nickname: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+    pub nickname_changed_at: Option<DateTime<Utc>>,
+}
+
+// Trimmed, with empty meaning "clear"; None when it is too long or has control characters
+// (line breaks are allowed where `multiline`)
+fn optional_text(value: &str, max_len: usize, multiline: bool) -> Option<Option<String>> {
+    let value = value.trim();
+    if value.chars().count() > max_len || value.chars().any(|c| c.is_control() && !(multiline && c == '\n')) {
+        return None;
+    }
+    Some(Some(value.to_string()).filter(|value| !value.is_empty()))
+}
+
+pub async fn update_profile(
+    State(state): State<AppState>,
+    UserId(user_id): UserId,
+    Json(params): Json<UpdateProfile>,
+) -> impl IntoResponse {
+    if params.nickname.is_none()
+        && params.avatar_style.is_none()
+        && params.display_name.is_none()
+        && params.bio.is_none()
+    {
+        return (StatusCode::BAD_REQUEST, Resp::error("Nothing to update"));
+    }
+
+    let user = match get_user_by_auth_id(user_id, state.db.clone()).await {
+        Some(user) if user.deleted_at.is_none() => user,
+        _ => return (StatusCode::NOT_FOUND, Resp::error("User not found")),
+    };
+
+    let nickname = params.nickname.as_deref().map(str::trim);
+    let renamed = nickname.filter(|nickname| user.nickname.as_deref() != Some(*nickname));
+    if let Some(nickname) = renamed {
+        if let Err(err) = validate_nickname(nickname) {
+            return (StatusCode::BAD_REQUEST, Resp::error(err.to_string()));
+        }
+        if let Some(changed_at) = user.nickname_changed_at {
+            let next_change = changed_at + Duration::days(nickname_cooldown_days());
+            if next_change > Utc::now() {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Resp::error(format!(
+                        "You can change your nickname again on {}",
+                        next_change.format("%Y-%m-%d")
+                    )),
+                );
+            }
+        }
+    }
+
+    let avatar_style = match params.avatar_style.as_deref().map(AvatarType::parse) {
+        None => None,
+        Some(Some(style)) => Some(style),
+        Some(None) => {
+            let styles: Vec<&str> = AvatarType::ALL.iter().map(|style| style.as_str()).collect();
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error(format!("Avatar style must be one of: {}", styles.join(", "))),
+            );
+        }
+    };
+
+    let display_name = params
+        .display_name
+        .as_deref()
+        .map(|value| optional_text(value, MAX_DISPLAY_NAME_LEN, false));
+    let display_name = match display_name {
+        None => user.display_name.clone(),
+        Some(Some(display_name)) => display_name,
+        Some(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error(format!(
+                    "Display name must have at most {} characters and no line breaks",
+                    MAX_DISPLAY_NAME_LEN
+                )),
+            );
+        }
+    };
+    let bio = match params.bio.as_deref().map(|value| optional_text(value, MAX_BIO_LEN, true)) {
+        None => user.bio.clone(),
+        Some(Some(bio)) => bio,
+        Some(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Resp::error(format!("Bio must have at most {} characters", MAX_BIO_LEN)),
+            );
+        }
+    };
+
+    // Generated avatars are seeded with the nickname, so a rename redraws them in the same style.
+    // Uploaded pictures are only replaced when a style is picked.
+    let new_nickname = renamed.or(user.nickname.as_deref());
+    let avatar = match avatar_style.or_else(|| renamed.and(user.avatar.as_deref().and_then(generated_style))) {
+        Some(style) => Some(generate_avatar(new_nickname.unwrap_or_default().to_string(), style)),
+        None => user.avatar.clone(),
+    };
+
+    let changes = ProfileChanges {
+        nickname: new_nickname,
+        renamed: renamed.is_some(),
+        avatar: avatar.as_deref(),
+        display_name: display_name.as_deref(),
+        bio: bio.as_deref(),
+    };
+    let profile = match save_profile(&state.db, user.id, user.nickname.as_deref(), &changes).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return (StatusCode::CONFLICT, Resp::error("Nickname is already taken")),
+        Err(e) => {
+            eprintln!("Failed to update profile: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to update profile"),
+            );
+        }
+    };
+
+    if let Some(nickname) = renamed {
+        rename_socket_user(&state.user_sockets, user.id, nickname).await;
+    }
+    emit_profile_updated(&state.io, &profile).await;
+
+    Resp::success("Profile updated", Some(profile))
+}
+
+struct ProfileChanges<'a> {
+    nickname: Option<&'a str>,
+    renamed: bool,
+    avatar: Option<&'a str>,
+    display_name: Option<&'a str>,
+    bio: Option<&'a str>,
+}
+
+// None when the new nickname belongs to someone else
+async fn save_profile(
+    db: &Pool<Postgres>,
+    user_id: i64,
+    previous_nickname: Option<&str>,
+    changes: &ProfileChanges<'_>,
+) -> Result<Option<Profile>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    if changes.renamed {
+        // Case-insensitive, so `Alice` can't pose as `alice`
+        let taken = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM users WHERE LOWER(nickname) = LOWER($1) AND id <> $2)",
+        )
+        .bind(changes.nickname)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if taken {
+            return Ok(None);
+        }
+    }
+
+    // The unique index catches a rename that raced past the check above
+    let profile = sqlx::query_as::<_, Profile>(
+        r#"
+        UPDATE users SET
+            nickname = $2, avatar = $3, display_name = $4, bio = $5,
+            nickname_changed_at = CASE WHEN $6 THEN NOW() ELSE nickname_changed_at END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, nickname, display_name, bio, avatar, nickname_changed_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(changes.nickname)
+    .bind(changes.avatar)
+    .bind(changes.display_name)
+    .bind(changes.bio)
+    .bind(changes.renamed)
+    .fetch_one(&mut *tx)
+    .await;
+    let profile = match profile {
+        Ok(profile) => profile,
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    record_audit(
+        &mut *tx,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.profile_updated",
+            target_type: "user",
+            target_id: Some(user_id),
+            metadata: if changes.renamed {
+                serde_json::json!({ "nicknameFrom": previous_nickname, "nicknameTo": changes.nickname })
+            } else {
+                serde_json::json!({})
+            },
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(profile))
+}
@@ -1,69 +1,41 @@
 use crate::AppState;
-use crate::db::model::User;
-use crate::libs::{Resp, crypto::generate_jwt};
-use axum::extract::State;
-use axum::http::StatusCode;
+use crate::controllers::session_controller::session::{ClientInfo, SessionError, rotate_refresh_token};
+use crate::libs::Resp;
+use crate::socket::handlers::disconnect_sessions;
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
-use sqlx::Pool;
-use sqlx::Postgres;
-
-#[derive(serde::Serialize)]
-pub struct RefreshTokenResponse {
-    pub token: String,
-    pub refresh_token: String,
-}
+use std::net::SocketAddr;
 
+// The body is the bare refresh token
 pub async fn refresh_token(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     refresh_token: String,
 ) -> impl IntoResponse {
-    let connection = &state.db;
-
-    // Find user with this refresh token
-    let user = sqlx::query_as::<_, User>(
-        "SELECT id, email, nickname, avatar FROM users WHERE refresh_token = $1"
-    )
-    .bind(&refresh_token)
-    .fetch_optional(connection)
-    .await;
-
-    match user {
-        Some(user) => {
-            // Generate new tokens
-            match generate_jwt(user.id) {
-                Ok(new_tokens) => {
-                    // Update refresh token in database
-                    let update_result = sqlx::query(
-                        "UPDATE users SET refresh_token = $1 WHERE id = $2"
-                    )
-                    .bind(&new_tokens.refresh_token)
-                    .bind(user.id)
-                    .execute(connection)
-                    .await;
+    let client = ClientInfo::from_request(&headers, peer);
 
-                    match update_result {
-                        Ok(_) => Resp::success(
-                            "Token refreshed successfully",
-                            Some(RefreshTokenResponse {
-                                token: new_tokens.token,
-                                refresh_token: new_tokens.refresh_token,
-                            }),
-                        ),
-                        Err(err) => (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Resp::error(format!("Failed to update refresh token: {}", err)),
-                        ),
-                    }
-                }
-                Err(err) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Resp::error(format!("Failed to generate new tokens: {}", err)),
-                ),
-            }
+    match rotate_refresh_token(&state.db, refresh_token.trim(), &client).await {
+        Ok(tokens) => Resp::success("Token refreshed successfully", Some(tokens)),
+        Err(err @ SessionError::Reused { user_id, session_id }) => {
+            // Whoever holds the leaked token may also have opened sockets with the session
+            disconnect_sessions(&state.user_sockets, user_id, &[session_id]).await;
+            (StatusCode::UNAUTHORIZED, Resp::error(err.to_string()))
+        }
+        Err(
+            err @ (SessionError::InvalidToken
+            | SessionError::WrongTokenType
+            | SessionError::Inactive),
+        ) => {
+            (StatusCode::UNAUTHORIZED, Resp::error(err.to_string()))
+        }
+        Err(err) => {
+            eprintln!("Failed to refresh token: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Resp::error("Failed to refresh token"),
+            )
         }
-        None => (
-            StatusCode::UNAUTHORIZED,
-            Resp::error("Invalid refresh token"),
-        ),
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,11 @@
 use crate::AppState;
+use crate::controllers::email_controller::verification::send_verification_email;
 use crate::libs::Resp;
 use crate::libs::avatar::AvatarType;
 use crate::libs::avatar::generate_avatar;
-use argon2::{
-    Argon2,
-    password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
-};
+use crate::libs::crypto::hash_password;
+use crate::libs::nickname::validate_nickname;
+use crate::libs::password_policy::PasswordPolicy;
 use axum::extract::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -25,17 +25,24 @@ pub async fn register_user(
     Json(params): Json<UserRegistration>,
 ) -> impl IntoResponse {
     let connection = &state.db;
-    let password = params.password.as_bytes();
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
+    if let Err(err) = validate_nickname(&params.nickname) {
+        return (StatusCode::BAD_REQUEST, Resp::error(err.to_string()));
+    }
+    // Stored lowercased, so the case-insensitive lookups elsewhere find exactly one account
+    let email = params.email.trim().to_lowercase();
+    if email.len() > 255 || !email.contains('@') {
+        return (StatusCode::BAD_REQUEST, Resp::error("A valid email address is required"));
+    }
+    if let Err(err) = PasswordPolicy::from_env().check(&params.password, &[&params.nickname, &email]) {
+        return (StatusCode::BAD_REQUEST, Resp::error(err.to_string()));
+    }
+
     let avatar = params.avatar.unwrap_or(generate_avatar(
         params.nickname.clone(),
         AvatarType::Identicon,
     ));
-    let password_hash = match argon2
-        .hash_password(password, &salt)
-    {
-        Ok(hash) => hash.to_string(),
+    let password_hash = match hash_password(&params.password) {
+        Ok(hash) => hash,
         Err(e) => {
             eprintln!("Failed to hash password: {}", e);
             return (
@@ -45,21 +52,36 @@ pub async fn register_user(
         }
     };
 
-    let results =
-        sqlx::query("INSERT INTO users (email, nickname, password, avatar) VALUES ($1,$2,$3,$4)")
-            .bind(params.email)
-            .bind(params.nickname)
-            .bind(password_hash)
-            .bind(avatar)
-            .execute(connection)
-            .await;
+    let results = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO users (email, nickname, password, avatar) VALUES ($1,$2,$3,$4) RETURNING id",
+    )
+    .bind(&email)
+    .bind(params.nickname)
+    .bind(password_hash)
+    .bind(avatar)
+    .fetch_one(connection)
+    .await;
     match results {
-        Ok(row) => Resp::success("User registered successfully", None::<()>),
+        Ok(user_id) => {
+            // Registration still succeeds if the mail can't be queued; the user can ask for a resend
+            if let Err(e) = send_verification_email(connection, &state.mailer, user_id, &email).await {
+                eprintln!("Failed to send verification email: {}", e);
+            }
+            Resp::success("User registered successfully, check your email to verify it", None::<()>)
+        }
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            let message = match err.constraint() {
+                Some("users_email_lower_key") => "Email is already registered",
+                Some("users_nickname_lower_key") => "Nickname is already taken",
+                _ => "Email or nickname is already taken",
+            };
+            (StatusCode::CONFLICT, Resp::error(message))
+        }
         Err(e) => {
             eprintln!("Error registering user: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Resp::error(e.to_string()),
+                Resp::error("Failed to register user"),
             )
         }
     }
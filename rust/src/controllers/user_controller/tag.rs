@@ -27,13 +27,11 @@ pub async fn get_tag(State(state): State<AppState>, Path(tag): Path<String>) ->
     match result {
         Ok(users) => {
             let nicknames: Vec<String> = users.iter().map(|data| data.nickname.clone()).collect();
-            return Resp::success("get tags success", Some(nicknames));
-        }
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Resp::error(err.to_string()),
-            );
+            Resp::success("get tags success", Some(nicknames))
         }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Resp::error(err.to_string()),
+        ),
     }
 }
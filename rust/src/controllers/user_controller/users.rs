@@ -18,14 +18,10 @@ pub async fn get_one_user(
         .await;
 
     match results {
-        Ok(user) => {
-            return Resp::success("Ok", Some(user));
-        }
-        Err(err) => {
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Resp::error(err.to_string()),
-            );
-        }
+        Ok(user) => Resp::success("Ok", Some(user)),
+        Err(err) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Resp::error(err.to_string()),
+        ),
     }
 }
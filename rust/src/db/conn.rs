@@ -3,8 +3,6 @@ use std::env;
 use dotenvy::dotenv;
 use sqlx::{Pool, Postgres, postgres::PgPoolOptions};
 
-use crate::AppState;
-
 pub async fn create_connection() -> Pool<Postgres> {
     dotenv().ok();
     let database_url = match env::var("DATABASE_URL") {
@@ -15,7 +13,7 @@ pub async fn create_connection() -> Pool<Postgres> {
         }
     };
 
-    match PgPoolOptions::new()
+    let pool = match PgPoolOptions::new()
         .max_connections(5)
         .connect(&database_url)
         .await
@@ -25,5 +23,12 @@ pub async fn create_connection() -> Pool<Postgres> {
             eprintln!("Failed to connect to database: {}", e);
             std::process::exit(1);
         }
+    };
+
+    if let Err(e) = sqlx::migrate!("./migrations").run(&pool).await {
+        eprintln!("Failed to run database migrations: {}", e);
+        std::process::exit(1);
     }
+
+    pool
 }
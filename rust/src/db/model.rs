@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, sqlx::FromRow)]
 pub struct User {
@@ -15,6 +15,21 @@ pub struct User {
     #[serde(rename = "authId")]
     #[sqlx(rename = "authId")]
     pub auth_id: Option<String>,
+    #[sqlx(default)]
+    pub role: Option<String>,
+    #[sqlx(default)]
+    pub email_verified_at: Option<DateTime<Utc>>,
+    // Set while a deletion request is in its grace period
+    #[sqlx(default)]
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub display_name: Option<String>,
+    #[sqlx(default)]
+    pub bio: Option<String>,
+    #[sqlx(default)]
+    pub nickname_changed_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
@@ -32,6 +47,161 @@ pub struct Chat {
     #[serde(rename = "replyId")]
     #[sqlx(rename = "replyId")]
     pub reply_id: Option<i64>,
-    pub user: Option<User>,
-    pub reply: Option<Chat>,
+    #[serde(rename = "roomId")]
+    #[sqlx(rename = "roomId")]
+    pub room_id: Option<i64>,
+    #[serde(rename = "attachmentId")]
+    #[sqlx(rename = "attachmentId", default)]
+    pub attachment_id: Option<uuid::Uuid>,
+    #[sqlx(default)]
+    pub edited: bool,
+    #[sqlx(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(rename = "deletedBy")]
+    #[sqlx(rename = "deletedBy", default)]
+    pub deleted_by: Option<i64>,
+    #[sqlx(default)]
+    pub preview: Option<sqlx::types::Json<AttachmentPreview>>,
+    // Built with json_build_object by the listing queries; absent on freshly inserted rows
+    #[sqlx(default)]
+    pub user: Option<sqlx::types::Json<ChatAuthor>>,
+    #[sqlx(default)]
+    pub reply: Option<sqlx::types::Json<ChatReply>>,
+}
+
+// Author summary embedded in chat responses
+#[derive(Serialize, Deserialize)]
+pub struct ChatAuthor {
+    #[serde(rename = "userId")]
+    pub user_id: i64,
+    pub nickname: Option<String>,
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+}
+
+// Parent of a reply; every field is null when the message isn't one
+#[derive(Serialize, Deserialize)]
+pub struct ChatReply {
+    pub id: Option<i64>,
+    pub message: Option<String>,
+    pub attachment: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Room {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "ownerId")]
+    #[sqlx(rename = "ownerId")]
+    pub owner_id: Option<i64>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct DirectConversation {
+    pub id: i64,
+    #[serde(rename = "userAId")]
+    #[sqlx(rename = "userAId")]
+    pub user_a_id: i64,
+    #[serde(rename = "userBId")]
+    #[sqlx(rename = "userBId")]
+    pub user_b_id: i64,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct DirectMessage {
+    pub id: i64,
+    #[serde(rename = "conversationId")]
+    #[sqlx(rename = "conversationId")]
+    pub conversation_id: i64,
+    #[serde(rename = "userId")]
+    #[sqlx(rename = "userId")]
+    pub user_id: i64,
+    pub message: String,
+    pub attachment: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct ChatRevision {
+    pub id: i64,
+    #[serde(rename = "chatId")]
+    #[sqlx(rename = "chatId")]
+    pub chat_id: i64,
+    #[serde(rename = "editorId")]
+    #[sqlx(rename = "editorId")]
+    pub editor_id: Option<i64>,
+    pub message: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Attachment {
+    pub id: uuid::Uuid,
+    #[serde(rename = "ownerId")]
+    #[sqlx(rename = "ownerId")]
+    pub owner_id: Option<i64>,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub blurhash: Option<String>,
+    #[serde(skip)]
+    pub thumbnail_key: Option<String>,
+    #[serde(skip)]
+    pub thumbnail_content_type: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Compact attachment summary embedded in chat responses
+#[derive(Serialize, Deserialize)]
+pub struct AttachmentPreview {
+    pub id: uuid::Uuid,
+    pub url: String,
+    #[serde(rename = "thumbnailUrl")]
+    pub thumbnail_url: Option<String>,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub blurhash: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Session {
+    pub id: uuid::Uuid,
+    #[serde(rename = "userId")]
+    #[sqlx(rename = "userId")]
+    pub user_id: i64,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_reason: Option<String>,
+    pub last_used_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Passkey {
+    pub id: i64,
+    #[serde(rename = "userId")]
+    #[sqlx(rename = "userId")]
+    pub user_id: i64,
+    pub credential_id: String,
+    #[serde(skip)]
+    pub public_key: Vec<u8>,
+    #[serde(skip)]
+    pub sign_count: i64,
+    pub name: Option<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
 }
@@ -1,30 +1,23 @@
-use axum::extract::FromRequest;
-use axum::http::Request;
-use axum::body::Body;
-use axum::response::{IntoResponse, Response};
-use crate::middleware::auth::JwtExt;
-use std::future::Future;
-use std::pin::Pin;
+use axum::extract::FromRequestParts;
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use uuid::Uuid;
 
+// Set by middleware_auth from the verified access token
 #[derive(Debug, Clone)]
 pub struct UserId(pub i64);
 
-impl<S> FromRequest<S> for UserId
+impl<S> FromRequestParts<S> for UserId
 where
     S: Send + Sync,
 {
     type Rejection = (StatusCode, &'static str);
 
-    fn from_request(req: Request<Body>, _state: &S) -> Self::Future {
-        let user_id = req.user_id();
-        let future = async move {
-            if user_id == 0 {
-                Err((StatusCode::UNAUTHORIZED, "Invalid user ID"))
-            } else {
-                Ok(UserId(user_id))
-            }
-        };
-        Box::pin(future)
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<i64>().copied() {
+            Some(user_id) if user_id != 0 => Ok(UserId(user_id)),
+            _ => Err((StatusCode::UNAUTHORIZED, "Invalid user ID")),
+        }
     }
 }
 
@@ -32,4 +25,19 @@ impl UserId {
     pub fn get(&self) -> i64 {
         self.0
     }
-}
\ No newline at end of file
+}
+
+// Session the access token was issued for; None for tokens minted before sessions existed
+#[derive(Debug, Clone, Copy)]
+pub struct SessionId(pub Option<Uuid>);
+
+impl<S> FromRequestParts<S> for SessionId
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<SessionId>().copied().unwrap_or(SessionId(None)))
+    }
+}
@@ -0,0 +1,3 @@
+pub mod purge_deleted_accounts;
+pub mod purge_tombstones;
+pub mod revocation_sync;
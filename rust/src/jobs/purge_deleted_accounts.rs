@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use sqlx::{Pool, Postgres};
+use tracing::info;
+
+use crate::controllers::account_controller::deletion::{DeletionTrigger, anonymize_account, due_deletions};
+use crate::socket::handlers::{UserSocketMap, disconnect_sessions};
+use crate::storage::SharedStorage;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Anonymizes accounts whose deletion grace period has run out
+pub fn spawn_account_purge(db: Pool<Postgres>, storage: SharedStorage, user_sockets: UserSocketMap) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let due = match due_deletions(&db).await {
+                Ok(due) => due,
+                Err(err) => {
+                    eprintln!("Failed to load accounts due for deletion: {}", err);
+                    continue;
+                }
+            };
+
+            for user_id in due {
+                // Left alone if the owner cancelled the deletion since it was listed
+                match anonymize_account(&db, &storage, user_id, DeletionTrigger::GracePeriodOver).await {
+                    Ok(Some(revoked)) => {
+                        disconnect_sessions(&user_sockets, user_id, &revoked).await;
+                        info!("Deleted account {}", user_id);
+                    }
+                    Ok(None) => {}
+                    Err(err) => eprintln!("Failed to delete account {}: {}", user_id, err),
+                }
+            }
+        }
+    });
+}
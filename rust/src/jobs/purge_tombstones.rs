@@ -0,0 +1,46 @@
+use std::env;
+use std::time::Duration;
+
+use sqlx::{Pool, Postgres};
+use tracing::info;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Hard-deletes soft-deleted messages older than the retention window.
+// Tombstones that are still the target of a reply are kept so threads stay intact.
+pub async fn purge_tombstones(db: &Pool<Postgres>, retention_days: i32) -> Result<u64, sqlx::Error> {
+    let query = r#"
+        DELETE FROM chats c
+        WHERE c.deleted_at IS NOT NULL
+          AND c.deleted_at < NOW() - make_interval(days => $1)
+          AND NOT EXISTS (SELECT 1 FROM chats r WHERE r."replyId" = c.id)
+    "#;
+    let result = sqlx::query(query).bind(retention_days).execute(db).await?;
+    Ok(result.rows_affected())
+}
+
+// Starts the hourly purge when CHAT_TOMBSTONE_RETENTION_DAYS is set; tombstones are kept forever otherwise
+pub fn spawn_tombstone_purge(db: Pool<Postgres>) {
+    let retention_days = match env::var("CHAT_TOMBSTONE_RETENTION_DAYS") {
+        Ok(value) => match value.parse::<i32>() {
+            Ok(days) if days >= 0 => days,
+            _ => {
+                eprintln!("Invalid CHAT_TOMBSTONE_RETENTION_DAYS: {}", value);
+                return;
+            }
+        },
+        Err(_) => return,
+    };
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_tombstones(&db, retention_days).await {
+                Ok(0) => {}
+                Ok(count) => info!("Purged {} deleted messages", count),
+                Err(err) => eprintln!("Failed to purge deleted messages: {}", err),
+            }
+        }
+    });
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use sqlx::{Pool, Postgres};
+
+use crate::libs::revocation::sync_revocations;
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+// Keeps the in-memory deny-list in step with revocations made by other instances
+pub fn spawn_revocation_sync(db: Pool<Postgres>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SYNC_INTERVAL);
+        let mut since = None;
+        loop {
+            interval.tick().await;
+            match sync_revocations(&db, since).await {
+                Ok(next_since) => since = Some(next_since),
+                Err(err) => eprintln!("Failed to sync revoked tokens: {}", err),
+            }
+        }
+    });
+}
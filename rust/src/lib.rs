@@ -1,7 +1,30 @@
+use socketioxide::SocketIo;
+use sqlx::{Pool, Postgres};
+
 pub mod compose;
+pub mod controllers;
 pub mod db;
 pub mod extract;
+pub mod jobs;
 pub mod libs;
+pub mod mailer;
 pub mod middleware;
+pub mod ratelimit;
 pub mod router;
-pub mod socket;
\ No newline at end of file
+pub mod socket;
+pub mod storage;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: Pool<Postgres>,
+    // Lets REST controllers broadcast to the same rooms the socket handlers use
+    pub io: SocketIo,
+    // Attachment bytes; local disk or S3 depending on STORAGE_BACKEND
+    pub storage: storage::SharedStorage,
+    // Outgoing mail; SMTP or files under MAIL_DIR depending on MAILER_BACKEND
+    pub mailer: mailer::SharedMailer,
+    // Live sockets per user, so REST actions such as session revocation can reach them
+    pub user_sockets: UserSocketMap,
+}
+
+pub type UserSocketMap = socket::handlers::UserSocketMap;
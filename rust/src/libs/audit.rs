@@ -0,0 +1,33 @@
+use serde_json::Value;
+
+// One row of the audit trail. `target_id` is a plain ID so the record
+// survives the target being purged later on.
+pub struct AuditEntry<'a> {
+    pub actor_id: Option<i64>,
+    pub action: &'a str,
+    pub target_type: &'a str,
+    pub target_id: Option<i64>,
+    pub metadata: Value,
+}
+
+// Takes any executor so callers can record inside their own transaction
+pub async fn record_audit<'e, E>(executor: E, entry: AuditEntry<'_>) -> Result<(), sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO audit_logs ("actorId", action, "targetType", "targetId", metadata, created_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        "#,
+    )
+    .bind(entry.actor_id)
+    .bind(entry.action)
+    .bind(entry.target_type)
+    .bind(entry.target_id)
+    .bind(sqlx::types::Json(entry.metadata))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
@@ -11,6 +11,19 @@ pub enum AvatarType {
 }
 
 impl AvatarType {
+    pub const ALL: [AvatarType; 6] = [
+        AvatarType::Personas,
+        AvatarType::Initials,
+        AvatarType::Shapes,
+        AvatarType::Identicon,
+        AvatarType::Bottts,
+        AvatarType::Avataaars,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|style| style.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             AvatarType::Personas => "personas",
@@ -29,9 +42,34 @@ impl fmt::Display for AvatarType {
     }
 }
 
+const AVATAR_BASE_URL: &str = "https://api.dicebear.com/9.x/";
+
 pub fn generate_avatar(nickname: String, type_ava: AvatarType) -> String {
     format!(
-        "https://api.dicebear.com/9.x/{}/svg?seed={}",
-        type_ava, nickname
+        "{}{}/svg?seed={}",
+        AVATAR_BASE_URL, type_ava, nickname
     )
 }
+
+// Style of an avatar made by generate_avatar; None for anything else, such as an uploaded picture
+pub fn generated_style(avatar: &str) -> Option<AvatarType> {
+    let rest = avatar.strip_prefix(AVATAR_BASE_URL)?;
+    let (style, _) = rest.split_once("/svg?seed=")?;
+    AvatarType::parse(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_style_back_from_generated_avatars() {
+        for style in AvatarType::ALL {
+            assert_eq!(AvatarType::parse(style.as_str()), Some(style));
+            assert_eq!(generated_style(&generate_avatar("alice".to_string(), style)), Some(style));
+        }
+        assert_eq!(AvatarType::parse("Bottts"), Some(AvatarType::Bottts));
+        assert_eq!(AvatarType::parse("pixel-art"), None);
+        assert_eq!(generated_style("https://example.com/me.png"), None);
+    }
+}
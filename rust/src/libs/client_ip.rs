@@ -0,0 +1,137 @@
+// The address a request really came from. X-Forwarded-For is only believed when the
+// connection comes from a proxy listed in TRUSTED_PROXIES, since anyone else can send it.
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+
+use axum::http::HeaderMap;
+
+// An address or a CIDR range, like `10.0.0.0/8` or `::1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl TrustedProxy {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (address, prefix) = match value.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+            None => (value.trim(), None),
+        };
+        let network = address.parse::<IpAddr>().ok()?.to_canonical();
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// TRUSTED_PROXIES, comma separated; unset trusts no proxy and always uses the peer address
+fn trusted_proxies() -> &'static [TrustedProxy] {
+    static PROXIES: OnceLock<Vec<TrustedProxy>> = OnceLock::new();
+    PROXIES.get_or_init(|| {
+        env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .filter_map(|value| {
+                let proxy = TrustedProxy::parse(value);
+                if proxy.is_none() {
+                    eprintln!("Ignoring invalid TRUSTED_PROXIES entry: {}", value);
+                }
+                proxy
+            })
+            .collect()
+    })
+}
+
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    resolve(&forwarded.join(","), peer.ip(), trusted_proxies())
+}
+
+// Each proxy appends the address it got the request from, so the chain is walked from the
+// right and the first hop that isn't one of ours is the client. Whatever is to its left
+// was written by the client and can't be trusted.
+fn resolve(forwarded: &str, peer: IpAddr, trusted: &[TrustedProxy]) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|proxy| proxy.contains(ip));
+    let mut client = peer.to_canonical();
+    if !is_trusted(client) {
+        return client;
+    }
+
+    for hop in forwarded.rsplit(',').map(str::trim).filter(|hop| !hop.is_empty()) {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) => client = ip.to_canonical(),
+            // Garbage a trusted proxy passed along; the last good hop is the best we have
+            Err(_) => break,
+        }
+        if !is_trusted(client) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn proxies(values: &[&str]) -> Vec<TrustedProxy> {
+        values.iter().map(|value| TrustedProxy::parse(value).unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_addresses_and_ranges() {
+        let range = TrustedProxy::parse("10.0.0.0/8").unwrap();
+        assert!(range.contains(ip("10.1.2.3")));
+        assert!(range.contains(ip("::ffff:10.1.2.3")));
+        assert!(!range.contains(ip("11.0.0.1")));
+        assert!(TrustedProxy::parse("::1").unwrap().contains(ip("::1")));
+        assert!(TrustedProxy::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+        assert_eq!(TrustedProxy::parse("10.0.0.0/33"), None);
+        assert_eq!(TrustedProxy::parse("proxy.local"), None);
+    }
+
+    #[test]
+    fn ignores_forwarded_for_from_untrusted_peers() {
+        assert_eq!(resolve("1.2.3.4", ip("203.0.113.9"), &[]), ip("203.0.113.9"));
+        let trusted = proxies(&["10.0.0.1"]);
+        assert_eq!(resolve("1.2.3.4", ip("203.0.113.9"), &trusted), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn takes_the_right_most_untrusted_hop() {
+        let trusted = proxies(&["10.0.0.0/8"]);
+        // The client made up 6.6.6.6; 198.51.100.7 is what our proxies saw
+        assert_eq!(
+            resolve("6.6.6.6, 198.51.100.7, 10.0.0.5", ip("10.0.0.1"), &trusted),
+            ip("198.51.100.7")
+        );
+        assert_eq!(resolve("", ip("10.0.0.1"), &trusted), ip("10.0.0.1"));
+        assert_eq!(resolve("not-an-ip, 10.0.0.5", ip("10.0.0.1"), &trusted), ip("10.0.0.5"));
+    }
+}
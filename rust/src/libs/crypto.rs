@@ -1,80 +1,57 @@
-use std::env;
-
-use argon2::password_hash::SaltString;
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHasher, SaltString};
 use base64::{Engine, engine::general_purpose as b64};
-use chrono::{Duration, TimeDelta, Utc};
-use dotenvy::dotenv;
-use hmac::{Hmac, Mac};
+use chrono::{DateTime, Duration, TimeDelta, Utc};
+use rand::RngCore;
 use rand::rngs::OsRng;
-use serde::{Deserialize, Serialize};
-use serde_json;
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
-type HmacSha256 = Hmac<Sha256>;
+use crate::libs::jwt::{Audience, Claims, TokenUse, key_set};
 
-#[derive(Serialize, Deserialize, Debug)]
-struct JwtHeader {
-    alg: String,
-    typ: String,
+// A signed token plus the claims needed to revoke it before it expires
+pub struct AccessToken {
+    pub token: String,
+    pub jti: String,
+    pub expires_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct JwtBody {
+fn jwt(
     id: i64,
-    iat: i64,
-    exp: i64,
-    jti: String,
-}
-
-fn create_hmac_signature(
-    message: &str,
-    secret: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())?;
-    mac.update(message.as_bytes());
-    let result = mac.finalize();
-    Ok(b64::URL_SAFE_NO_PAD.encode(result.into_bytes()))
-}
-
-fn jwt(id: i64, duration: TimeDelta) -> Result<String, Box<dyn std::error::Error>> {
-    dotenv().ok();
-    let secret = env::var("SECRET")?;
+    sid: Option<&str>,
+    duration: TimeDelta,
+    token_use: TokenUse,
+) -> Result<AccessToken, Box<dyn std::error::Error>> {
+    let keys = key_set()?;
+    let validation = keys.validation();
 
-    // Header
-    let header = JwtHeader {
-        alg: "HS256".to_string(),
-        typ: "https://youtu.be/xvFZjo5PgG0".to_string(),
-    };
-    let header_json = serde_json::to_string(&header)?;
-    let header_b64 = b64::URL_SAFE_NO_PAD.encode(header_json);
-    let salt = SaltString::generate(&mut OsRng);
-
-    // Body/Payload
     let now = Utc::now();
     let exp_time = now + duration;
-    let jwt_body_struct = JwtBody {
-        id,
+    let claims = Claims {
+        sub: id.to_string(),
         iat: now.timestamp(),
+        nbf: Some(now.timestamp()),
         exp: exp_time.timestamp(),
-        jti: salt.as_str().to_string(),
+        jti: Uuid::new_v4().to_string(),
+        iss: validation.issuer.clone(),
+        aud: validation.audience.clone().map(Audience::One),
+        sid: sid.map(str::to_string),
+        token_use: Some(token_use),
     };
-    let jwt_body_json = serde_json::to_string(&jwt_body_struct)?;
-    let jwt_body_b64 = b64::URL_SAFE_NO_PAD.encode(jwt_body_json);
-
-    // Create message to sign (header.payload)
-    let message = format!("{}.{}", header_b64, jwt_body_b64);
-
-    // Create HMAC signature
-    let signature = create_hmac_signature(&message, &secret)?;
 
-    // Return complete JWT
-    Ok(format!("{}.{}", message, signature))
+    Ok(AccessToken {
+        token: keys.encode(&claims)?,
+        jti: claims.jti,
+        expires_at: exp_time,
+    })
 }
 
 pub struct VerifyJwt {
     pub result: bool,
     pub reason: String,
     pub user_id: Option<i64>,
+    pub session_id: Option<String>,
+    pub jti: Option<String>,
 }
 
 impl VerifyJwt {
@@ -84,103 +61,93 @@ impl VerifyJwt {
             0
         })
     }
-}
-
-pub fn verify_jwt(token: &str) -> VerifyJwt {
-    let secret = match env::var("SECRET") {
-        Ok(secret) => secret,
-        Err(_) => {
-            return VerifyJwt {
-                result: false,
-                reason: "SECRET environment variable not set".to_string(),
-                user_id: None,
-            };
-        }
-    };
 
-    let parts: Vec<&str> = token.split('.').collect();
-    if parts.len() != 3 {
-        return VerifyJwt {
+    fn rejected(reason: impl ToString) -> Self {
+        VerifyJwt {
             result: false,
-            reason: "Invalid Token format".to_string(),
+            reason: reason.to_string(),
             user_id: None,
-        };
+            session_id: None,
+            jti: None,
+        }
     }
+}
 
-    let (header_b64, payload_b64, signature_b64) = (parts[0], parts[1], parts[2]);
-
-    // Verify signature
-    let message = format!("{}.{}", header_b64, payload_b64);
-    let expected_signature = match create_hmac_signature(&message, &secret) {
-        Ok(sign) => sign,
-        Err(err) => {
-            return VerifyJwt {
-                result: false,
-                reason: err.to_string(),
-                user_id: None,
-            };
-        }
+// Accepts access tokens only; refresh tokens are opaque and checked against their session instead
+pub fn verify_jwt(token: &str) -> VerifyJwt {
+    let keys = match key_set() {
+        Ok(keys) => keys,
+        Err(err) => return VerifyJwt::rejected(err),
     };
 
-    if signature_b64 != expected_signature {
-        return VerifyJwt {
-            result: false,
-            reason: "Invalid Signature".to_string(),
-            user_id: None,
-        };
-    }
-
-    // Decode and verify payload
-    let payload_json = match b64::URL_SAFE_NO_PAD.decode(payload_b64) {
-        Ok(vector) => vector,
-        Err(err) => {
-            return VerifyJwt {
-                result: false,
-                reason: err.to_string(),
-                user_id: None,
-            };
-        }
+    let claims = match keys.decode_as(token, TokenUse::Access) {
+        Ok(claims) => claims,
+        Err(err) => return VerifyJwt::rejected(err),
     };
 
-    let payload: JwtBody = match serde_json::from_slice(&payload_json) {
-        Ok(json_body) => json_body,
-        Err(err) => {
-            return VerifyJwt {
-                result: false,
-                reason: err.to_string(),
-                user_id: None,
-            };
-        }
+    let user_id = match claims.sub.parse::<i64>() {
+        Ok(user_id) => user_id,
+        Err(_) => return VerifyJwt::rejected("Invalid subject"),
     };
 
-    // Check expiration
-    let now = Utc::now().timestamp();
-    if payload.exp < now {
-        return VerifyJwt {
-            result: false,
-            reason: "Token Expired".to_string(),
-            user_id: None,
-        };
-    } else {
-        return VerifyJwt {
-            result: true,
-            reason: "Ok".to_string(),
-            user_id: Some(payload.id),
-        };
+    VerifyJwt {
+        result: true,
+        reason: "Ok".to_string(),
+        user_id: Some(user_id),
+        session_id: claims.sid,
+        jti: Some(claims.jti),
     }
 }
 
-pub struct GenerateJWt {
-    pub token: String,
-    pub refresh_token: String,
+// Short-lived access token; refresh tokens are opaque and tied to a session
+pub fn generate_access_token(id: i64, session_id: &str) -> Result<AccessToken, Box<dyn std::error::Error>> {
+    jwt(id, Some(session_id), Duration::hours(1), TokenUse::Access)
 }
 
-pub fn generate_jwt(id: i64) -> Result<GenerateJWt, Box<dyn std::error::Error>> {
-    let token = jwt(id, Duration::hours(1))?;
-    let refresh_token = jwt(id, Duration::days(30))?;
+// Handed out after the password check when the account has 2FA; exchanged with a code for real tokens
+pub fn generate_mfa_pending_token(id: i64) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(jwt(id, None, Duration::minutes(5), TokenUse::MfaPending)?.token)
+}
+
+// The user the pending token was issued to, if it is valid and of that type
+pub fn verify_mfa_pending_token(token: &str) -> Option<i64> {
+    let claims = key_set().ok()?.decode_as(token, TokenUse::MfaPending).ok()?;
+    claims.sub.parse().ok()
+}
 
-    Ok(GenerateJWt {
-        token,
-        refresh_token,
+// Marks opaque refresh tokens, so a JWT (or anything else) is refused before any lookup
+pub const REFRESH_TOKEN_PREFIX: &str = "rt_";
+
+// 256 random bits, URL safe; for tokens that are mailed or stored hashed
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    b64::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn generate_refresh_token() -> String {
+    format!("{}{}", REFRESH_TOKEN_PREFIX, generate_opaque_token())
+}
+
+pub fn is_refresh_token(token: &str) -> bool {
+    token.strip_prefix(REFRESH_TOKEN_PREFIX).is_some_and(|body| {
+        !body.is_empty()
+            && body
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
     })
 }
+
+// Argon2 PHC string with a fresh salt, as stored in users.password
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+// Hex SHA-256 digest, used to store opaque tokens without keeping them readable
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,227 @@
+use base64::{Engine, engine::general_purpose as b64};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signer, Verifier};
+use hmac::{Hmac, Mac};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::signature::SignatureEncoding;
+use rsa::BigUint;
+use rsa::traits::PublicKeyParts;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::JwtError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// RFC 7518 asks for an HS256 key at least as long as the hash output
+pub const MIN_HMAC_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    HS256,
+    RS256,
+    EdDSA,
+}
+
+// Key material. Verify-only variants hold retired keys that may still have live tokens.
+pub enum KeyMaterial {
+    Hmac(Vec<u8>),
+    RsaPrivate(Box<rsa::RsaPrivateKey>),
+    RsaPublic(rsa::RsaPublicKey),
+    Ed25519Private(ed25519_dalek::SigningKey),
+    Ed25519Public(ed25519_dalek::VerifyingKey),
+}
+
+pub struct Key {
+    pub kid: String,
+    pub material: KeyMaterial,
+}
+
+impl Key {
+    pub fn algorithm(&self) -> Algorithm {
+        match self.material {
+            KeyMaterial::Hmac(_) => Algorithm::HS256,
+            KeyMaterial::RsaPrivate(_) | KeyMaterial::RsaPublic(_) => Algorithm::RS256,
+            KeyMaterial::Ed25519Private(_) | KeyMaterial::Ed25519Public(_) => Algorithm::EdDSA,
+        }
+    }
+
+    pub fn can_sign(&self) -> bool {
+        matches!(
+            self.material,
+            KeyMaterial::Hmac(_) | KeyMaterial::RsaPrivate(_) | KeyMaterial::Ed25519Private(_)
+        )
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JwtError> {
+        match &self.material {
+            KeyMaterial::Hmac(secret) => {
+                let mut mac = HmacSha256::new_from_slice(secret).map_err(|err| JwtError::Signing(err.to_string()))?;
+                mac.update(message);
+                Ok(mac.finalize().into_bytes().to_vec())
+            }
+            KeyMaterial::RsaPrivate(key) => {
+                let signer = rsa::pkcs1v15::SigningKey::<Sha256>::new((**key).clone());
+                let signature = signer.try_sign(message).map_err(|err| JwtError::Signing(err.to_string()))?;
+                Ok(signature.to_vec())
+            }
+            KeyMaterial::Ed25519Private(key) => Ok(key.sign(message).to_bytes().to_vec()),
+            KeyMaterial::RsaPublic(_) | KeyMaterial::Ed25519Public(_) => {
+                Err(JwtError::Signing(format!("key {} is verify-only", self.kid)))
+            }
+        }
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match &self.material {
+            // verify_slice compares in constant time
+            KeyMaterial::Hmac(secret) => match HmacSha256::new_from_slice(secret) {
+                Ok(mut mac) => {
+                    mac.update(message);
+                    mac.verify_slice(signature).is_ok()
+                }
+                Err(_) => false,
+            },
+            KeyMaterial::RsaPrivate(key) => verify_rsa(&key.to_public_key(), message, signature),
+            KeyMaterial::RsaPublic(key) => verify_rsa(key, message, signature),
+            KeyMaterial::Ed25519Private(key) => verify_ed25519(&key.verifying_key(), message, signature),
+            KeyMaterial::Ed25519Public(key) => verify_ed25519(key, message, signature),
+        }
+    }
+
+    // Verify-only key from a published JWK (RSA or Ed25519)
+    pub fn from_jwk(jwk: &serde_json::Value) -> Result<Key, JwtError> {
+        let field = |name: &str| {
+            jwk[name]
+                .as_str()
+                .and_then(|value| b64::URL_SAFE_NO_PAD.decode(value).ok())
+                .ok_or_else(|| JwtError::Config(format!("JWK is missing `{}`", name)))
+        };
+        let kid = jwk["kid"].as_str().unwrap_or("default").to_string();
+
+        let material = match (jwk["kty"].as_str(), jwk["crv"].as_str()) {
+            (Some("RSA"), _) => {
+                let key = rsa::RsaPublicKey::new(BigUint::from_bytes_be(&field("n")?), BigUint::from_bytes_be(&field("e")?))
+                    .map_err(|err| JwtError::Config(err.to_string()))?;
+                KeyMaterial::RsaPublic(key)
+            }
+            (Some("OKP"), Some("Ed25519")) => {
+                let x: [u8; 32] = field("x")?
+                    .try_into()
+                    .map_err(|_| JwtError::Config("Ed25519 JWK has a bad `x`".to_string()))?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(&x).map_err(|err| JwtError::Config(err.to_string()))?;
+                KeyMaterial::Ed25519Public(key)
+            }
+            _ => return Err(JwtError::UnsupportedAlgorithm),
+        };
+
+        Ok(Key { kid, material })
+    }
+
+    // Public half as a JWK; shared secrets are never published
+    pub fn jwk(&self) -> Option<serde_json::Value> {
+        let (public, alg) = match &self.material {
+            KeyMaterial::Hmac(_) => return None,
+            KeyMaterial::RsaPrivate(key) => (PublicJwk::Rsa(key.to_public_key()), "RS256"),
+            KeyMaterial::RsaPublic(key) => (PublicJwk::Rsa(key.clone()), "RS256"),
+            KeyMaterial::Ed25519Private(key) => (PublicJwk::Ed25519(key.verifying_key()), "EdDSA"),
+            KeyMaterial::Ed25519Public(key) => (PublicJwk::Ed25519(*key), "EdDSA"),
+        };
+
+        let mut jwk = match public {
+            PublicJwk::Rsa(key) => serde_json::json!({
+                "kty": "RSA",
+                "n": b64::URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                "e": b64::URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }),
+            PublicJwk::Ed25519(key) => serde_json::json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": b64::URL_SAFE_NO_PAD.encode(key.as_bytes()),
+            }),
+        };
+        jwk["kid"] = self.kid.clone().into();
+        jwk["alg"] = alg.into();
+        jwk["use"] = "sig".into();
+        Some(jwk)
+    }
+}
+
+enum PublicJwk {
+    Rsa(rsa::RsaPublicKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+fn verify_rsa(key: &rsa::RsaPublicKey, message: &[u8], signature: &[u8]) -> bool {
+    let verifier = rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key.clone());
+    match rsa::pkcs1v15::Signature::try_from(signature) {
+        Ok(signature) => verifier.verify(message, &signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn verify_ed25519(key: &ed25519_dalek::VerifyingKey, message: &[u8], signature: &[u8]) -> bool {
+    match ed25519_dalek::Signature::from_slice(signature) {
+        Ok(signature) => key.verify_strict(message, &signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// One entry of the JWT_KEYS_FILE `keys` array
+#[derive(Deserialize)]
+pub struct KeyConfig {
+    pub kid: String,
+    pub alg: Algorithm,
+    pub secret: Option<String>,
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
+}
+
+fn read_pem(path: &str) -> Result<String, JwtError> {
+    std::fs::read_to_string(path).map_err(|err| JwtError::Config(format!("{}: {}", path, err)))
+}
+
+impl KeyConfig {
+    pub fn load(self) -> Result<Key, JwtError> {
+        let invalid = |err: &dyn std::fmt::Display| JwtError::Config(format!("key {}: {}", self.kid, err));
+
+        let material = match (self.alg, &self.secret, &self.private_key_path, &self.public_key_path) {
+            (Algorithm::HS256, Some(secret), None, None) => {
+                if secret.len() < MIN_HMAC_SECRET_LEN {
+                    return Err(invalid(&"HS256 secrets must be at least 32 bytes"));
+                }
+                KeyMaterial::Hmac(secret.as_bytes().to_vec())
+            }
+            (Algorithm::RS256, None, Some(path), _) => {
+                let pem = read_pem(path)?;
+                let key = rsa::RsaPrivateKey::from_pkcs8_pem(&pem)
+                    .or_else(|_| rsa::RsaPrivateKey::from_pkcs1_pem(&pem))
+                    .map_err(|err| invalid(&err))?;
+                KeyMaterial::RsaPrivate(Box::new(key))
+            }
+            (Algorithm::RS256, None, None, Some(path)) => {
+                let pem = read_pem(path)?;
+                let key = rsa::RsaPublicKey::from_public_key_pem(&pem)
+                    .or_else(|_| rsa::RsaPublicKey::from_pkcs1_pem(&pem))
+                    .map_err(|err| invalid(&err))?;
+                KeyMaterial::RsaPublic(key)
+            }
+            (Algorithm::EdDSA, None, Some(path), _) => {
+                let key = ed25519_dalek::SigningKey::from_pkcs8_pem(&read_pem(path)?).map_err(|err| invalid(&err))?;
+                KeyMaterial::Ed25519Private(key)
+            }
+            (Algorithm::EdDSA, None, None, Some(path)) => {
+                let key =
+                    ed25519_dalek::VerifyingKey::from_public_key_pem(&read_pem(path)?).map_err(|err| invalid(&err))?;
+                KeyMaterial::Ed25519Public(key)
+            }
+            _ => {
+                return Err(invalid(
+                    &"HS256 keys need `secret`; RS256/EdDSA keys need `private_key_path` or `public_key_path`",
+                ));
+            }
+        };
+
+        Ok(Key { kid: self.kid, material })
+    }
+}
@@ -0,0 +1,525 @@
+pub mod keys;
+
+use std::env;
+use std::sync::OnceLock;
+
+use base64::{Engine, engine::general_purpose as b64};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use keys::{Algorithm, Key, KeyConfig, KeyMaterial, MIN_HMAC_SECRET_LEN};
+
+pub const DEFAULT_LEEWAY_SECS: i64 = 30;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    #[error("Invalid token format")]
+    Malformed,
+    #[error("Unsupported algorithm")]
+    UnsupportedAlgorithm,
+    #[error("Unknown signing key")]
+    UnknownKey,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Token expired")]
+    Expired,
+    #[error("Token not yet valid")]
+    NotYetValid,
+    #[error("Invalid issuer")]
+    InvalidIssuer,
+    #[error("Invalid audience")]
+    InvalidAudience,
+    #[error("Wrong token type")]
+    WrongTokenType,
+    #[error("JWT configuration error: {0}")]
+    Config(String),
+    #[error("Failed to sign token: {0}")]
+    Signing(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+// `aud` may be a single string or an array (RFC 7519 section 4.1.3)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(value) => value == audience,
+            Audience::Many(values) => values.iter().any(|value| value == audience),
+        }
+    }
+}
+
+// What a token may be used for; a token is only accepted where its type is expected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenUse {
+    Access,
+    // Password checked, second factor still owed; only /v1/login/mfa takes it
+    MfaPending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
+    pub exp: i64,
+    pub jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+    // Session the token was issued for, so revoking the session can find its sockets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    // Missing on tokens minted before the claim existed; those match no expected type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_use: Option<TokenUse>,
+}
+
+// Registered-claim checks applied on decode
+pub struct Validation {
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub leeway: i64,
+}
+
+impl Validation {
+    pub fn from_env() -> Self {
+        Self {
+            issuer: env::var("JWT_ISSUER").ok(),
+            audience: env::var("JWT_AUDIENCE").ok(),
+            leeway: env::var("JWT_LEEWAY_SECS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_LEEWAY_SECS),
+        }
+    }
+}
+
+// Every key we accept, by kid, plus the one new tokens are signed with.
+// Rotation: add the new key, make it the signing key, drop the old one once its tokens have expired.
+pub struct KeySet {
+    keys: Vec<Key>,
+    // None for sets that only verify, such as an identity provider's published keys
+    signing_kid: Option<String>,
+    validation: Validation,
+}
+
+// exp/nbf/iss/aud, checked whatever else a token carries
+#[derive(Deserialize)]
+struct RegisteredClaims {
+    exp: i64,
+    #[serde(default)]
+    nbf: Option<i64>,
+    #[serde(default)]
+    iss: Option<String>,
+    #[serde(default)]
+    aud: Option<Audience>,
+}
+
+#[derive(Deserialize)]
+struct KeyFile {
+    signing_kid: String,
+    keys: Vec<KeyConfig>,
+}
+
+impl KeySet {
+    pub fn new(keys: Vec<Key>, signing_kid: &str, validation: Validation) -> Result<Self, JwtError> {
+        match keys.iter().find(|key| key.kid == signing_kid) {
+            Some(key) if key.can_sign() => {}
+            Some(_) => return Err(JwtError::Config(format!("signing key {} has no private key", signing_kid))),
+            None => return Err(JwtError::Config(format!("signing key {} is not configured", signing_kid))),
+        }
+
+        Ok(Self {
+            keys,
+            signing_kid: Some(signing_kid.to_string()),
+            validation,
+        })
+    }
+
+    // Verify-only set from a JWKS document; keys of unsupported types are skipped
+    pub fn from_jwks(jwks: &serde_json::Value, validation: Validation) -> Result<Self, JwtError> {
+        let keys: Vec<Key> = jwks["keys"]
+            .as_array()
+            .ok_or_else(|| JwtError::Config("JWKS has no keys".to_string()))?
+            .iter()
+            .filter_map(|jwk| Key::from_jwk(jwk).ok())
+            .collect();
+        if keys.is_empty() {
+            return Err(JwtError::Config("JWKS has no supported keys".to_string()));
+        }
+
+        Ok(Self {
+            keys,
+            signing_kid: None,
+            validation,
+        })
+    }
+
+    // JWT_KEYS_FILE points at a JSON key set; without it SECRET is used as a single HS256 key
+    pub fn from_env() -> Result<Self, JwtError> {
+        dotenvy::dotenv().ok();
+        let validation = Validation::from_env();
+
+        if let Ok(path) = env::var("JWT_KEYS_FILE") {
+            let raw = std::fs::read_to_string(&path).map_err(|err| JwtError::Config(format!("{}: {}", path, err)))?;
+            let file: KeyFile =
+                serde_json::from_str(&raw).map_err(|err| JwtError::Config(format!("{}: {}", path, err)))?;
+            let keys = file.keys.into_iter().map(KeyConfig::load).collect::<Result<Vec<_>, _>>()?;
+            return Self::new(keys, &file.signing_kid, validation);
+        }
+
+        let secret = env::var("SECRET").map_err(|_| JwtError::Config("set JWT_KEYS_FILE or SECRET".to_string()))?;
+        Self::new(vec![secret_key(secret)?], "default", validation)
+    }
+
+    fn key(&self, kid: &str) -> Option<&Key> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    pub fn validation(&self) -> &Validation {
+        &self.validation
+    }
+
+    pub fn encode(&self, claims: &Claims) -> Result<String, JwtError> {
+        let kid = self.signing_kid.as_deref().ok_or(JwtError::UnknownKey)?;
+        let key = self.key(kid).ok_or(JwtError::UnknownKey)?;
+        let header = Header {
+            alg: format!("{:?}", key.algorithm()),
+            typ: Some("JWT".to_string()),
+            kid: Some(key.kid.clone()),
+        };
+
+        let header_json = serde_json::to_vec(&header).map_err(|err| JwtError::Signing(err.to_string()))?;
+        let claims_json = serde_json::to_vec(claims).map_err(|err| JwtError::Signing(err.to_string()))?;
+        let message = format!(
+            "{}.{}",
+            b64::URL_SAFE_NO_PAD.encode(header_json),
+            b64::URL_SAFE_NO_PAD.encode(claims_json)
+        );
+        let signature = key.sign(message.as_bytes())?;
+
+        Ok(format!("{}.{}", message, b64::URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    pub fn decode(&self, token: &str) -> Result<Claims, JwtError> {
+        self.decode_claims(token)
+    }
+
+    // Verifies the signature and registered claims, then parses the payload as `T`;
+    // for tokens with claims of their own, such as OpenID Connect ID tokens
+    pub fn decode_claims<T: serde::de::DeserializeOwned>(&self, token: &str) -> Result<T, JwtError> {
+        let mut parts = token.split('.');
+        let (header_b64, claims_b64, signature_b64) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(header), Some(claims), Some(signature), None) => (header, claims, signature),
+            _ => return Err(JwtError::Malformed),
+        };
+
+        let header: Header = decode_part(header_b64)?;
+        let algorithm = match header.alg.as_str() {
+            "HS256" => Algorithm::HS256,
+            "RS256" => Algorithm::RS256,
+            "EdDSA" => Algorithm::EdDSA,
+            // Includes "none"
+            _ => return Err(JwtError::UnsupportedAlgorithm),
+        };
+
+        // Tokens from before key rotation carry no kid; they can only match the default key
+        let key = self.key(header.kid.as_deref().unwrap_or("default")).ok_or(JwtError::UnknownKey)?;
+        // The key decides the algorithm, never the token, so an RSA public key can't be used as an HMAC secret
+        if key.algorithm() != algorithm {
+            return Err(JwtError::UnsupportedAlgorithm);
+        }
+
+        let signature = b64::URL_SAFE_NO_PAD.decode(signature_b64).map_err(|_| JwtError::Malformed)?;
+        let message = format!("{}.{}", header_b64, claims_b64);
+        if !key.verify(message.as_bytes(), &signature) {
+            return Err(JwtError::InvalidSignature);
+        }
+
+        self.validate(&decode_part(claims_b64)?)?;
+        decode_part(claims_b64)
+    }
+
+    // Decodes and also requires the token_use claim, so one kind of token can't stand in for another
+    pub fn decode_as(&self, token: &str, expected: TokenUse) -> Result<Claims, JwtError> {
+        let claims = self.decode(token)?;
+        if claims.token_use != Some(expected) {
+            return Err(JwtError::WrongTokenType);
+        }
+        Ok(claims)
+    }
+
+    fn validate(&self, claims: &RegisteredClaims) -> Result<(), JwtError> {
+        let now = Utc::now().timestamp();
+        let leeway = self.validation.leeway;
+
+        if claims.exp + leeway < now {
+            return Err(JwtError::Expired);
+        }
+        if claims.nbf.is_some_and(|nbf| nbf - leeway > now) {
+            return Err(JwtError::NotYetValid);
+        }
+        if let Some(issuer) = &self.validation.issuer {
+            if claims.iss.as_ref() != Some(issuer) {
+                return Err(JwtError::InvalidIssuer);
+            }
+        }
+        if let Some(audience) = &self.validation.audience {
+            if !claims.aud.as_ref().is_some_and(|aud| aud.contains(audience)) {
+                return Err(JwtError::InvalidAudience);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn jwks(&self) -> serde_json::Value {
+        let keys: Vec<serde_json::Value> = self.keys.iter().filter_map(Key::jwk).collect();
+        serde_json::json!({ "keys": keys })
+    }
+}
+
+fn decode_part<T: serde::de::DeserializeOwned>(part: &str) -> Result<T, JwtError> {
+    let bytes = b64::URL_SAFE_NO_PAD.decode(part).map_err(|_| JwtError::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| JwtError::Malformed)
+}
+
+// SECRET as the single HS256 key, held to the same minimum length as keys from JWT_KEYS_FILE
+fn secret_key(secret: String) -> Result<Key, JwtError> {
+    if secret.len() < MIN_HMAC_SECRET_LEN {
+        return Err(JwtError::Config(format!(
+            "SECRET must be at least {} bytes",
+            MIN_HMAC_SECRET_LEN
+        )));
+    }
+    Ok(Key {
+        kid: "default".to_string(),
+        material: KeyMaterial::Hmac(secret.into_bytes()),
+    })
+}
+
+static KEYS: OnceLock<KeySet> = OnceLock::new();
+
+// Loads the key set once; call at startup so misconfiguration fails fast
+pub fn init() -> Result<&'static KeySet, JwtError> {
+    if let Some(keys) = KEYS.get() {
+        return Ok(keys);
+    }
+    let keys = KeySet::from_env()?;
+    Ok(KEYS.get_or_init(|| keys))
+}
+
+pub fn key_set() -> Result<&'static KeySet, JwtError> {
+    init()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(exp_offset: i64) -> Claims {
+        let now = Utc::now().timestamp();
+        Claims {
+            sub: "42".to_string(),
+            iat: now,
+            nbf: Some(now),
+            exp: now + exp_offset,
+            jti: "test-jti".to_string(),
+            iss: Some("cheetah".to_string()),
+            aud: Some(Audience::One("cheetah-api".to_string())),
+            sid: None,
+            token_use: Some(TokenUse::Access),
+        }
+    }
+
+    fn validation() -> Validation {
+        Validation {
+            issuer: Some("cheetah".to_string()),
+            audience: Some("cheetah-api".to_string()),
+            leeway: 0,
+        }
+    }
+
+    fn hmac_key(kid: &str) -> Key {
+        Key {
+            kid: kid.to_string(),
+            material: KeyMaterial::Hmac(b"0123456789abcdef0123456789abcdef".to_vec()),
+        }
+    }
+
+    fn ed25519_key(kid: &str, seed: u8) -> Key {
+        Key {
+            kid: kid.to_string(),
+            material: KeyMaterial::Ed25519Private(ed25519_dalek::SigningKey::from_bytes(&[seed; 32])),
+        }
+    }
+
+    #[test]
+    fn round_trips_with_each_algorithm() {
+        let mut rng = rand::rngs::OsRng;
+        let rsa = rsa::RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let keys = [
+            hmac_key("hs"),
+            ed25519_key("ed", 7),
+            Key {
+                kid: "rs".to_string(),
+                material: KeyMaterial::RsaPrivate(Box::new(rsa)),
+            },
+        ];
+        let sets: Vec<KeySet> = ["hs", "ed", "rs"]
+            .iter()
+            .map(|kid| KeySet::new(keys.iter().map(clone_key).collect(), kid, validation()).unwrap())
+            .collect();
+
+        for set in &sets {
+            let token = set.encode(&claims(60)).unwrap();
+            // Any set holding the key can verify, whichever key it signs with
+            for other in &sets {
+                assert_eq!(other.decode(&token).unwrap().sub, "42");
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_keys_keep_verifying_old_tokens() {
+        let old = KeySet::new(vec![ed25519_key("2026-09", 1)], "2026-09", validation()).unwrap();
+        let token = old.encode(&claims(60)).unwrap();
+
+        let rotated = KeySet::new(vec![ed25519_key("2026-10", 2), ed25519_key("2026-09", 1)], "2026-10", validation())
+            .unwrap();
+        assert!(rotated.decode(&token).is_ok());
+
+        let retired = KeySet::new(vec![ed25519_key("2026-10", 2)], "2026-10", validation()).unwrap();
+        assert!(matches!(retired.decode(&token), Err(JwtError::UnknownKey)));
+    }
+
+    #[test]
+    fn rejects_tampering_and_algorithm_confusion() {
+        let set = KeySet::new(vec![ed25519_key("ed", 7)], "ed", validation()).unwrap();
+        let token = set.encode(&claims(60)).unwrap();
+
+        let mut forged = token.clone();
+        forged.pop();
+        forged.push(if token.ends_with('A') { 'B' } else { 'A' });
+        assert!(matches!(set.decode(&forged), Err(JwtError::InvalidSignature | JwtError::Malformed)));
+
+        let (_, rest) = token.split_once('.').unwrap();
+        for alg in ["none", "HS256"] {
+            let header = b64::URL_SAFE_NO_PAD.encode(format!(r#"{{"alg":"{}","kid":"ed"}}"#, alg));
+            let swapped = format!("{}.{}", header, rest);
+            assert!(matches!(set.decode(&swapped), Err(JwtError::UnsupportedAlgorithm)));
+        }
+    }
+
+    #[test]
+    fn enforces_registered_claims() {
+        let set = KeySet::new(vec![hmac_key("hs")], "hs", validation()).unwrap();
+
+        let expired = set.encode(&claims(-10)).unwrap();
+        assert!(matches!(set.decode(&expired), Err(JwtError::Expired)));
+
+        let mut early = claims(60);
+        early.nbf = Some(Utc::now().timestamp() + 30);
+        assert!(matches!(set.decode(&set.encode(&early).unwrap()), Err(JwtError::NotYetValid)));
+
+        let mut foreign = claims(60);
+        foreign.iss = Some("someone-else".to_string());
+        assert!(matches!(set.decode(&set.encode(&foreign).unwrap()), Err(JwtError::InvalidIssuer)));
+
+        let mut wrong_audience = claims(60);
+        wrong_audience.aud = Some(Audience::Many(vec!["other".to_string()]));
+        assert!(matches!(set.decode(&set.encode(&wrong_audience).unwrap()), Err(JwtError::InvalidAudience)));
+
+        let lenient = KeySet::new(
+            vec![hmac_key("hs")],
+            "hs",
+            Validation {
+                leeway: 60,
+                ..validation()
+            },
+        )
+        .unwrap();
+        assert!(lenient.decode(&expired).is_ok());
+    }
+
+    #[test]
+    fn enforces_token_use() {
+        let set = KeySet::new(vec![hmac_key("hs")], "hs", validation()).unwrap();
+        let access = set.encode(&claims(60)).unwrap();
+        assert!(set.decode_as(&access, TokenUse::Access).is_ok());
+        assert!(matches!(set.decode_as(&access, TokenUse::MfaPending), Err(JwtError::WrongTokenType)));
+
+        let mut untyped = claims(60);
+        untyped.token_use = None;
+        let untyped = set.encode(&untyped).unwrap();
+        assert!(matches!(set.decode_as(&untyped, TokenUse::Access), Err(JwtError::WrongTokenType)));
+    }
+
+    #[test]
+    fn jwks_publishes_only_public_keys() {
+        let set = KeySet::new(vec![hmac_key("hs"), ed25519_key("ed", 7)], "hs", validation()).unwrap();
+        let jwks = set.jwks();
+        let keys = jwks["keys"].as_array().unwrap();
+
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0]["kid"], "ed");
+        assert_eq!(keys[0]["kty"], "OKP");
+        assert!(keys[0].get("d").is_none());
+    }
+
+    #[test]
+    fn verifies_against_published_jwks() {
+        let rsa = rsa::RsaPrivateKey::new(&mut rand::rngs::OsRng, 1024).unwrap();
+        let keys = vec![
+            Key {
+                kid: "rs".to_string(),
+                material: KeyMaterial::RsaPrivate(Box::new(rsa)),
+            },
+            ed25519_key("ed", 9),
+        ];
+        let signer = KeySet::new(keys, "rs", validation()).unwrap();
+        let verifier = KeySet::from_jwks(&signer.jwks(), validation()).unwrap();
+
+        let token = signer.encode(&claims(60)).unwrap();
+        assert_eq!(verifier.decode(&token).unwrap().sub, "42");
+        // Public keys alone can't mint tokens
+        assert!(verifier.encode(&claims(60)).is_err());
+    }
+
+    #[test]
+    fn short_secrets_are_refused() {
+        assert!(matches!(secret_key("yourjwtsecret".to_string()), Err(JwtError::Config(_))));
+        assert!(secret_key("a".repeat(MIN_HMAC_SECRET_LEN)).is_ok());
+    }
+
+    fn clone_key(key: &Key) -> Key {
+        let material = match &key.material {
+            KeyMaterial::Hmac(secret) => KeyMaterial::Hmac(secret.clone()),
+            KeyMaterial::RsaPrivate(rsa) => KeyMaterial::RsaPrivate(rsa.clone()),
+            KeyMaterial::RsaPublic(rsa) => KeyMaterial::RsaPublic(rsa.clone()),
+            KeyMaterial::Ed25519Private(ed) => KeyMaterial::Ed25519Private(ed.clone()),
+            KeyMaterial::Ed25519Public(ed) => KeyMaterial::Ed25519Public(*ed),
+        };
+        Key {
+            kid: key.kid.clone(),
+            material,
+        }
+    }
+}
@@ -1,7 +1,17 @@
 use axum::http::StatusCode;
+use axum::response::IntoResponse;
 use serde::Serialize;
+pub mod audit;
 pub mod avatar;
+pub mod client_ip;
 pub mod crypto;
+pub mod jwt;
+pub mod nickname;
+pub mod oidc;
+pub mod password_policy;
+pub mod webauthn;
+pub mod revocation;
+pub mod totp;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
@@ -33,7 +43,7 @@ impl AppError {
     }
 }
 
-impl axum::response::IntoResponse for AppError {
+impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         let (status, error_message) = match self {
             AppError::Database(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
@@ -42,11 +52,12 @@ impl axum::response::IntoResponse for AppError {
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
-        (status, axum::Json(Resp {
+        (status, axum::Json(Resp::<()> {
             msg: error_message,
             data: None,
             success: false,
         }))
+            .into_response()
     }
 }
 
@@ -0,0 +1,103 @@
+// Rules for nicknames chosen by users; they appear in profile URLs and mentions
+use std::env;
+
+pub const MIN_NICKNAME_LEN: usize = 3;
+pub const MAX_NICKNAME_LEN: usize = 24;
+pub const DEFAULT_NICKNAME_COOLDOWN_DAYS: i64 = 30;
+
+// Names that could pass for staff, the system, or a route. Matched ignoring case and separators,
+// so `Ad_Min` is as reserved as `admin`. RESERVED_NICKNAMES adds more, comma separated.
+const RESERVED_NICKNAMES: &[&str] = &[
+    "admin", "administrator", "moderator", "mod", "staff", "support", "help", "system", "root",
+    "cheetah", "official", "me", "everyone", "here", "null", "undefined", "unknown",
+];
+
+// Deleted accounts are renamed to `deleted-<id>`
+const DELETED_PREFIX: &str = "deleted";
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum NicknameError {
+    #[error("Nickname must have between {MIN_NICKNAME_LEN} and {MAX_NICKNAME_LEN} characters")]
+    Length,
+    #[error("Nickname may only contain letters, numbers, `_`, `-` and `.`, and must start with a letter or number")]
+    Characters,
+    #[error("This nickname is reserved")]
+    Reserved,
+}
+
+fn normalize(nickname: &str) -> String {
+    nickname
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+pub fn is_reserved(nickname: &str) -> bool {
+    let normalized = normalize(nickname);
+    let extra = env::var("RESERVED_NICKNAMES").unwrap_or_default();
+    let placeholder = normalized
+        .strip_prefix(DELETED_PREFIX)
+        .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()));
+    placeholder
+        || RESERVED_NICKNAMES
+            .iter()
+            .copied()
+            .chain(extra.split(',').map(str::trim).filter(|name| !name.is_empty()))
+            .any(|reserved| normalize(reserved) == normalized)
+}
+
+pub fn validate_nickname(nickname: &str) -> Result<(), NicknameError> {
+    let length = nickname.chars().count();
+    if !(MIN_NICKNAME_LEN..=MAX_NICKNAME_LEN).contains(&length) {
+        return Err(NicknameError::Length);
+    }
+    if !nickname.starts_with(char::is_alphanumeric)
+        || !nickname.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(NicknameError::Characters);
+    }
+    if is_reserved(nickname) {
+        return Err(NicknameError::Reserved);
+    }
+    Ok(())
+}
+
+// Days a user must wait between nickname changes, from NICKNAME_CHANGE_COOLDOWN_DAYS
+pub fn nickname_cooldown_days() -> i64 {
+    env::var("NICKNAME_CHANGE_COOLDOWN_DAYS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_NICKNAME_COOLDOWN_DAYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ordinary_nicknames() {
+        assert_eq!(validate_nickname("alice"), Ok(()));
+        assert_eq!(validate_nickname("Bob_the.builder-2"), Ok(()));
+        assert_eq!(validate_nickname("Zoë"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_bad_shapes() {
+        assert_eq!(validate_nickname("ab"), Err(NicknameError::Length));
+        assert_eq!(validate_nickname(&"a".repeat(25)), Err(NicknameError::Length));
+        assert_eq!(validate_nickname("with space"), Err(NicknameError::Characters));
+        assert_eq!(validate_nickname("_leading"), Err(NicknameError::Characters));
+        assert_eq!(validate_nickname("slash/name"), Err(NicknameError::Characters));
+    }
+
+    #[test]
+    fn rejects_reserved_names_in_any_spelling() {
+        assert_eq!(validate_nickname("admin"), Err(NicknameError::Reserved));
+        assert_eq!(validate_nickname("Ad_Min"), Err(NicknameError::Reserved));
+        assert_eq!(validate_nickname("deleted-42"), Err(NicknameError::Reserved));
+        assert!(!is_reserved("administrate"));
+        assert!(!is_reserved("deletedscenes"));
+    }
+}
@@ -0,0 +1,395 @@
+// OpenID Connect relying party: authorization code flow with PKCE (RFC 7636)
+// against any provider that publishes a discovery document, e.g. Google or Keycloak.
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use base64::{Engine, engine::general_purpose as b64};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+
+use crate::libs::jwt::{JwtError, KeySet, Validation};
+use crate::libs::nickname::MAX_NICKNAME_LEN;
+
+// How long a login may sit at the provider before its state row expires
+pub const LOGIN_TIMEOUT_SECS: i64 = 10 * 60;
+
+// Discovery documents and keys are refetched after this, or sooner on an unknown kid
+const METADATA_TTL: Duration = Duration::from_secs(60 * 60);
+const ID_TOKEN_LEEWAY_SECS: i64 = 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    #[error("Unknown identity provider")]
+    UnknownProvider,
+    #[error("Identity provider request failed: {0}")]
+    Http(String),
+    #[error("Invalid discovery document: {0}")]
+    Discovery(String),
+    #[error("Code exchange failed: {0}")]
+    TokenExchange(String),
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(#[from] JwtError),
+    #[error("ID token nonce mismatch")]
+    NonceMismatch,
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(err: reqwest::Error) -> Self {
+        OidcError::Http(err.to_string())
+    }
+}
+
+// One configured identity provider, named in OIDC_PROVIDERS
+#[derive(Debug, Clone)]
+pub struct Provider {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    // Public clients rely on PKCE alone
+    pub client_secret: Option<String>,
+    // Where the provider sends the browser back to; the frontend posts the code on to us
+    pub redirect_uri: String,
+    pub scopes: String,
+}
+
+impl Provider {
+    // OIDC_<NAME>_ISSUER, _CLIENT_ID and _REDIRECT_URI are required, _CLIENT_SECRET and _SCOPES optional
+    pub fn from_env(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        if !enabled_providers().contains(&name) {
+            return None;
+        }
+
+        let prefix = format!("OIDC_{}_", name.to_uppercase().replace('-', "_"));
+        let var = |key: &str| env::var(format!("{}{}", prefix, key)).ok().filter(|value| !value.is_empty());
+
+        Some(Self {
+            issuer: var("ISSUER")?.trim_end_matches('/').to_string(),
+            client_id: var("CLIENT_ID")?,
+            client_secret: var("CLIENT_SECRET"),
+            redirect_uri: var("REDIRECT_URI")?,
+            scopes: var("SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+            name,
+        })
+    }
+
+    // Authorization request the browser is sent to
+    pub fn authorization_url(
+        &self,
+        metadata: &ProviderMetadata,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, OidcError> {
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client_id),
+                ("redirect_uri", &self.redirect_uri),
+                ("scope", &self.scopes),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|err| OidcError::Discovery(format!("authorization_endpoint: {}", err)))?;
+        Ok(url.into())
+    }
+
+    // Trades the authorization code for tokens; only the ID token is used
+    pub async fn exchange_code(
+        &self,
+        metadata: &ProviderMetadata,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<String, OidcError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.redirect_uri),
+            ("client_id", &self.client_id),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let response = http_client().post(&metadata.token_endpoint).form(&form).send().await?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await?;
+        if !status.is_success() {
+            let error = body["error_description"].as_str().or(body["error"].as_str()).unwrap_or("rejected");
+            return Err(OidcError::TokenExchange(format!("{} ({})", error, status)));
+        }
+
+        body["id_token"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| OidcError::TokenExchange("response has no id_token".to_string()))
+    }
+
+    // Signature, issuer, audience, expiry and the nonce we sent with the authorization request
+    pub async fn verify_id_token(&self, id_token: &str, nonce: &str) -> Result<IdTokenClaims, OidcError> {
+        let metadata = self.metadata().await?;
+        let claims = match verify_with(&metadata, &self.client_id, id_token) {
+            // The provider may have rotated its keys since we cached them
+            Err(OidcError::InvalidIdToken(JwtError::UnknownKey)) => {
+                let metadata = self.refresh_metadata().await?;
+                verify_with(&metadata, &self.client_id, id_token)?
+            }
+            result => result?,
+        };
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(OidcError::NonceMismatch);
+        }
+        Ok(claims)
+    }
+
+    // Cached discovery document and keys
+    pub async fn metadata(&self) -> Result<Arc<ProviderMetadata>, OidcError> {
+        let cached = metadata_cache().lock().unwrap().get(&self.name).cloned();
+        match cached {
+            Some(metadata) if metadata.fetched_at.elapsed() < METADATA_TTL => Ok(metadata),
+            _ => self.refresh_metadata().await,
+        }
+    }
+
+    async fn refresh_metadata(&self) -> Result<Arc<ProviderMetadata>, OidcError> {
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+        let discovery: Discovery = http_client().get(&url).send().await?.error_for_status()?.json().await?;
+        // The document must describe the issuer we were configured with, or ID tokens won't match it
+        if discovery.issuer.trim_end_matches('/') != self.issuer {
+            return Err(OidcError::Discovery(format!("issuer is {}", discovery.issuer)));
+        }
+        let jwks = http_client().get(&discovery.jwks_uri).send().await?.error_for_status()?.json().await?;
+
+        let metadata = Arc::new(ProviderMetadata {
+            issuer: discovery.issuer,
+            authorization_endpoint: discovery.authorization_endpoint,
+            token_endpoint: discovery.token_endpoint,
+            jwks,
+            fetched_at: Instant::now(),
+        });
+        metadata_cache().lock().unwrap().insert(self.name.clone(), metadata.clone());
+        Ok(metadata)
+    }
+}
+
+fn verify_with(metadata: &ProviderMetadata, client_id: &str, id_token: &str) -> Result<IdTokenClaims, OidcError> {
+    let validation = Validation {
+        issuer: Some(metadata.issuer.clone()),
+        audience: Some(client_id.to_string()),
+        leeway: ID_TOKEN_LEEWAY_SECS,
+    };
+    let keys = KeySet::from_jwks(&metadata.jwks, validation)?;
+    Ok(keys.decode_claims(id_token)?)
+}
+
+// Names from OIDC_PROVIDERS, e.g. "google,keycloak"
+pub fn enabled_providers() -> Vec<String> {
+    env::var("OIDC_PROVIDERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    jwks: serde_json::Value,
+    fetched_at: Instant,
+}
+
+fn metadata_cache() -> &'static Mutex<HashMap<String, Arc<ProviderMetadata>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Arc<ProviderMetadata>>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("HTTP client configuration is valid")
+    })
+}
+
+// The ID token claims we read; `exp`, `iss` and `aud` are checked by the key set
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+    #[serde(default)]
+    pub picture: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+impl IdTokenClaims {
+    // Only an address the provider vouches for may be matched against our accounts
+    pub fn verified_email(&self) -> Option<String> {
+        self.email
+            .as_deref()
+            .filter(|_| self.email_verified)
+            .map(|email| email.trim().to_lowercase())
+    }
+
+    // Starting point for a new account's nickname; callers make it unique
+    pub fn nickname_hint(&self) -> String {
+        let source = self
+            .preferred_username
+            .as_deref()
+            .or(self.name.as_deref())
+            .or(self.email.as_deref().and_then(|email| email.split('@').next()))
+            .unwrap_or("");
+        sanitize_nickname(source)
+    }
+}
+
+// Some providers send `"email_verified": "true"`
+fn bool_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Text(String),
+    }
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(value) => value,
+        Flag::Text(value) => value.eq_ignore_ascii_case("true"),
+    })
+}
+
+// Letters, digits, `_`, `-` and `.`; whitespace becomes `_`
+pub fn sanitize_nickname(source: &str) -> String {
+    let nickname: String = source
+        .trim()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') => Some(c),
+            c if c.is_whitespace() => Some('_'),
+            _ => None,
+        })
+        .take(MAX_NICKNAME_LEN)
+        .collect();
+
+    if nickname.is_empty() { "user".to_string() } else { nickname }
+}
+
+// 32 random bytes, base64url: used for state, nonce and the PKCE code verifier
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    b64::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// S256 code challenge for a code verifier
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    b64::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn random_tokens_are_valid_code_verifiers() {
+        let token = random_token();
+        // RFC 7636 wants 43 to 128 unreserved characters
+        assert_eq!(token.len(), 43);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_ne!(token, random_token());
+    }
+
+    #[test]
+    fn authorization_url_carries_pkce_and_nonce() {
+        let provider = Provider {
+            name: "mock".to_string(),
+            issuer: "http://localhost:8080/default".to_string(),
+            client_id: "cheetah".to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost:8081/oauth/callback".to_string(),
+            scopes: "openid email profile".to_string(),
+        };
+        let metadata = ProviderMetadata {
+            issuer: provider.issuer.clone(),
+            authorization_endpoint: "http://localhost:8080/default/authorize".to_string(),
+            token_endpoint: "http://localhost:8080/default/token".to_string(),
+            jwks: serde_json::json!({ "keys": [] }),
+            fetched_at: Instant::now(),
+        };
+
+        let url = provider.authorization_url(&metadata, "state-1", "nonce-1", "challenge-1").unwrap();
+        let url = reqwest::Url::parse(&url).unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(url.path(), "/default/authorize");
+        assert_eq!(params["response_type"], "code");
+        assert_eq!(params["client_id"], "cheetah");
+        assert_eq!(params["redirect_uri"], "http://localhost:8081/oauth/callback");
+        assert_eq!(params["scope"], "openid email profile");
+        assert_eq!(params["state"], "state-1");
+        assert_eq!(params["nonce"], "nonce-1");
+        assert_eq!(params["code_challenge"], "challenge-1");
+        assert_eq!(params["code_challenge_method"], "S256");
+    }
+
+    #[test]
+    fn unverified_emails_are_not_offered_for_linking() {
+        let claims: IdTokenClaims = serde_json::from_value(serde_json::json!({
+            "sub": "1",
+            "email": "Alice@Example.com",
+            "email_verified": "true",
+        }))
+        .unwrap();
+        assert_eq!(claims.verified_email().as_deref(), Some("alice@example.com"));
+
+        let claims: IdTokenClaims = serde_json::from_value(serde_json::json!({
+            "sub": "1",
+            "email": "alice@example.com",
+        }))
+        .unwrap();
+        assert_eq!(claims.verified_email(), None);
+    }
+
+    #[test]
+    fn nicknames_are_sanitized() {
+        assert_eq!(sanitize_nickname("Alice Smith"), "Alice_Smith");
+        assert_eq!(sanitize_nickname("  bob<script>@x "), "bobscriptx");
+        assert_eq!(sanitize_nickname("!!!"), "user");
+        assert_eq!(sanitize_nickname(&"a".repeat(40)).len(), MAX_NICKNAME_LEN);
+    }
+}
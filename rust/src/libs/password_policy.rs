@@ -0,0 +1,234 @@
+// Rules a new password must meet: length, character classes, a zxcvbn strength score,
+// and not appearing in a local copy of the Pwned Passwords corpus.
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::PathBuf;
+
+use sha1::{Digest, Sha1};
+
+pub const DEFAULT_MIN_LENGTH: usize = 8;
+// Argon2 hashes whatever it is given, so very long inputs only cost us CPU
+pub const MAX_LENGTH: usize = 128;
+pub const DEFAULT_MIN_SCORE: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Lower,
+    Upper,
+    Letter,
+    Digit,
+    Symbol,
+}
+
+impl CharClass {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            "letter" => Some(Self::Letter),
+            "digit" => Some(Self::Digit),
+            "symbol" => Some(Self::Symbol),
+            _ => None,
+        }
+    }
+
+    fn matches(self, c: char) -> bool {
+        match self {
+            Self::Lower => c.is_lowercase(),
+            Self::Upper => c.is_uppercase(),
+            Self::Letter => c.is_alphabetic(),
+            Self::Digit => c.is_numeric(),
+            Self::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Lower => "a lowercase letter",
+            Self::Upper => "an uppercase letter",
+            Self::Letter => "a letter",
+            Self::Digit => "a number",
+            Self::Symbol => "a symbol",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasswordError {
+    #[error("Password must have at least {0} characters")]
+    TooShort(usize),
+    #[error("Password must have at most {0} characters")]
+    TooLong(usize),
+    #[error("Password must contain {}", .0.describe())]
+    MissingClass(CharClass),
+    #[error("Password is too easy to guess. {0}")]
+    TooWeak(String),
+    #[error("Password has appeared in a data breach, choose another one")]
+    Breached,
+}
+
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub required_classes: Vec<CharClass>,
+    // zxcvbn score from 0 (guessable in 10^3 tries) to 4 (more than 10^10)
+    pub min_score: u8,
+    // Directory of Pwned Passwords range files, see `is_breached`
+    pub breached_dir: Option<PathBuf>,
+}
+
+impl PasswordPolicy {
+    // The defaults keep the old backend's rule (a letter and a number) and add a length and strength floor
+    pub fn from_env() -> Self {
+        Self {
+            min_length: env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_MIN_LENGTH)
+                .min(MAX_LENGTH),
+            required_classes: env::var("PASSWORD_REQUIRE")
+                .unwrap_or_else(|_| "letter,digit".to_string())
+                .split(',')
+                .filter_map(CharClass::parse)
+                .collect(),
+            min_score: env::var("PASSWORD_MIN_SCORE")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_MIN_SCORE)
+                .min(4),
+            breached_dir: env::var("PASSWORD_BREACHED_DIR").ok().filter(|dir| !dir.is_empty()).map(PathBuf::from),
+        }
+    }
+
+    // `user_inputs` are the account's own details (email, nickname), which make a password easier to guess
+    pub fn check(&self, password: &str, user_inputs: &[&str]) -> Result<(), PasswordError> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(PasswordError::TooShort(self.min_length));
+        }
+        if length > MAX_LENGTH {
+            return Err(PasswordError::TooLong(MAX_LENGTH));
+        }
+
+        if let Some(class) = self
+            .required_classes
+            .iter()
+            .find(|class| !password.chars().any(|c| class.matches(c)))
+        {
+            return Err(PasswordError::MissingClass(*class));
+        }
+
+        let entropy = zxcvbn::zxcvbn(password, user_inputs);
+        if u8::from(entropy.score()) < self.min_score {
+            let feedback = entropy
+                .feedback()
+                .map(|feedback| feedback.to_string().trim().to_string())
+                .filter(|feedback| !feedback.is_empty())
+                .unwrap_or_else(|| "Add another word or two.".to_string());
+            return Err(PasswordError::TooWeak(feedback));
+        }
+
+        if let Some(dir) = &self.breached_dir {
+            // An unreadable corpus shouldn't block every signup; log it and move on
+            match is_breached(dir, password) {
+                Ok(true) => return Err(PasswordError::Breached),
+                Ok(false) => {}
+                Err(err) => eprintln!("Breached password check failed: {}", err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Looks the password up in a local copy of Pwned Passwords laid out like its k-anonymity
+// range API: one file per 5-hex-digit SHA-1 prefix (`5BAA6` or `5BAA6.txt`), each line
+// `<35-digit suffix>:<count>`. Only the one small file for the prefix is read.
+pub fn is_breached(dir: &std::path::Path, password: &str) -> std::io::Result<bool> {
+    let digest: String = Sha1::digest(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    let (prefix, suffix) = digest.split_at(5);
+
+    let file = match File::open(dir.join(prefix)).or_else(|_| File::open(dir.join(format!("{}.txt", prefix)))) {
+        Ok(file) => file,
+        // Prefixes nobody has used have no file
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let (candidate, count) = line.trim().split_once(':').unwrap_or((line.trim(), "1"));
+        // Padded entries carry a count of 0
+        if candidate.eq_ignore_ascii_case(suffix) && count.trim() != "0" {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 8,
+            required_classes: vec![CharClass::Letter, CharClass::Digit],
+            min_score: 2,
+            breached_dir: None,
+        }
+    }
+
+    #[test]
+    fn enforces_length_and_classes() {
+        assert!(matches!(policy().check("ab1", &[]), Err(PasswordError::TooShort(8))));
+        assert!(matches!(policy().check(&"a1".repeat(65), &[]), Err(PasswordError::TooLong(MAX_LENGTH))));
+        assert!(matches!(
+            policy().check("correct horse battery staple", &[]),
+            Err(PasswordError::MissingClass(CharClass::Digit))
+        ));
+        assert!(matches!(
+            policy().check("1234 5678 9012", &[]),
+            Err(PasswordError::MissingClass(CharClass::Letter))
+        ));
+    }
+
+    #[test]
+    fn rejects_guessable_passwords() {
+        assert!(matches!(policy().check("password1", &[]), Err(PasswordError::TooWeak(_))));
+        // Built from the user's own details
+        assert!(matches!(
+            policy().check("alicesmith1", &["alicesmith", "alice@example.com"]),
+            Err(PasswordError::TooWeak(_))
+        ));
+        assert!(policy().check("tundra7 mosaic lantern", &[]).is_ok());
+    }
+
+    #[test]
+    fn finds_passwords_in_range_files() {
+        let dir = std::env::temp_dir().join(format!("pwned-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // SHA-1("password") is 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+        std::fs::write(
+            dir.join("5BAA6.txt"),
+            "0018A45C4D1DEF81644B54AB7F969B88D65:0\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\r\n",
+        )
+        .unwrap();
+
+        assert!(is_breached(&dir, "password").unwrap());
+        assert!(!is_breached(&dir, "tundra7 mosaic lantern").unwrap());
+
+        let policy = PasswordPolicy {
+            min_length: 0,
+            required_classes: vec![],
+            min_score: 0,
+            breached_dir: Some(dir.clone()),
+        };
+        assert!(matches!(policy.check("password", &[]), Err(PasswordError::Breached)));
+        assert!(policy.check("tundra7 mosaic lantern", &[]).is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
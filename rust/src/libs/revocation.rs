@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+// In-memory copy of `revoked_tokens`, checked on every authenticated request.
+// Local revocations land here immediately; other instances pick them up on the next sync.
+#[derive(Default)]
+pub struct RevocationList {
+    entries: RwLock<HashMap<String, DateTime<Utc>>>,
+}
+
+impl RevocationList {
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        let entries = self.entries.read().unwrap_or_else(|err| err.into_inner());
+        entries.get(jti).is_some_and(|expires_at| *expires_at > Utc::now())
+    }
+
+    pub fn insert(&self, jti: String, expires_at: DateTime<Utc>) {
+        let mut entries = self.entries.write().unwrap_or_else(|err| err.into_inner());
+        entries.insert(jti, expires_at);
+    }
+
+    // Expired tokens fail verification on their own, so their entries can go
+    pub fn purge_expired(&self) {
+        let now = Utc::now();
+        let mut entries = self.entries.write().unwrap_or_else(|err| err.into_inner());
+        entries.retain(|_, expires_at| *expires_at > now);
+    }
+}
+
+// Process-wide list; middleware_auth has no access to AppState
+pub fn revocation_list() -> &'static RevocationList {
+    static LIST: OnceLock<RevocationList> = OnceLock::new();
+    LIST.get_or_init(RevocationList::default)
+}
+
+pub fn is_token_revoked(jti: &str) -> bool {
+    revocation_list().is_revoked(jti)
+}
+
+#[derive(sqlx::FromRow)]
+struct RevokedToken {
+    jti: String,
+    expires_at: DateTime<Utc>,
+}
+
+pub async fn revoke_token(db: &Pool<Postgres>, jti: &str, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO revoked_tokens (jti, expires_at) VALUES ($1, $2) ON CONFLICT (jti) DO NOTHING")
+        .bind(jti)
+        .bind(expires_at)
+        .execute(db)
+        .await?;
+    revocation_list().insert(jti.to_string(), expires_at);
+    Ok(())
+}
+
+// Denies every access token of the sessions that hasn't expired yet
+pub async fn revoke_session_tokens(db: &Pool<Postgres>, session_ids: &[Uuid]) -> Result<(), sqlx::Error> {
+    if session_ids.is_empty() {
+        return Ok(());
+    }
+
+    let revoked = sqlx::query_as::<_, RevokedToken>(
+        r#"
+        INSERT INTO revoked_tokens (jti, expires_at)
+        SELECT access_jti, access_expires_at FROM refresh_tokens
+        WHERE "sessionId" = ANY($1) AND access_jti IS NOT NULL AND access_expires_at > NOW()
+        ON CONFLICT (jti) DO NOTHING
+        RETURNING jti, expires_at
+        "#,
+    )
+    .bind(session_ids)
+    .fetch_all(db)
+    .await?;
+
+    for token in revoked {
+        revocation_list().insert(token.jti, token.expires_at);
+    }
+    Ok(())
+}
+
+// Loads entries revoked since `since` (everything when None) and drops expired rows.
+// Returns where the next sync should start, read from the database clock that stamps
+// created_at, so skew between this host and the database can't open a gap.
+pub async fn sync_revocations(
+    db: &Pool<Postgres>,
+    since: Option<DateTime<Utc>>,
+) -> Result<DateTime<Utc>, sqlx::Error> {
+    // Overlap the window a little so a row committed mid-sync isn't skipped
+    let next_since = sqlx::query_scalar::<_, DateTime<Utc>>("SELECT NOW() - INTERVAL '5 seconds'")
+        .fetch_one(db)
+        .await?;
+    let revoked = sqlx::query_as::<_, RevokedToken>(
+        "SELECT jti, expires_at FROM revoked_tokens WHERE expires_at > NOW() AND ($1::TIMESTAMPTZ IS NULL OR created_at >= $1)",
+    )
+    .bind(since)
+    .fetch_all(db)
+    .await?;
+
+    let list = revocation_list();
+    for token in revoked {
+        list.insert(token.jti, token.expires_at);
+    }
+    list.purge_expired();
+
+    sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= NOW()")
+        .execute(db)
+        .await?;
+    Ok(next_since)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn entries_stop_matching_once_the_token_expires() {
+        let list = RevocationList::default();
+        list.insert("live".to_string(), Utc::now() + Duration::minutes(5));
+        list.insert("stale".to_string(), Utc::now() - Duration::minutes(5));
+
+        assert!(list.is_revoked("live"));
+        assert!(!list.is_revoked("stale"));
+        assert!(!list.is_revoked("unknown"));
+
+        list.purge_expired();
+        assert_eq!(list.entries.read().unwrap().len(), 1);
+    }
+}
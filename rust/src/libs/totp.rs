@@ -0,0 +1,151 @@
+// RFC 6238 time-based one-time passwords (SHA-1, 6 digits, 30 s steps), the
+// parameters every authenticator app supports.
+use base32::Alphabet;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use sha1::Sha1;
+
+pub const DIGITS: u32 = 6;
+pub const STEP_SECS: i64 = 30;
+// Steps accepted on either side of the current one, for clock drift
+pub const SKEW_STEPS: i64 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const SECRET_ALPHABET: Alphabet = Alphabet::Rfc4648 { padding: false };
+
+// 160-bit secret, base32 as authenticator apps expect it
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    base32::encode(SECRET_ALPHABET, &bytes)
+}
+
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]])
+        & 0x7fff_ffff;
+    binary % 10u32.pow(DIGITS)
+}
+
+pub fn step_at(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECS)
+}
+
+// Returns the matching time step so callers can refuse to accept it twice
+pub fn verify_code(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let secret = base32::decode(SECRET_ALPHABET, secret)?;
+
+    let current = step_at(unix_time);
+    // Checks every candidate so the timing doesn't reveal which step matched
+    let mut matched = None;
+    for step in current - SKEW_STEPS..=current + SKEW_STEPS {
+        if step >= 0 && hotp(&secret, step as u64) == code && matched.is_none() {
+            matched = Some(step);
+        }
+    }
+    matched
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// Key URI format understood by Google Authenticator and compatible apps
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+// Codes look like `k3x9m-7qp2d`: 50 random bits each, easy to type from paper
+pub fn generate_recovery_codes() -> Vec<String> {
+    const ALPHABET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 10];
+            OsRng.fill_bytes(&mut bytes);
+            let chars: String = bytes.iter().map(|byte| ALPHABET[(*byte & 0x1f) as usize] as char).collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+// Recovery codes are compared after dropping case, spaces and dashes
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B secret, base32 encoded
+    fn rfc_secret() -> String {
+        base32::encode(SECRET_ALPHABET, b"12345678901234567890")
+    }
+
+    #[test]
+    fn matches_rfc_6238_vectors() {
+        // Last six digits of the SHA-1 vectors
+        for (time, code) in [(59, "287082"), (1111111109, "081804"), (1234567890, "005924"), (2000000000, "279037")] {
+            assert_eq!(verify_code(&rfc_secret(), code, time), Some(step_at(time)));
+        }
+    }
+
+    #[test]
+    fn accepts_one_step_of_drift_only() {
+        let secret = rfc_secret();
+        assert_eq!(verify_code(&secret, "287082", 59 + STEP_SECS), Some(1));
+        assert_eq!(verify_code(&secret, "287082", 59 + 2 * STEP_SECS), None);
+        assert_eq!(verify_code(&secret, "28708", 59), None);
+        assert_eq!(verify_code(&secret, "28708a", 59), None);
+    }
+
+    #[test]
+    fn recovery_codes_are_distinct_and_normalize() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|code| code.len() == 11 && code.as_bytes()[5] == b'-'));
+
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), codes.len());
+
+        assert_eq!(normalize_recovery_code(" K3X9M-7qp2d "), "k3x9m7qp2d");
+    }
+
+    #[test]
+    fn builds_otpauth_uri() {
+        assert_eq!(
+            otpauth_uri("Cheetah", "ana@example.com", "ABC"),
+            "otpauth://totp/Cheetah:ana%40example.com?secret=ABC&issuer=Cheetah&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
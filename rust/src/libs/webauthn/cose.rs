@@ -0,0 +1,78 @@
+// The two COSE key types we accept for passkeys (RFC 9053): ES256 on P-256, which
+// every platform authenticator supports, and EdDSA on Ed25519.
+use ciborium::Value;
+use p256::ecdsa::signature::Verifier;
+
+use super::WebauthnError;
+
+pub const ALG_ES256: i64 = -7;
+pub const ALG_EDDSA: i64 = -8;
+
+const KTY_OKP: i64 = 1;
+const KTY_EC2: i64 = 2;
+const CRV_P256: i64 = 1;
+const CRV_ED25519: i64 = 6;
+
+pub enum PublicKey {
+    Es256(p256::ecdsa::VerifyingKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+fn int_key(map: &[(Value, Value)], key: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(k, _)| k.as_integer().is_some_and(|k| i128::from(k) == i128::from(key)))
+        .map(|(_, v)| v)
+}
+
+fn int_value(map: &[(Value, Value)], key: i64) -> Result<i64, WebauthnError> {
+    int_key(map, key)
+        .and_then(Value::as_integer)
+        .and_then(|v| i64::try_from(v).ok())
+        .ok_or(WebauthnError::UnsupportedKey)
+}
+
+fn bytes_value(map: &[(Value, Value)], key: i64) -> Result<&[u8], WebauthnError> {
+    int_key(map, key)
+        .and_then(Value::as_bytes)
+        .map(Vec::as_slice)
+        .ok_or(WebauthnError::UnsupportedKey)
+}
+
+impl PublicKey {
+    pub fn from_cose(bytes: &[u8]) -> Result<Self, WebauthnError> {
+        let value: Value = ciborium::from_reader(bytes).map_err(|_| WebauthnError::Malformed("credential public key"))?;
+        let map = value.as_map().ok_or(WebauthnError::Malformed("credential public key"))?;
+
+        // Labels: 1 kty, 3 alg, -1 crv, -2 x, -3 y
+        match (int_value(map, 1)?, int_value(map, 3)?, int_value(map, -1)?) {
+            (KTY_EC2, ALG_ES256, CRV_P256) => {
+                let (x, y) = (bytes_value(map, -2)?, bytes_value(map, -3)?);
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(WebauthnError::UnsupportedKey);
+                }
+                let mut sec1 = Vec::with_capacity(65);
+                sec1.push(0x04);
+                sec1.extend_from_slice(x);
+                sec1.extend_from_slice(y);
+                let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&sec1).map_err(|_| WebauthnError::UnsupportedKey)?;
+                Ok(Self::Es256(key))
+            }
+            (KTY_OKP, ALG_EDDSA, CRV_ED25519) => {
+                let x: [u8; 32] = bytes_value(map, -2)?.try_into().map_err(|_| WebauthnError::UnsupportedKey)?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(&x).map_err(|_| WebauthnError::UnsupportedKey)?;
+                Ok(Self::Ed25519(key))
+            }
+            _ => Err(WebauthnError::UnsupportedKey),
+        }
+    }
+
+    // ES256 signatures arrive DER encoded, EdDSA ones raw
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            Self::Es256(key) => p256::ecdsa::Signature::from_der(signature)
+                .is_ok_and(|signature| key.verify(message, &signature).is_ok()),
+            Self::Ed25519(key) => ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify_strict(message, &signature).is_ok()),
+        }
+    }
+}
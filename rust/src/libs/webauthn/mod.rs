@@ -0,0 +1,560 @@
+// Minimal WebAuthn relying party (Level 2): registration and authentication
+// ceremonies for passkeys. Attestation statements are not checked since we ask
+// for `attestation: "none"`; we trust the key the authenticator hands us, not its make.
+pub mod cose;
+
+use std::env;
+
+use base64::{Engine, engine::general_purpose as b64};
+use ciborium::Value;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use cose::{ALG_EDDSA, ALG_ES256, PublicKey};
+
+pub const CEREMONY_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_DATA: u8 = 0x40;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebauthnError {
+    #[error("Malformed {0}")]
+    Malformed(&'static str),
+    #[error("Unexpected ceremony type")]
+    WrongCeremony,
+    #[error("Challenge mismatch")]
+    ChallengeMismatch,
+    #[error("Origin not allowed")]
+    OriginMismatch,
+    #[error("Relying party ID mismatch")]
+    RpIdMismatch,
+    #[error("User presence was not confirmed")]
+    UserNotPresent,
+    #[error("User verification is required")]
+    UserNotVerified,
+    #[error("Unsupported credential key")]
+    UnsupportedKey,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Signature counter went backwards, the credential may be cloned")]
+    CounterRegression,
+}
+
+// This server as a WebAuthn relying party
+pub struct RelyingParty {
+    // Registrable domain the credentials are scoped to, e.g. example.com
+    pub id: String,
+    pub name: String,
+    // Exact origins the ceremonies may run on, e.g. https://app.example.com
+    pub origins: Vec<String>,
+    pub require_user_verification: bool,
+}
+
+impl RelyingParty {
+    pub fn from_env() -> Self {
+        Self {
+            id: env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string()),
+            name: env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "Cheetah".to_string()),
+            origins: env::var("WEBAUTHN_ORIGINS")
+                .unwrap_or_else(|_| "http://localhost:8081".to_string())
+                .split(',')
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+            require_user_verification: env::var("WEBAUTHN_REQUIRE_USER_VERIFICATION").map_or(true, |value| value != "false"),
+        }
+    }
+
+    fn user_verification(&self) -> &'static str {
+        if self.require_user_verification { "required" } else { "preferred" }
+    }
+
+    // PublicKeyCredentialCreationOptions for navigator.credentials.create()
+    pub fn creation_options(
+        &self,
+        challenge: &str,
+        user_handle: &[u8],
+        user_name: &str,
+        exclude_credentials: &[String],
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "challenge": challenge,
+            "rp": { "id": self.id, "name": self.name },
+            "user": {
+                "id": b64::URL_SAFE_NO_PAD.encode(user_handle),
+                "name": user_name,
+                "displayName": user_name,
+            },
+            "pubKeyCredParams": [
+                { "type": "public-key", "alg": ALG_ES256 },
+                { "type": "public-key", "alg": ALG_EDDSA },
+            ],
+            "timeout": CEREMONY_TIMEOUT_MS,
+            "attestation": "none",
+            "authenticatorSelection": {
+                "residentKey": "required",
+                "userVerification": self.user_verification(),
+            },
+            "excludeCredentials": exclude_credentials
+                .iter()
+                .map(|id| serde_json::json!({ "type": "public-key", "id": id }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    // PublicKeyCredentialRequestOptions for navigator.credentials.get(). With no
+    // allowed credentials the authenticator offers any passkey it holds for us.
+    pub fn request_options(&self, challenge: &str, allow_credentials: &[String]) -> serde_json::Value {
+        serde_json::json!({
+            "challenge": challenge,
+            "rpId": self.id,
+            "timeout": CEREMONY_TIMEOUT_MS,
+            "userVerification": self.user_verification(),
+            "allowCredentials": allow_credentials
+                .iter()
+                .map(|id| serde_json::json!({ "type": "public-key", "id": id }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn verify_registration(
+        &self,
+        expected_challenge: &str,
+        credential: &RegistrationCredential,
+    ) -> Result<VerifiedCredential, WebauthnError> {
+        let client_data = decode_b64(&credential.response.client_data_json, "clientDataJSON")?;
+        self.check_client_data(&client_data, "webauthn.create", expected_challenge)?;
+
+        let attestation = decode_b64(&credential.response.attestation_object, "attestationObject")?;
+        let attestation: Value =
+            ciborium::from_reader(attestation.as_slice()).map_err(|_| WebauthnError::Malformed("attestationObject"))?;
+        let auth_data = attestation
+            .as_map()
+            .and_then(|map| map.iter().find(|(key, _)| key.as_text() == Some("authData")))
+            .and_then(|(_, value)| value.as_bytes())
+            .ok_or(WebauthnError::Malformed("attestationObject"))?;
+
+        let parsed = self.check_authenticator_data(auth_data)?;
+        let (credential_id, public_key) = parsed
+            .attested_credential
+            .ok_or(WebauthnError::Malformed("authenticator data"))?;
+        // The ID the browser reports must be the one the authenticator attested
+        if decode_b64(&credential.raw_id, "rawId")? != credential_id {
+            return Err(WebauthnError::Malformed("credential ID"));
+        }
+        PublicKey::from_cose(&public_key)?;
+
+        Ok(VerifiedCredential {
+            credential_id: b64::URL_SAFE_NO_PAD.encode(&credential_id),
+            public_key,
+            sign_count: parsed.sign_count,
+        })
+    }
+
+    // `public_key` and `stored_sign_count` come from the credential registered under `credential.raw_id`
+    pub fn verify_assertion(
+        &self,
+        expected_challenge: &str,
+        credential: &AssertionCredential,
+        public_key: &[u8],
+        stored_sign_count: u32,
+    ) -> Result<VerifiedAssertion, WebauthnError> {
+        let client_data = decode_b64(&credential.response.client_data_json, "clientDataJSON")?;
+        self.check_client_data(&client_data, "webauthn.get", expected_challenge)?;
+
+        let auth_data = decode_b64(&credential.response.authenticator_data, "authenticatorData")?;
+        let parsed = self.check_authenticator_data(&auth_data)?;
+
+        // The signature covers authenticatorData || SHA-256(clientDataJSON)
+        let mut signed = auth_data;
+        signed.extend_from_slice(&Sha256::digest(&client_data));
+        let signature = decode_b64(&credential.response.signature, "signature")?;
+        if !PublicKey::from_cose(public_key)?.verify(&signed, &signature) {
+            return Err(WebauthnError::InvalidSignature);
+        }
+
+        // Authenticators that keep no counter always send 0
+        if (parsed.sign_count != 0 || stored_sign_count != 0) && parsed.sign_count <= stored_sign_count {
+            return Err(WebauthnError::CounterRegression);
+        }
+
+        Ok(VerifiedAssertion {
+            sign_count: parsed.sign_count,
+            user_verified: parsed.flags & FLAG_USER_VERIFIED != 0,
+        })
+    }
+
+    fn check_client_data(&self, client_data: &[u8], ceremony: &str, expected_challenge: &str) -> Result<(), WebauthnError> {
+        let client_data: ClientData =
+            serde_json::from_slice(client_data).map_err(|_| WebauthnError::Malformed("clientDataJSON"))?;
+
+        if client_data.kind != ceremony {
+            return Err(WebauthnError::WrongCeremony);
+        }
+        // Both sides are base64url without padding, so a plain comparison is enough
+        if client_data.challenge != expected_challenge {
+            return Err(WebauthnError::ChallengeMismatch);
+        }
+        if !self.origins.contains(&client_data.origin) {
+            return Err(WebauthnError::OriginMismatch);
+        }
+        Ok(())
+    }
+
+    fn check_authenticator_data(&self, auth_data: &[u8]) -> Result<AuthenticatorData, WebauthnError> {
+        let parsed = AuthenticatorData::parse(auth_data)?;
+
+        if parsed.rp_id_hash[..] != Sha256::digest(self.id.as_bytes())[..] {
+            return Err(WebauthnError::RpIdMismatch);
+        }
+        if parsed.flags & FLAG_USER_PRESENT == 0 {
+            return Err(WebauthnError::UserNotPresent);
+        }
+        if self.require_user_verification && parsed.flags & FLAG_USER_VERIFIED == 0 {
+            return Err(WebauthnError::UserNotVerified);
+        }
+        Ok(parsed)
+    }
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData {
+    rp_id_hash: [u8; 32],
+    flags: u8,
+    sign_count: u32,
+    // Credential ID and COSE public key, present on registration
+    attested_credential: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl AuthenticatorData {
+    // Layout: rpIdHash(32) flags(1) signCount(4) [aaguid(16) idLen(2) id(idLen) COSE key]
+    fn parse(data: &[u8]) -> Result<Self, WebauthnError> {
+        let malformed = WebauthnError::Malformed("authenticator data");
+        if data.len() < 37 {
+            return Err(malformed);
+        }
+        let rp_id_hash: [u8; 32] = data[..32].try_into().map_err(|_| WebauthnError::Malformed("authenticator data"))?;
+        let flags = data[32];
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+        let attested_credential = if flags & FLAG_ATTESTED_DATA != 0 {
+            let rest = &data[37..];
+            if rest.len() < 18 {
+                return Err(malformed);
+            }
+            let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+            let id = rest.get(18..18 + id_len).ok_or(WebauthnError::Malformed("authenticator data"))?;
+            // Re-encoded rather than sliced, since extensions may follow the key
+            let key: Value = ciborium::from_reader(&rest[18 + id_len..]).map_err(|_| WebauthnError::Malformed("credential public key"))?;
+            let mut key_bytes = Vec::new();
+            ciborium::into_writer(&key, &mut key_bytes).map_err(|_| WebauthnError::Malformed("credential public key"))?;
+            Some((id.to_vec(), key_bytes))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested_credential,
+        })
+    }
+}
+
+fn decode_b64(value: &str, what: &'static str) -> Result<Vec<u8>, WebauthnError> {
+    // Some clients pad their base64url; the spec says they shouldn't
+    b64::URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| WebauthnError::Malformed(what))
+}
+
+// 32 random bytes, base64url; the same encoding the browser echoes back in clientDataJSON
+pub fn generate_challenge() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    b64::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// PublicKeyCredential.toJSON() of navigator.credentials.create()
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationCredential {
+    pub raw_id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+// PublicKeyCredential.toJSON() of navigator.credentials.get()
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionCredential {
+    pub raw_id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    pub user_handle: Option<String>,
+}
+
+pub struct VerifiedCredential {
+    // base64url, as browsers refer to credentials
+    pub credential_id: String,
+    // COSE_Key bytes
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+pub struct VerifiedAssertion {
+    pub sign_count: u32,
+    // The authenticator checked a PIN or biometric, not just presence
+    pub user_verified: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Signer as _;
+
+    const ORIGIN: &str = "https://chat.example.com";
+
+    fn rp() -> RelyingParty {
+        RelyingParty {
+            id: "example.com".to_string(),
+            name: "Cheetah".to_string(),
+            origins: vec![ORIGIN.to_string()],
+            require_user_verification: true,
+        }
+    }
+
+    enum SoftKey {
+        P256(p256::ecdsa::SigningKey),
+        Ed25519(ed25519_dalek::SigningKey),
+    }
+
+    // Software authenticator: what a platform authenticator and the browser produce together
+    struct SoftAuthenticator {
+        credential_id: Vec<u8>,
+        key: SoftKey,
+        rp_id: String,
+        origin: String,
+        flags: u8,
+        sign_count: u32,
+    }
+
+    impl SoftAuthenticator {
+        fn new(key: SoftKey) -> Self {
+            Self {
+                credential_id: vec![7; 16],
+                key,
+                rp_id: "example.com".to_string(),
+                origin: ORIGIN.to_string(),
+                flags: FLAG_USER_PRESENT | FLAG_USER_VERIFIED,
+                sign_count: 0,
+            }
+        }
+
+        fn cose_key(&self) -> Vec<u8> {
+            let int = |v: i64| Value::Integer(v.into());
+            let map = match &self.key {
+                SoftKey::P256(key) => {
+                    let point = key.verifying_key().to_encoded_point(false);
+                    vec![
+                        (int(1), int(2)),
+                        (int(3), int(ALG_ES256)),
+                        (int(-1), int(1)),
+                        (int(-2), Value::Bytes(point.x().unwrap().to_vec())),
+                        (int(-3), Value::Bytes(point.y().unwrap().to_vec())),
+                    ]
+                }
+                SoftKey::Ed25519(key) => vec![
+                    (int(1), int(1)),
+                    (int(3), int(ALG_EDDSA)),
+                    (int(-1), int(6)),
+                    (int(-2), Value::Bytes(key.verifying_key().to_bytes().to_vec())),
+                ],
+            };
+            let mut bytes = Vec::new();
+            ciborium::into_writer(&Value::Map(map), &mut bytes).unwrap();
+            bytes
+        }
+
+        fn auth_data(&self, attested: bool) -> Vec<u8> {
+            let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+            data.push(self.flags | if attested { FLAG_ATTESTED_DATA } else { 0 });
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            if attested {
+                data.extend_from_slice(&[0; 16]);
+                data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                data.extend_from_slice(&self.cose_key());
+            }
+            data
+        }
+
+        fn client_data(&self, kind: &str, challenge: &str) -> Vec<u8> {
+            serde_json::to_vec(&serde_json::json!({
+                "type": kind,
+                "challenge": challenge,
+                "origin": self.origin,
+                "crossOrigin": false,
+            }))
+            .unwrap()
+        }
+
+        fn register(&self, challenge: &str) -> RegistrationCredential {
+            let attestation = Value::Map(vec![
+                (Value::Text("fmt".into()), Value::Text("none".into())),
+                (Value::Text("attStmt".into()), Value::Map(vec![])),
+                (Value::Text("authData".into()), Value::Bytes(self.auth_data(true))),
+            ]);
+            let mut attestation_object = Vec::new();
+            ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+            RegistrationCredential {
+                raw_id: b64::URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: AttestationResponse {
+                    client_data_json: b64::URL_SAFE_NO_PAD.encode(self.client_data("webauthn.create", challenge)),
+                    attestation_object: b64::URL_SAFE_NO_PAD.encode(attestation_object),
+                },
+            }
+        }
+
+        fn assert(&mut self, challenge: &str) -> AssertionCredential {
+            self.sign_count += 1;
+            let auth_data = self.auth_data(false);
+            let client_data = self.client_data("webauthn.get", challenge);
+
+            let mut signed = auth_data.clone();
+            signed.extend_from_slice(&Sha256::digest(&client_data));
+            let signature = match &self.key {
+                SoftKey::P256(key) => {
+                    let signature: p256::ecdsa::Signature = key.sign(&signed);
+                    signature.to_der().as_bytes().to_vec()
+                }
+                SoftKey::Ed25519(key) => key.sign(&signed).to_bytes().to_vec(),
+            };
+
+            AssertionCredential {
+                raw_id: b64::URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: AssertionResponse {
+                    client_data_json: b64::URL_SAFE_NO_PAD.encode(client_data),
+                    authenticator_data: b64::URL_SAFE_NO_PAD.encode(auth_data),
+                    signature: b64::URL_SAFE_NO_PAD.encode(signature),
+                    user_handle: Some(b64::URL_SAFE_NO_PAD.encode(42i64.to_be_bytes())),
+                },
+            }
+        }
+    }
+
+    fn p256_authenticator() -> SoftAuthenticator {
+        SoftAuthenticator::new(SoftKey::P256(p256::ecdsa::SigningKey::random(&mut OsRng)))
+    }
+
+    #[test]
+    fn registers_and_signs_in_with_each_key_type() {
+        let ed25519 = SoftKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[3; 32]));
+        for mut authenticator in [p256_authenticator(), SoftAuthenticator::new(ed25519)] {
+            let challenge = generate_challenge();
+            let credential = rp().verify_registration(&challenge, &authenticator.register(&challenge)).unwrap();
+            assert_eq!(credential.credential_id, b64::URL_SAFE_NO_PAD.encode(&authenticator.credential_id));
+
+            let challenge = generate_challenge();
+            let assertion = authenticator.assert(&challenge);
+            let verified = rp()
+                .verify_assertion(&challenge, &assertion, &credential.public_key, credential.sign_count)
+                .unwrap();
+            assert_eq!(verified.sign_count, 1);
+        }
+    }
+
+    #[test]
+    fn rejects_registration_for_another_site_or_challenge() {
+        let challenge = generate_challenge();
+
+        let mut authenticator = p256_authenticator();
+        authenticator.origin = "https://evil.example".to_string();
+        assert!(matches!(
+            rp().verify_registration(&challenge, &authenticator.register(&challenge)),
+            Err(WebauthnError::OriginMismatch)
+        ));
+
+        let mut authenticator = p256_authenticator();
+        authenticator.rp_id = "evil.example".to_string();
+        assert!(matches!(
+            rp().verify_registration(&challenge, &authenticator.register(&challenge)),
+            Err(WebauthnError::RpIdMismatch)
+        ));
+
+        let authenticator = p256_authenticator();
+        assert!(matches!(
+            rp().verify_registration(&challenge, &authenticator.register(&generate_challenge())),
+            Err(WebauthnError::ChallengeMismatch)
+        ));
+
+        let mut authenticator = p256_authenticator();
+        authenticator.flags = FLAG_USER_PRESENT;
+        assert!(matches!(
+            rp().verify_registration(&challenge, &authenticator.register(&challenge)),
+            Err(WebauthnError::UserNotVerified)
+        ));
+    }
+
+    #[test]
+    fn rejects_forged_or_replayed_assertions() {
+        let mut authenticator = p256_authenticator();
+        let challenge = generate_challenge();
+        let credential = rp().verify_registration(&challenge, &authenticator.register(&challenge)).unwrap();
+
+        // Signed by a different key than the one registered
+        let mut impostor = p256_authenticator();
+        let challenge = generate_challenge();
+        let forged = impostor.assert(&challenge);
+        assert!(matches!(
+            rp().verify_assertion(&challenge, &forged, &credential.public_key, 0),
+            Err(WebauthnError::InvalidSignature)
+        ));
+
+        // A registration response can't stand in for an assertion
+        let challenge = generate_challenge();
+        let mut assertion = authenticator.assert(&challenge);
+        assertion.response.client_data_json =
+            b64::URL_SAFE_NO_PAD.encode(authenticator.client_data("webauthn.create", &challenge));
+        assert!(matches!(
+            rp().verify_assertion(&challenge, &assertion, &credential.public_key, 0),
+            Err(WebauthnError::WrongCeremony)
+        ));
+
+        // Counter not ahead of the stored one: a replay or a cloned authenticator
+        let challenge = generate_challenge();
+        let assertion = authenticator.assert(&challenge);
+        assert!(matches!(
+            rp().verify_assertion(&challenge, &assertion, &credential.public_key, 5),
+            Err(WebauthnError::CounterRegression)
+        ));
+    }
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::info;
+use uuid::Uuid;
+
+use super::{Email, Mailer, MailerError};
+
+// Writes each mail to its own file and logs it, instead of delivering it
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub async fn new(dir: impl Into<PathBuf>) -> Result<Self, MailerError> {
+        let dir = dir.into();
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|err| MailerError::Config(format!("{}: {}", dir.display(), err)))?;
+        Ok(Self { dir })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailerError> {
+        let now = Utc::now();
+        let path = self
+            .dir
+            .join(format!("{}-{}.eml", now.format("%Y%m%dT%H%M%S"), Uuid::new_v4()));
+        let contents = format!(
+            "Date: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}\r\n",
+            now.to_rfc2822(),
+            email.to,
+            email.subject,
+            email.body
+        );
+
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|err| MailerError::Send(format!("{}: {}", path.display(), err)))?;
+        info!("Mail \"{}\" to {} written to {}", email.subject, email.to, path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+pub mod file;
+pub mod smtp;
+
+use std::env;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MailerError {
+    #[error("Mailer configuration error: {0}")]
+    Config(String),
+    #[error("Failed to send mail: {0}")]
+    Send(String),
+}
+
+// Plain-text message; every mail we send is transactional
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), MailerError>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+// MAILER_BACKEND selects the transport: `file` (default, for local testing) or `smtp`
+pub async fn mailer_from_env() -> Result<SharedMailer, MailerError> {
+    let backend = env::var("MAILER_BACKEND").unwrap_or_else(|_| "file".to_string());
+    match backend.as_str() {
+        "file" => {
+            let dir = env::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string());
+            Ok(Arc::new(file::FileMailer::new(dir).await?))
+        }
+        "smtp" => Ok(Arc::new(smtp::SmtpMailer::from_env()?)),
+        other => Err(MailerError::Config(format!("unsupported MAILER_BACKEND: {}", other))),
+    }
+}
+
+// Sends in the background so the response time doesn't depend on the mail server,
+// or reveal whether a mail was sent at all
+pub fn send_in_background(mailer: SharedMailer, email: Email) {
+    tokio::spawn(async move {
+        if let Err(err) = mailer.send(&email).await {
+            eprintln!("Failed to send \"{}\" mail: {}", email.subject, err);
+        }
+    });
+}
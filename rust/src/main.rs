@@ -1,72 +1,85 @@
-use axum::{Router, routing::get};
+use axum::routing::get;
 use serde_json::Value;
 use socketioxide::{
     SocketIo,
-    extract::{AckSender, Data, SocketRef},
+    extract::{AckSender, Data, SocketRef, TryData},
+    handler::ConnectHandler,
 };
-use sqlx::{Pool, Postgres};
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tracing::info;
 use tracing_subscriber::FmtSubscriber;
 
-use crate::db::conn::create_connection;
-use crate::socket::handlers::{handle_join, handle_left, handle_chat, handle_writing, handle_cancel_writing};
+use rust::db::conn::create_connection;
+use rust::socket::auth::{authenticate_socket, socket_user};
+use rust::socket::handlers::{
+    handle_cancel_writing, handle_chat, handle_direct_message, handle_disconnect, handle_history, handle_join, track_socket,
+    handle_left, handle_writing,
+};
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-mod controllers;
-mod compose;
-mod db;
-mod extract;
-mod libs;
-mod middleware;
-mod router;
-mod socket;
+use rust::{AppState, UserSocketMap, jobs, libs, mailer, ratelimit, router, storage};
 
-fn on_connect(
+async fn on_connect(
     socket: SocketRef,
-    Data(data): Data<Value>,
     app_state: AppState,
     user_sockets: UserSocketMap,
 ) {
     info!("Socket.IO connected: {:?} {:?}", socket.ns(), socket.id);
-    socket.emit("auth", &data).ok();
+
+    // authenticate_socket already ran, so the identity is always present here
+    if let Some(user) = socket_user(&socket) {
+        track_socket(&user_sockets, &socket, user.id).await;
+        socket
+            .emit("auth", &serde_json::json!({ "userId": user.id, "nickname": user.nickname }))
+            .ok();
+    }
 
     // Handle join event
+    let join_state = app_state.clone();
     socket.on("join", move |socket: SocketRef, Data::<Value>(data)| {
-        let app_state = app_state.clone();
-        let user_sockets = user_sockets.clone();
-        handle_join(socket, SocketIo::new(), data, user_sockets);
+        handle_join(socket, Data(data), join_state.db.clone())
     });
 
     // Handle left event
-    socket.on("left", move |socket: SocketRef, Data::<Value>(data)| {
-        let app_state = app_state.clone();
-        let user_sockets = user_sockets.clone();
-        handle_left(socket, SocketIo::new(), data, user_sockets);
+    socket.on("left", |socket: SocketRef, Data::<Value>(data)| {
+        handle_left(socket, Data(data))
     });
 
     // Handle chat event
+    let chat_state = app_state.clone();
     socket.on("chat", move |socket: SocketRef, Data::<Value>(data)| {
-        let app_state = app_state.clone();
-        let user_sockets = user_sockets.clone();
-        handle_chat(socket, SocketIo::new(), data, app_state.db, user_sockets);
+        handle_chat(socket, Data(data), chat_state.db.clone())
+    });
+
+    // Handle dm event
+    let (dm_state, dm_sockets) = (app_state.clone(), user_sockets.clone());
+    socket.on("dm", move |socket: SocketRef, Data::<Value>(data)| {
+        handle_direct_message(socket, Data(data), dm_state.db.clone(), dm_sockets.clone())
+    });
+
+    // Handle history event
+    let history_state = app_state.clone();
+    socket.on("history", move |socket: SocketRef, Data::<Value>(data)| {
+        handle_history(socket, Data(data), history_state.db.clone())
     });
 
     // Handle writing event
-    socket.on("writing", move |socket: SocketRef, Data::<Value>(data)| {
-        let app_state = app_state.clone();
-        handle_writing(socket, SocketIo::new(), data);
+    socket.on("writing", |socket: SocketRef, Data::<Value>(data)| {
+        handle_writing(socket, Data(data))
     });
 
     // Handle cancelWriting event
-    socket.on("cancelWriting", move |socket: SocketRef, Data::<Value>(data)| {
-        let app_state = app_state.clone();
-        handle_cancel_writing(socket, SocketIo::new(), data);
+    socket.on("cancelWriting", |socket: SocketRef, Data::<Value>(data)| {
+        handle_cancel_writing(socket, Data(data))
     });
 
+    // Forget the user mapping once the socket goes away
+    socket.on_disconnect(move |socket: SocketRef| handle_disconnect(socket, user_sockets.clone()));
+
     // Keep ping for testing
     socket.on("ping", |socket: SocketRef, Data::<Value>(data)| {
         info!("Received event: {:?}", data);
@@ -79,36 +92,75 @@ fn on_connect(
     });
 }
 
-#[derive(Clone)]
-struct AppState {
-    db: Pool<Postgres>,
-}
-
-type UserSocketMap = handlers::UserSocketMap;
-
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::subscriber::set_global_default(FmtSubscriber::default())?;
 
     let pool = create_connection().await;
 
-    let state = AppState { db: pool };
-    let user_sockets: UserSocketMap = Arc::new(RwLock::new(HashMap::new()));
+    let storage = match storage::storage_from_env().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Failed to initialise attachment storage: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mailer = match mailer::mailer_from_env().await {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            eprintln!("Failed to initialise mailer: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = libs::jwt::init() {
+        eprintln!("Failed to load JWT signing keys: {}", e);
+        std::process::exit(1);
+    }
+
+    if let Err(e) = ratelimit::init().await {
+        eprintln!("Failed to initialise rate limiting: {}", e);
+        std::process::exit(1);
+    }
 
     let (layer, io) = SocketIo::new_layer();
 
+    let state = AppState {
+        db: pool,
+        io: io.clone(),
+        storage,
+        mailer,
+        user_sockets: Arc::new(RwLock::new(HashMap::new())),
+    };
+
+    jobs::purge_tombstones::spawn_tombstone_purge(state.db.clone());
+    jobs::revocation_sync::spawn_revocation_sync(state.db.clone());
+    jobs::purge_deleted_accounts::spawn_account_purge(
+        state.db.clone(),
+        state.storage.clone(),
+        state.user_sockets.clone(),
+    );
+
     let layer = ServiceBuilder::new()
         .layer(CorsLayer::permissive()) // Enable CORS policy
         .layer(layer);
 
     // Create a closure that captures the state and user_sockets
-    let on_connect_handler = move |socket: SocketRef, Data(data): Data<Value>| {
-        let state = state.clone();
-        let user_sockets = user_sockets.clone();
-        on_connect(socket, data, state, user_sockets);
+    let socket_state = state.clone();
+    let on_connect_handler = move |socket: SocketRef| {
+        let state = socket_state.clone();
+        let user_sockets = state.user_sockets.clone();
+        on_connect(socket, state, user_sockets)
+    };
+
+    // Reject sockets without a valid access token before on_connect runs
+    let auth_db = state.db.clone();
+    let auth_middleware = move |socket: SocketRef, TryData::<Value>(auth)| {
+        authenticate_socket(socket, auth.ok(), auth_db.clone())
     };
 
-    io.ns("/", on_connect_handler);
+    io.ns("/", on_connect_handler.with(auth_middleware));
 
     let app = axum::Router::new()
         .route("/", get(|| async { "Hello, World!" }))
@@ -122,16 +174,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Ok(listener) => listener,
         Err(e) => {
             eprintln!("Failed to bind to port 3333: {}", e);
-            return Err(Box::new(std::io::Error::new(
+            return Err(std::io::Error::new(
                 std::io::ErrorKind::AddrInUse,
                 format!("Failed to bind to port 3333: {}", e),
-            )));
+            )
+            .into());
         }
     };
 
-    if let Err(e) = axum::serve(listener, app).await {
+    if let Err(e) = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await {
         eprintln!("Server error: {}", e);
-        return Err(Box::new(e));
+        return Err(e.into());
     }
 
     Ok(())
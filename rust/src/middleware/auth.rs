@@ -1,6 +1,7 @@
-use crate::libs::{Resp, crypto::verify_jwt};
+use crate::extract::SessionId;
+use crate::libs::{Resp, crypto::verify_jwt, revocation::is_token_revoked};
 use axum::{Json, extract::Request, http::StatusCode, middleware::Next, response::IntoResponse};
-use std::collections::HashMap;
+use uuid::Uuid;
 
 // Extract user ID from JWT and add to extensions
 pub async fn middleware_auth(
@@ -40,16 +41,22 @@ pub async fn middleware_auth(
         return Err((StatusCode::UNAUTHORIZED, Resp::error(verified.reason)));
     }
 
+    // Logged-out or revoked sessions leave their tokens on the deny-list until exp
+    if verified.jti.as_deref().is_some_and(is_token_revoked) {
+        return Err((StatusCode::UNAUTHORIZED, Resp::error("Token has been revoked")));
+    }
+
     // Extract user ID from verified JWT token
     let user_id = verified.extract_user_id();
+    let session_id = verified
+        .session_id
+        .as_deref()
+        .and_then(|sid| Uuid::parse_str(sid).ok());
 
-    // Add user ID to request extensions
-    let mut extensions = req.extensions().clone();
-    extensions.insert("user_id", user_id);
-
-    // Create new request with extensions
+    // Add user ID and session to request extensions
     let mut req_with_ext = req;
-    req_with_ext = req_with_ext.with_extensions(extensions);
+    req_with_ext.extensions_mut().insert(user_id);
+    req_with_ext.extensions_mut().insert(SessionId(session_id));
 
     Ok(next.run(req_with_ext).await)
 }
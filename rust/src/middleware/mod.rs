@@ -1,2 +1,3 @@
 pub mod auth;
 pub mod logger;
+pub mod rate_limit;
@@ -0,0 +1,117 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum::extract::{ConnectInfo, Request};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+
+use crate::libs::Resp;
+use crate::libs::client_ip::client_ip;
+use crate::ratelimit::{Decision, Subject, rate_limiter};
+
+// Limits the wrapped routes with a named policy from crate::ratelimit. Signed-in requests
+// are counted per user, so put it inside middleware_auth; anonymous ones per client address.
+//
+//     .route("/chat", post(create_chat).layer(RateLimitLayer::new("chat")))
+#[derive(Clone, Copy)]
+pub struct RateLimitLayer {
+    policy: &'static str,
+}
+
+impl RateLimitLayer {
+    pub fn new(policy: &'static str) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            policy: self.policy,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    policy: &'static str,
+}
+
+impl<S> Service<Request> for RateLimit<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // The clone that was polled ready is the one that must serve this request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let policy = self.policy;
+
+        Box::pin(async move {
+            let limiter = match rate_limiter() {
+                Some(limiter) => limiter,
+                None => return inner.call(req).await,
+            };
+
+            // Set by middleware_auth; 0 is its "no user" fallback
+            let user_id = req.extensions().get::<i64>().copied().filter(|id| *id != 0);
+            let ip = request_ip(&req);
+            let subject = match user_id {
+                Some(user_id) => Subject::User(user_id),
+                None => Subject::Ip(&ip),
+            };
+
+            let decision = match limiter.check(policy, &subject).await {
+                Some(decision) => decision,
+                None => return inner.call(req).await,
+            };
+
+            let mut response = if decision.allowed {
+                inner.call(req).await?
+            } else {
+                let retry_after = decision.retry_after_secs.unwrap_or(1);
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Resp::<()>::error(format!("Too many requests, try again in {} seconds", retry_after)),
+                )
+                    .into_response()
+            };
+            insert_headers(response.headers_mut(), &decision);
+            Ok(response)
+        })
+    }
+}
+
+fn request_ip(req: &Request) -> String {
+    req.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| client_ip(req.headers(), *peer).to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// RateLimit-Limit/-Remaining/-Reset on every limited response, Retry-After on refusals
+pub fn insert_headers(headers: &mut HeaderMap, decision: &Decision) {
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(decision.reset_secs));
+    if let Some(retry_after) = decision.retry_after_secs {
+        headers.insert("retry-after", HeaderValue::from(retry_after));
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use super::{Decision, Policy, RateLimitError, RateLimitStore};
+
+// How often buckets that have filled up again are dropped; a full bucket is the same as none
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    // Worked out from the bucket's own policy, since keys of every policy share the map
+    full_at: Instant,
+}
+
+// Buckets in this process only; with several instances each enforces its own limit
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl MemoryStore {
+    // Sweeps from a background task so requests never wait on it; stops once the store is dropped
+    pub fn spawn_sweeper(store: &Arc<Self>) {
+        let store = Arc::downgrade(store);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match store.upgrade() {
+                    Some(store) => store.sweep_at(Instant::now()),
+                    None => break,
+                }
+            }
+        });
+    }
+
+    fn sweep_at(&self, now: Instant) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        buckets.retain(|_, bucket| bucket.full_at > now);
+    }
+
+    fn take_at(&self, key: &str, policy: &Policy, now: Instant) -> Decision {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let capacity = f64::from(policy.capacity);
+        let rate = policy.refill_per_sec();
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            full_at: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        bucket.full_at = now + Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+        policy.decision(allowed, bucket.tokens)
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, policy: &Policy) -> Result<Decision, RateLimitError> {
+        Ok(self.take_at(key, policy, Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_up_to_capacity_then_refuses() {
+        let store = MemoryStore::default();
+        let policy = Policy::new(3, 30);
+        let now = Instant::now();
+
+        for remaining in [2, 1, 0] {
+            let decision = store.take_at("k", &policy, now);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+
+        let refused = store.take_at("k", &policy, now);
+        assert!(!refused.allowed);
+        assert_eq!(refused.retry_after_secs, Some(10));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let store = MemoryStore::default();
+        let policy = Policy::new(2, 10);
+        let now = Instant::now();
+
+        store.take_at("k", &policy, now);
+        store.take_at("k", &policy, now);
+        assert!(!store.take_at("k", &policy, now).allowed);
+        assert!(store.take_at("k", &policy, now + Duration::from_secs(5)).allowed);
+        assert!(!store.take_at("k", &policy, now + Duration::from_secs(5)).allowed);
+    }
+
+    #[test]
+    fn keys_have_separate_buckets() {
+        let store = MemoryStore::default();
+        let policy = Policy::new(1, 60);
+        let now = Instant::now();
+
+        assert!(store.take_at("a", &policy, now).allowed);
+        assert!(!store.take_at("a", &policy, now).allowed);
+        assert!(store.take_at("b", &policy, now).allowed);
+    }
+
+    #[test]
+    fn sweeps_buckets_once_their_own_policy_refills_them() {
+        let store = MemoryStore::default();
+        let now = Instant::now();
+
+        store.take_at("fast", &Policy::new(10, 10), now);
+        store.take_at("slow", &Policy::new(5, 3600), now);
+        store.sweep_at(now + Duration::from_secs(2));
+
+        let buckets = store.buckets.lock().unwrap();
+        assert!(!buckets.contains_key("fast"));
+        assert!(buckets.contains_key("slow"));
+    }
+}
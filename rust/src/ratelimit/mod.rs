@@ -0,0 +1,202 @@
+pub mod memory;
+#[cfg(feature = "redis")]
+pub mod redis;
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, OnceLock};
+
+use async_trait::async_trait;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RateLimitError {
+    #[error("Rate limit configuration error: {0}")]
+    Config(String),
+    #[error("Rate limit backend error: {0}")]
+    Backend(String),
+}
+
+// Token bucket: holds up to `capacity` requests and refills `capacity` every `period_secs`,
+// so a client can burst and then keeps to the average rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Policy {
+    pub capacity: u32,
+    pub period_secs: u32,
+}
+
+impl Policy {
+    pub const fn new(capacity: u32, period_secs: u32) -> Self {
+        Self { capacity, period_secs }
+    }
+
+    pub fn refill_per_sec(&self) -> f64 {
+        f64::from(self.capacity) / f64::from(self.period_secs)
+    }
+
+    // `<requests>/<seconds>`, e.g. `10/60` or `10/60s`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (capacity, period) = value.trim().split_once('/')?;
+        let capacity: u32 = capacity.trim().parse().ok()?;
+        let period: u32 = period.trim().trim_end_matches('s').parse().ok()?;
+        (capacity > 0 && period > 0).then_some(Self::new(capacity, period))
+    }
+
+    // What a bucket holding `tokens` (after this request was counted or refused) tells the client
+    pub fn decision(&self, allowed: bool, tokens: f64) -> Decision {
+        let rate = self.refill_per_sec();
+        let capacity = f64::from(self.capacity);
+        Decision {
+            allowed,
+            limit: self.capacity,
+            remaining: tokens.max(0.0).floor() as u32,
+            reset_secs: ((capacity - tokens) / rate).ceil().max(0.0) as u64,
+            retry_after_secs: (!allowed).then(|| ((1.0 - tokens) / rate).ceil().max(1.0) as u64),
+        }
+    }
+}
+
+// Outcome of one request against a bucket, in the terms of the RateLimit-* headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // Seconds until the bucket is full again
+    pub reset_secs: u64,
+    // Set when refused: seconds until the next request would be let through
+    pub retry_after_secs: Option<u64>,
+}
+
+// Where buckets live. Keys are built by the limiter, never taken from clients.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    // Takes one token from the bucket at `key` if there is one
+    async fn take(&self, key: &str, policy: &Policy) -> Result<Decision, RateLimitError>;
+}
+
+// Whose bucket a request is taken from: the signed-in user, or the client address otherwise
+pub enum Subject<'a> {
+    User(i64),
+    Ip(&'a str),
+}
+
+// Named policies and the store their buckets are kept in
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    policies: HashMap<String, Policy>,
+}
+
+// Defaults for the policies the routes and socket handlers use; RATE_LIMIT_<NAME> overrides them
+pub const DEFAULT_POLICIES: &[(&str, Policy)] = &[
+    // Sending messages: REST, replies, socket chat and direct messages
+    ("chat", Policy::new(10, 10)),
+    // Typing indicators
+    ("writing", Policy::new(10, 10)),
+    ("register", Policy::new(5, 3600)),
+    ("check", Policy::new(30, 60)),
+    // Password reset mails, per client IP
+    ("forgot", Policy::new(5, 3600)),
+];
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn RateLimitStore>, policies: HashMap<String, Policy>) -> Self {
+        Self { store, policies }
+    }
+
+    // None when the policy is unknown or switched off, i.e. the request isn't limited.
+    // A failing backend lets requests through rather than taking the API down with it.
+    pub async fn check(&self, policy: &str, subject: &Subject<'_>) -> Option<Decision> {
+        let limits = self.policies.get(policy)?;
+        let key = match subject {
+            Subject::User(user_id) => format!("rl:{}:user:{}", policy, user_id),
+            Subject::Ip(ip) => format!("rl:{}:ip:{}", policy, ip),
+        };
+
+        match self.store.take(&key, limits).await {
+            Ok(decision) => Some(decision),
+            Err(err) => {
+                eprintln!("Rate limit check failed, allowing request: {}", err);
+                None
+            }
+        }
+    }
+}
+
+fn policies_from_env() -> Result<HashMap<String, Policy>, RateLimitError> {
+    let mut policies = HashMap::new();
+    for (name, default) in DEFAULT_POLICIES {
+        let var = format!("RATE_LIMIT_{}", name.to_uppercase());
+        match env::var(&var) {
+            Ok(value) if value.trim() == "off" => {}
+            Ok(value) => {
+                let policy = Policy::parse(&value)
+                    .ok_or_else(|| RateLimitError::Config(format!("{} must look like 10/60, got {}", var, value)))?;
+                policies.insert(name.to_string(), policy);
+            }
+            Err(_) => {
+                policies.insert(name.to_string(), *default);
+            }
+        }
+    }
+    Ok(policies)
+}
+
+// RATE_LIMIT_BACKEND selects the store: `memory` (default, per instance) or `redis`
+// when built with the redis feature, so every instance shares the same buckets
+pub async fn rate_limiter_from_env() -> Result<RateLimiter, RateLimitError> {
+    let policies = policies_from_env()?;
+    let backend = env::var("RATE_LIMIT_BACKEND").unwrap_or_else(|_| "memory".to_string());
+    let store: Arc<dyn RateLimitStore> = match backend.as_str() {
+        "memory" => {
+            let store = Arc::new(memory::MemoryStore::default());
+            memory::MemoryStore::spawn_sweeper(&store);
+            store
+        }
+        #[cfg(feature = "redis")]
+        "redis" => Arc::new(redis::RedisStore::from_env().await?),
+        other => return Err(RateLimitError::Config(format!("unsupported RATE_LIMIT_BACKEND: {}", other))),
+    };
+    Ok(RateLimiter::new(store, policies))
+}
+
+// Process-wide limiter; route layers and socket handlers have no access to AppState
+static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+
+pub async fn init() -> Result<&'static RateLimiter, RateLimitError> {
+    let limiter = rate_limiter_from_env().await?;
+    Ok(LIMITER.get_or_init(|| limiter))
+}
+
+// Before init (e.g. in tests) nothing is limited
+pub fn rate_limiter() -> Option<&'static RateLimiter> {
+    LIMITER.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_policies() {
+        assert_eq!(Policy::parse("10/60"), Some(Policy::new(10, 60)));
+        assert_eq!(Policy::parse(" 5 / 3600s "), Some(Policy::new(5, 3600)));
+        assert_eq!(Policy::parse("0/60"), None);
+        assert_eq!(Policy::parse("10"), None);
+        assert_eq!(Policy::parse("ten/60"), None);
+    }
+
+    #[test]
+    fn decisions_describe_the_bucket() {
+        let policy = Policy::new(10, 10);
+
+        let allowed = policy.decision(true, 7.5);
+        assert_eq!(allowed.remaining, 7);
+        assert_eq!(allowed.reset_secs, 3);
+        assert_eq!(allowed.retry_after_secs, None);
+
+        let refused = policy.decision(false, 0.25);
+        assert_eq!(refused.remaining, 0);
+        assert_eq!(refused.reset_secs, 10);
+        assert_eq!(refused.retry_after_secs, Some(1));
+    }
+}
@@ -0,0 +1,72 @@
+use std::env;
+
+use async_trait::async_trait;
+use redis::Script;
+use redis::aio::ConnectionManager;
+
+use super::{Decision, Policy, RateLimitError, RateLimitStore};
+
+// The whole bucket update runs in Redis so concurrent instances can't race on it.
+// Uses the server clock, so instances with skewed clocks still agree.
+const TAKE_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * rate)
+
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', tostring(now))
+redis.call('EXPIRE', KEYS[1], math.ceil((capacity - tokens) / rate) + 1)
+return { allowed, tostring(tokens) }
+"#;
+
+pub struct RedisStore {
+    connection: ConnectionManager,
+    script: Script,
+}
+
+impl RedisStore {
+    pub async fn from_env() -> Result<Self, RateLimitError> {
+        let url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+        let client = redis::Client::open(url).map_err(|err| RateLimitError::Config(err.to_string()))?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .map_err(|err| RateLimitError::Backend(err.to_string()))?;
+
+        Ok(Self {
+            connection,
+            script: Script::new(TAKE_SCRIPT),
+        })
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    async fn take(&self, key: &str, policy: &Policy) -> Result<Decision, RateLimitError> {
+        // The manager reconnects on its own; clones share the connection
+        let mut connection = self.connection.clone();
+        let (allowed, tokens): (i64, String) = self
+            .script
+            .key(key)
+            .arg(policy.capacity)
+            .arg(policy.refill_per_sec())
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|err| RateLimitError::Backend(err.to_string()))?;
+
+        let tokens = tokens
+            .parse()
+            .map_err(|_| RateLimitError::Backend(format!("unexpected bucket value {}", tokens)))?;
+        Ok(policy.decision(allowed == 1, tokens))
+    }
+}
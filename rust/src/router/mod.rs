@@ -1,11 +1,14 @@
 mod v1;
-use axum::{Router, middleware::from_fn};
+use axum::{Router, middleware::from_fn, routing::get};
 
-use crate::{AppState, middleware::logger::middleware_logger};
+use crate::{
+    AppState, controllers::jwks_controller::get_jwks::get_jwks,
+    middleware::logger::middleware_logger,
+};
 
 pub fn main() -> Router<AppState> {
-    let router = Router::new()
+    Router::new()
         .nest("/v1", v1::main())
-        .layer(from_fn(middleware_logger));
-    router
+        .route("/.well-known/jwks.json", get(get_jwks))
+        .layer(from_fn(middleware_logger))
 }
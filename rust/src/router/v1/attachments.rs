@@ -0,0 +1,33 @@
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    middleware::from_fn,
+    routing::{get, post},
+};
+
+use crate::{
+    AppState,
+    controllers::attachment_controller::{self, validate::max_upload_bytes},
+    middleware::auth::middleware_auth,
+};
+
+pub fn attachment() -> Router<AppState> {
+    Router::new()
+        // Upload a file; leave room for the multipart framing on top of the file limit
+        .route(
+            "/attachments",
+            post(attachment_controller::upload::upload_attachment)
+                .layer(DefaultBodyLimit::max(max_upload_bytes() + 64 * 1024)),
+        )
+        // Download a file
+        .route(
+            "/attachments/{id}",
+            get(attachment_controller::download::download_attachment),
+        )
+        // Download the preview of an image
+        .route(
+            "/attachments/{id}/thumbnail",
+            get(attachment_controller::download::download_thumbnail),
+        )
+        .layer(from_fn(middleware_auth))
+}
@@ -1,18 +1,32 @@
-use axum::{Router, middleware::from_fn, routing::{get, post, delete}};
+use axum::{Router, middleware::from_fn, routing::{get, post, delete, patch}};
 
-use crate::{AppState, controllers::chat_controller, middleware::auth::middleware_auth};
+use crate::{
+    AppState,
+    controllers::chat_controller,
+    middleware::{auth::middleware_auth, rate_limit::RateLimitLayer},
+};
 
 pub fn chat() -> Router<AppState> {
-    let router = Router::new()
+    Router::new()
         // Chat pagination
         .route(
             "/chat/page/{page}",
             get(chat_controller::pagination::get_chat),
         )
+        // Keyset chat history
+        .route(
+            "/chat/history",
+            get(chat_controller::history::get_history),
+        )
+        // Full-text search
+        .route(
+            "/chat/search",
+            get(chat_controller::search::search_chats),
+        )
         // Create new message
         .route(
             "/chat",
-            post(chat_controller::create::create_chat),
+            post(chat_controller::create::create_chat).layer(RateLimitLayer::new("chat")),
         )
         // Get specific message
         .route(
@@ -24,11 +38,20 @@ pub fn chat() -> Router<AppState> {
             "/chat/{id}",
             delete(chat_controller::delete_message::delete_chat),
         )
+        // Edit message
+        .route(
+            "/chat/{id}",
+            patch(chat_controller::edit_message::edit_chat),
+        )
+        // Edit history of a message
+        .route(
+            "/chat/{id}/revisions",
+            get(chat_controller::revisions::get_chat_revisions),
+        )
         // Reply to message
         .route(
             "/chat/{id}/reply",
-            post(chat_controller::reply_message::reply_to_chat),
+            post(chat_controller::reply_message::reply_to_chat).layer(RateLimitLayer::new("chat")),
         )
-        .layer(from_fn(middleware_auth));
-    router
+        .layer(from_fn(middleware_auth))
 }
@@ -0,0 +1,23 @@
+use axum::{
+    Router,
+    middleware::from_fn,
+    routing::get,
+};
+
+use crate::{AppState, controllers::direct_controller, middleware::auth::middleware_auth};
+
+pub fn direct() -> Router<AppState> {
+    Router::new()
+        // List my conversations, open one with another user
+        .route(
+            "/dm",
+            get(direct_controller::list::list_direct_conversations)
+                .post(direct_controller::open::open_direct_conversation),
+        )
+        // Messages of a conversation
+        .route(
+            "/dm/{id}/messages",
+            get(direct_controller::messages::get_direct_messages),
+        )
+        .layer(from_fn(middleware_auth))
+}
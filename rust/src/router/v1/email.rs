@@ -0,0 +1,10 @@
+use axum::{Router, routing::post};
+
+use crate::{AppState, controllers::email_controller};
+
+pub fn email() -> Router<AppState> {
+    // Public: with EMAIL_VERIFICATION_POLICY=login, unverified users can't get a token
+    Router::new()
+        .route("/email/verify", post(email_controller::verify::verify_email))
+        .route("/email/resend", post(email_controller::resend::resend_verification))
+}
@@ -1,10 +1,23 @@
 use axum::Router;
 
 use crate::AppState;
+mod attachments;
 mod chats;
+mod directs;
+mod email;
+mod oauth;
+mod password;
+mod rooms;
 mod users;
 
 pub fn main() -> Router<AppState> {
-    let router = Router::new().merge(users::user()).merge(chats::chat());
-    router
+    Router::new()
+        .merge(users::user())
+        .merge(chats::chat())
+        .merge(rooms::room())
+        .merge(directs::direct())
+        .merge(attachments::attachment())
+        .merge(password::password())
+        .merge(email::email())
+        .merge(oauth::oauth())
 }
@@ -0,0 +1,13 @@
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::{AppState, controllers::oauth_controller};
+
+pub fn oauth() -> Router<AppState> {
+    // Public: these replace /v1/login for accounts signing in through an identity provider
+    Router::new()
+        .route("/oauth/{provider}/authorize", get(oauth_controller::authorize::authorize))
+        .route("/oauth/{provider}/callback", post(oauth_controller::callback::callback))
+}
@@ -0,0 +1,13 @@
+use axum::{Router, routing::post};
+
+use crate::{AppState, controllers::password_controller, middleware::rate_limit::RateLimitLayer};
+
+pub fn password() -> Router<AppState> {
+    // Public: the caller can't sign in, which is the point
+    Router::new()
+        .route(
+            "/password/forgot",
+            post(password_controller::forgot::forgot_password).layer(RateLimitLayer::new("forgot")),
+        )
+        .route("/password/reset", post(password_controller::reset::reset_password))
+}
@@ -0,0 +1,24 @@
+use axum::{
+    Router,
+    middleware::from_fn,
+    routing::{get, patch, post},
+};
+
+use crate::{AppState, controllers::room_controller, middleware::auth::middleware_auth};
+
+pub fn room() -> Router<AppState> {
+    Router::new()
+        // List rooms, create a new one
+        .route(
+            "/room",
+            get(room_controller::list::list_rooms).post(room_controller::create::create_room),
+        )
+        // Rename room
+        .route("/room/{id}", patch(room_controller::rename::rename_room))
+        // Archive room
+        .route(
+            "/room/{id}/archive",
+            post(room_controller::archive::archive_room),
+        )
+        .layer(from_fn(middleware_auth))
+}
@@ -1,27 +1,93 @@
 use axum::{
     Router,
     middleware::from_fn,
-    routing::{get, post},
+    routing::{delete, get, patch, post},
 };
 
 use crate::{
     AppState,
+    controllers::{
+        account_controller, email_controller, lockout_controller, mfa_controller, passkey_controller,
+        password_controller, session_controller,
+    },
     controllers::user_controller::{self, check, logout, refresh_token, tag, users::get_one_user},
-    middleware::auth::middleware_auth,
+    middleware::{auth::middleware_auth, rate_limit::RateLimitLayer},
 };
 
 pub fn user() -> Router<AppState> {
     let protected_routes = Router::new()
-        .route("/check", post(check::check_available))
+        .route("/check", post(check::check_available).layer(RateLimitLayer::new("check")))
         .route("/{nickname}", get(get_one_user))
         .route("/tag/{tag}", get(tag::get_tag))
         .route("/logout", post(logout::logout_user))
+        // Own account; the static paths take precedence over /{nickname}
+        .route(
+            "/me",
+            patch(user_controller::profile::update_profile)
+                .delete(account_controller::delete::delete_account),
+        )
+        .route(
+            "/me/deletion",
+            delete(account_controller::delete::cancel_account_deletion),
+        )
+        .route("/me/password", post(password_controller::change::change_password))
+        .route("/me/email", post(email_controller::change::change_email))
+        // Signed-in devices
+        .route(
+            "/sessions",
+            get(session_controller::list::list_sessions)
+                .delete(session_controller::revoke::revoke_other_sessions),
+        )
+        .route(
+            "/sessions/{id}",
+            delete(session_controller::revoke::revoke_one_session),
+        )
+        // Two-factor authentication
+        .route(
+            "/mfa/totp",
+            post(mfa_controller::enroll::enroll_totp).delete(mfa_controller::disable::disable_totp),
+        )
+        .route("/mfa/totp/confirm", post(mfa_controller::confirm::confirm_totp))
+        .route(
+            "/mfa/recovery-codes",
+            post(mfa_controller::recovery::regenerate_codes),
+        )
+        // Passkeys
+        .route(
+            "/passkeys",
+            get(passkey_controller::manage::list_user_passkeys)
+                .post(passkey_controller::register::register_passkey),
+        )
+        .route(
+            "/passkeys/options",
+            post(passkey_controller::register::registration_options),
+        )
+        .route(
+            "/passkeys/reauth-options",
+            post(passkey_controller::reauth::reauth_options),
+        )
+        .route(
+            "/passkeys/{id}",
+            delete(passkey_controller::manage::delete_passkey),
+        )
         .layer(from_fn(middleware_auth));
 
-    let router = Router::new()
-        .route("/register", post(user_controller::register::register_user))
+    Router::new()
+        .route(
+            "/register",
+            post(user_controller::register::register_user).layer(RateLimitLayer::new("register")),
+        )
         .route("/login", post(user_controller::login::login_by_email))
+        .route("/login/mfa", post(mfa_controller::verify_login::login_with_mfa))
+        .route(
+            "/login/passkey/options",
+            post(passkey_controller::login::login_options),
+        )
+        .route(
+            "/login/passkey",
+            post(passkey_controller::login::login_with_passkey),
+        )
+        .route("/unlock", post(lockout_controller::unlock::unlock_account))
         .route("/refresh", post(refresh_token::refresh_token))
-        .nest("/user", protected_routes);
-    router
+        .nest("/user", protected_routes)
 }
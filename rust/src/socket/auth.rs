@@ -0,0 +1,102 @@
+use crate::controllers::session_controller::session::is_session_active;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::libs::crypto::verify_jwt;
+use crate::libs::revocation::is_token_revoked;
+use crate::socket::handlers::UserSocketMap;
+use serde_json::Value;
+use socketioxide::extract::SocketRef;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+// Identity established during the handshake and kept in the socket extensions.
+// Handlers must read the sender from here, never from the event payload.
+#[derive(Debug, Clone)]
+pub struct SocketUser {
+    pub id: i64,
+    pub nickname: String,
+    // Lets session revocation find and drop this socket
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SocketAuthError {
+    #[error("Authentication token is missing")]
+    MissingToken,
+    #[error("{0}")]
+    InvalidToken(String),
+    #[error("User not found")]
+    UnknownUser,
+    #[error("Session has been revoked")]
+    RevokedSession,
+}
+
+// Clients send the access token as `auth: { token }`, an `Authorization: Bearer` header also works
+fn handshake_token(socket: &SocketRef, auth: Option<Value>) -> Option<String> {
+    if let Some(token) = auth.as_ref().and_then(|auth| auth["token"].as_str()) {
+        return Some(token.trim().to_string());
+    }
+
+    socket
+        .req_parts()
+        .headers
+        .get("Authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
+// Connect middleware: an error here rejects the connection before any handler runs
+pub async fn authenticate_socket(
+    socket: SocketRef,
+    auth: Option<Value>,
+    db: Pool<Postgres>,
+) -> Result<(), SocketAuthError> {
+    let token = handshake_token(&socket, auth).ok_or(SocketAuthError::MissingToken)?;
+
+    let verified = verify_jwt(&token);
+    if !verified.result {
+        return Err(SocketAuthError::InvalidToken(verified.reason));
+    }
+    if verified.jti.as_deref().is_some_and(is_token_revoked) {
+        return Err(SocketAuthError::InvalidToken("Token has been revoked".to_string()));
+    }
+
+    let session_id = verified
+        .session_id
+        .as_deref()
+        .and_then(|sid| Uuid::parse_str(sid).ok());
+    if let Some(session_id) = session_id {
+        if !is_session_active(&db, session_id).await {
+            return Err(SocketAuthError::RevokedSession);
+        }
+    }
+
+    let user = get_user_by_auth_id(verified.extract_user_id(), db)
+        .await
+        .ok_or(SocketAuthError::UnknownUser)?;
+
+    socket.extensions.insert(SocketUser {
+        id: user.id,
+        nickname: user.nickname.unwrap_or_else(|| "Anonymous".to_string()),
+        session_id,
+    });
+
+    Ok(())
+}
+
+pub fn socket_user(socket: &SocketRef) -> Option<SocketUser> {
+    socket.extensions.get::<SocketUser>()
+}
+
+// Keeps the identity of the user's live sockets in step with a nickname change
+pub async fn rename_socket_user(user_sockets: &UserSocketMap, user_id: i64, nickname: &str) {
+    let sockets = user_sockets.read().await;
+    for socket in sockets.get(&user_id.to_string()).into_iter().flatten() {
+        if let Some(user) = socket_user(socket) {
+            socket.extensions.insert(SocketUser {
+                nickname: nickname.to_string(),
+                ..user
+            });
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::controllers::chat_controller::delete_message::TOMBSTONE_MESSAGE;
+use crate::controllers::user_controller::profile::Profile;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use socketioxide::SocketIo;
+use sqlx::{Pool, Postgres};
+
+// Fields of a stored chat message that make up the `new_message` payload
+pub struct MessageEvent<'a> {
+    pub id: i64,
+    pub message: Option<&'a str>,
+    pub attachment: Option<&'a str>,
+    pub user_id: i64,
+    pub reply_id: Option<i64>,
+    pub room_id: i64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+pub struct UserInfo {
+    id: i64,
+    nickname: Option<String>,
+    avatar: Option<String>,
+}
+
+pub async fn get_user_info(db: &Pool<Postgres>, user_id: i64) -> UserInfo {
+    let query = "SELECT id, nickname, avatar FROM users WHERE id = $1";
+
+    match sqlx::query_as::<_, UserInfo>(query)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        _ => UserInfo {
+            id: user_id,
+            nickname: Some("Unknown".to_string()),
+            avatar: None,
+        },
+    }
+}
+
+// Same payload whether the message arrived over Socket.IO or the REST API
+pub async fn message_payload(db: &Pool<Postgres>, event: &MessageEvent<'_>) -> Value {
+    let user_info = get_user_info(db, event.user_id).await;
+
+    serde_json::json!({
+        "id": event.id,
+        "message": event.message,
+        "attachment": event.attachment,
+        "userId": event.user_id,
+        "nickname": user_info.nickname,
+        "avatar": user_info.avatar,
+        "replyId": event.reply_id,
+        "roomId": event.room_id,
+        "createdAt": event.created_at,
+        "user": user_info
+    })
+}
+
+// Lightweight thread notification sent next to `new_message` for replies
+pub fn reply_payload(event: &MessageEvent<'_>) -> Option<Value> {
+    event.reply_id.map(|reply_id| {
+        serde_json::json!({
+            "id": event.id,
+            "replyId": reply_id,
+            "roomId": event.room_id
+        })
+    })
+}
+
+// Broadcast a message created over REST to every socket in its room, the author's included
+pub async fn emit_new_message(io: &SocketIo, db: &Pool<Postgres>, event: &MessageEvent<'_>) {
+    let payload = message_payload(db, event).await;
+    let channel = event.room_id.to_string();
+
+    io.to(channel.clone()).emit("new_message", &payload).await.ok();
+
+    if let Some(reply) = reply_payload(event) {
+        io.to(channel).emit("message_replied", &reply).await.ok();
+    }
+}
+
+pub async fn emit_message_deleted(io: &SocketIo, chat_id: i64, room_id: i64) {
+    io.to(room_id.to_string())
+        .emit("message_deleted", &serde_json::json!({
+            "id": chat_id,
+            "roomId": room_id,
+            "message": TOMBSTONE_MESSAGE
+        }))
+        .await
+        .ok();
+}
+
+pub async fn emit_message_edited(
+    io: &SocketIo,
+    chat_id: i64,
+    room_id: i64,
+    message: &str,
+    updated_at: DateTime<Utc>,
+) {
+    io.to(room_id.to_string())
+        .emit("message_edited", &serde_json::json!({
+            "id": chat_id,
+            "roomId": room_id,
+            "message": message,
+            "edited": true,
+            "updatedAt": updated_at
+        }))
+        .await
+        .ok();
+}
+
+// Sent to every client so they can refresh cached nicknames and avatars of this user
+pub async fn emit_profile_updated(io: &SocketIo, profile: &Profile) {
+    io.emit("profile_updated", &serde_json::json!({
+        "userId": profile.id,
+        "nickname": profile.nickname,
+        "displayName": profile.display_name,
+        "avatar": profile.avatar,
+        "bio": profile.bio
+    }))
+    .await
+    .ok();
+}
@@ -1,25 +1,33 @@
-use socketioxide::{
-    extract::{Data, SocketRef},
-    SocketIo,
-};
+use crate::socket::handlers::joined_room;
+use crate::socket::auth::socket_user;
+use socketioxide::extract::{Data, SocketRef};
 use serde_json::Value;
 use tracing::info;
 
 pub async fn handle_cancel_writing(
     socket: SocketRef,
-    io: SocketIo,
     Data(data): Data<Value>,
 ) {
-    let user_id = data["userId"].as_i64().unwrap_or(0);
+    let user = match socket_user(&socket) {
+        Some(user) => user,
+        None => return,
+    };
 
-    info!("User {} stopped typing", user_id);
+    let channel = match joined_room(&socket, &data) {
+        Some(channel) => channel,
+        None => return,
+    };
+
+    info!("User {} stopped typing in room {}", user.id, channel);
 
     // Broadcast typing stop
     socket
-        .to("general_chat")
-        .emit("user_typing", serde_json::json!({
-            "userId": user_id,
+        .to(channel)
+        .emit("user_typing", &serde_json::json!({
+            "userId": user.id,
+            "roomId": data["roomId"],
             "typing": false
         }))
+        .await
         .ok();
-}
\ No newline at end of file
+}
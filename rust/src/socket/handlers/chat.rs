@@ -1,10 +1,12 @@
-use crate::AppState;
+use crate::controllers::chat_controller::reply_message::reply_target_room;
+use crate::controllers::email_controller::verification::may_post_messages;
+use crate::controllers::room_controller::get_room::get_active_room;
 use crate::db::model::Chat;
-use crate::socket::handlers::UserSocketMap;
-use socketioxide::{
-    extract::{Data, SocketRef},
-    SocketIo,
-};
+use crate::socket::handlers::joined_room;
+use crate::socket::auth::socket_user;
+use crate::socket::rate_limit::allow_event;
+use crate::socket::events::{MessageEvent, message_payload, reply_payload};
+use socketioxide::extract::{Data, SocketRef};
 use sqlx::Pool;
 use sqlx::Postgres;
 use serde_json::Value;
@@ -12,12 +14,17 @@ use tracing::info;
 
 pub async fn handle_chat(
     socket: SocketRef,
-    io: SocketIo,
     Data(data): Data<Value>,
     db: Pool<Postgres>,
-    user_sockets: UserSocketMap,
 ) {
-    let user_id = data["userId"].as_i64().unwrap_or(0);
+    let user = match socket_user(&socket) {
+        Some(user) => user,
+        None => {
+            socket.emit("error", "Unauthenticated socket").ok();
+            return;
+        }
+    };
+    let user_id = user.id;
     let message = data["message"].as_str().unwrap_or("");
     let reply_id = data["replyId"].as_i64();
 
@@ -26,39 +33,80 @@ pub async fn handle_chat(
         return;
     }
 
-    info!("User {} sent message: {}", user_id, message);
+    if !allow_event(&socket, "chat", "chat").await {
+        return;
+    }
+
+    if !may_post_messages(user_id, &db).await {
+        socket.emit("error", "Verify your email address before posting messages").ok();
+        return;
+    }
+
+    let channel = match joined_room(&socket, &data) {
+        Some(channel) => channel,
+        None => {
+            socket.emit("error", "Join the room before sending messages").ok();
+            return;
+        }
+    };
+
+    // The room may have been archived since the socket joined it
+    let room = match get_active_room(data["roomId"].as_i64().unwrap_or(0), &db).await {
+        Some(room) => room,
+        None => {
+            socket.emit("error", "Room not found or archived").ok();
+            return;
+        }
+    };
+
+    if let Some(reply_id) = reply_id {
+        match reply_target_room(reply_id, &db).await {
+            Ok(Some(room_id)) if room_id == room.id => {}
+            Ok(_) => {
+                socket.emit("error", "Original message not found").ok();
+                return;
+            }
+            Err(err) => {
+                socket.emit("error", &format!("Failed to load original message: {}", err)).ok();
+                return;
+            }
+        }
+    }
+
+    info!("User {} sent message to room {}: {}", user_id, room.id, message);
 
     // Save message to database
-    let chat_result = save_chat_message(&db, user_id, message, reply_id).await;
+    let chat_result = save_chat_message(&db, user_id, room.id, message, reply_id).await;
 
     match chat_result {
         Ok(chat) => {
-            // Get user info for the message
-            let user_info = get_user_info(&db, user_id).await;
-
-            let chat_data = serde_json::json!({
-                "id": chat.id,
-                "message": chat.message,
-                "attachment": chat.attachment,
-                "userId": user_id,
-                "nickname": user_info.nickname,
-                "avatar": user_info.avatar,
-                "replyId": reply_id,
-                "createdAt": chat.created_at,
-                "user": user_info
-            });
+            let event = MessageEvent {
+                id: chat.id,
+                message: chat.message.as_deref(),
+                attachment: chat.attachment.as_deref(),
+                user_id,
+                reply_id,
+                room_id: room.id,
+                created_at: chat.created_at,
+            };
+            let chat_data = message_payload(&db, &event).await;
 
             // Broadcast to all users in the room
             socket
-                .to("general_chat")
-                .emit("new_message", chat_data)
+                .to(channel.clone())
+                .emit("new_message", &chat_data)
+                .await
                 .ok();
 
+            if let Some(reply) = reply_payload(&event) {
+                socket.to(channel).emit("message_replied", &reply).await.ok();
+            }
+
             // Acknowledge receipt
-            socket.emit("message_sent", chat_data).ok();
+            socket.emit("message_sent", &chat_data).ok();
         }
         Err(err) => {
-            socket.emit("error", format!("Failed to send message: {}", err)).ok();
+            socket.emit("error", &format!("Failed to send message: {}", err)).ok();
         }
     }
 }
@@ -67,46 +115,23 @@ pub async fn handle_chat(
 async fn save_chat_message(
     db: &Pool<Postgres>,
     user_id: i64,
+    room_id: i64,
     message: &str,
     reply_id: Option<i64>,
-) -> Result<Chat, Box<dyn std::error::Error>> {
+) -> Result<Chat, sqlx::Error> {
     let query = r#"
-        INSERT INTO chats (message, "userId", "replyId", created_at, updated_at)
-        VALUES ($1, $2, $3, NOW(), NOW())
-        RETURNING id, message, attachment, "userId", "replyId", created_at, updated_at
+        INSERT INTO chats (message, "userId", "replyId", "roomId", created_at, updated_at)
+        VALUES ($1, $2, $3, $4, NOW(), NOW())
+        RETURNING id, message, attachment, "userId", "replyId", "roomId", created_at, updated_at
     "#;
 
     let chat = sqlx::query_as::<_, Chat>(query)
         .bind(message)
         .bind(user_id)
         .bind(reply_id)
+        .bind(room_id)
         .fetch_one(db)
         .await?;
 
     Ok(chat)
 }
-
-// Helper function to get user info
-#[derive(serde::Serialize)]
-struct UserInfo {
-    id: i64,
-    nickname: Option<String>,
-    avatar: Option<String>,
-}
-
-async fn get_user_info(db: &Pool<Postgres>, user_id: i64) -> UserInfo {
-    let query = "SELECT id, nickname, avatar FROM users WHERE id = $1";
-
-    match sqlx::query_as::<_, UserInfo>(query)
-        .bind(user_id)
-        .fetch_optional(db)
-        .await
-    {
-        Ok(Some(user)) => user,
-        _ => UserInfo {
-            id: user_id,
-            nickname: Some("Unknown".to_string()),
-            avatar: None,
-        },
-    }
-}
\ No newline at end of file
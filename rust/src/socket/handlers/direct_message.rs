@@ -0,0 +1,104 @@
+use crate::controllers::direct_controller::conversation::{open_conversation, save_direct_message};
+use crate::controllers::email_controller::verification::may_post_messages;
+use crate::controllers::user_controller::get_user::get_user_by_auth_id;
+use crate::socket::handlers::UserSocketMap;
+use crate::socket::auth::socket_user;
+use crate::socket::rate_limit::allow_event;
+use socketioxide::extract::{Data, SocketRef};
+use sqlx::Pool;
+use sqlx::Postgres;
+use serde_json::Value;
+use tracing::info;
+
+pub async fn handle_direct_message(
+    socket: SocketRef,
+    Data(data): Data<Value>,
+    db: Pool<Postgres>,
+    user_sockets: UserSocketMap,
+) {
+    let user = match socket_user(&socket) {
+        Some(user) => user,
+        None => {
+            socket.emit("error", "Unauthenticated socket").ok();
+            return;
+        }
+    };
+    let user_id = user.id;
+    let message = data["message"].as_str().unwrap_or("");
+
+    let to_user_id = match data["toUserId"].as_i64() {
+        Some(to_user_id) => to_user_id,
+        None => {
+            socket.emit("error", "toUserId is required").ok();
+            return;
+        }
+    };
+
+    if message.trim().is_empty() {
+        socket.emit("error", "Message cannot be empty").ok();
+        return;
+    }
+
+    if !allow_event(&socket, "chat", "dm").await {
+        return;
+    }
+
+    if to_user_id == user_id {
+        socket.emit("error", "Cannot message yourself").ok();
+        return;
+    }
+
+    if !may_post_messages(user_id, &db).await {
+        socket.emit("error", "Verify your email address before posting messages").ok();
+        return;
+    }
+
+    // Deleted accounts keep their row but can't be written to
+    match get_user_by_auth_id(to_user_id, db.clone()).await {
+        Some(recipient) if recipient.deleted_at.is_none() => {}
+        _ => {
+            socket.emit("error", "User not found").ok();
+            return;
+        }
+    }
+
+    let conversation = match open_conversation(user_id, to_user_id, &db).await {
+        Ok(conversation) => conversation,
+        Err(err) => {
+            socket.emit("error", &format!("Failed to send message: {}", err)).ok();
+            return;
+        }
+    };
+
+    info!("User {} sent direct message to {}", user_id, to_user_id);
+
+    let direct_message = match save_direct_message(conversation.id, user_id, message, &db).await {
+        Ok(direct_message) => direct_message,
+        Err(err) => {
+            socket.emit("error", &format!("Failed to send message: {}", err)).ok();
+            return;
+        }
+    };
+
+    let dm_data = serde_json::json!({
+        "id": direct_message.id,
+        "conversationId": direct_message.conversation_id,
+        "message": direct_message.message,
+        "attachment": direct_message.attachment,
+        "userId": user_id,
+        "toUserId": to_user_id,
+        "createdAt": direct_message.created_at,
+    });
+
+    // Deliver only to the recipient's own sockets, never to a room
+    let recipients = {
+        let sockets = user_sockets.read().await;
+        sockets.get(&to_user_id.to_string()).cloned().unwrap_or_default()
+    };
+    for recipient in recipients {
+        recipient.emit("new_dm", &dm_data).ok();
+    }
+
+    // Acknowledge receipt
+    socket.emit("dm_sent", &dm_data).ok();
+}
@@ -0,0 +1,43 @@
+use crate::socket::auth::socket_user;
+use crate::socket::handlers::UserSocketMap;
+use socketioxide::extract::SocketRef;
+use tracing::info;
+use uuid::Uuid;
+
+pub async fn handle_disconnect(socket: SocketRef, user_sockets: UserSocketMap) {
+    info!("Socket.IO disconnected: {:?}", socket.id);
+
+    // Drop this socket from the user mapping; rooms are cleaned up by socketioxide
+    let mut sockets = user_sockets.write().await;
+    for connections in sockets.values_mut() {
+        connections.retain(|user_socket| user_socket.id != socket.id);
+    }
+    sockets.retain(|_, connections| !connections.is_empty());
+}
+
+// Signs out the live sockets of revoked sessions
+pub async fn disconnect_sessions(user_sockets: &UserSocketMap, user_id: i64, sessions: &[Uuid]) {
+    let revoked: Vec<SocketRef> = {
+        let mut map = user_sockets.write().await;
+        let connections = match map.get_mut(&user_id.to_string()) {
+            Some(connections) => connections,
+            None => return,
+        };
+        let (revoked, kept): (Vec<_>, Vec<_>) = connections.drain(..).partition(|socket| {
+            socket_user(socket)
+                .and_then(|user| user.session_id)
+                .is_some_and(|session_id| sessions.contains(&session_id))
+        });
+        *connections = kept;
+        if connections.is_empty() {
+            map.remove(&user_id.to_string());
+        }
+        revoked
+    };
+
+    // The lock is released first: disconnecting runs handle_disconnect, which takes it again
+    for socket in revoked {
+        socket.emit("session_revoked", &serde_json::json!({ "reason": "Session was revoked" })).ok();
+        socket.disconnect().ok();
+    }
+}
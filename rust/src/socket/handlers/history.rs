@@ -0,0 +1,50 @@
+use crate::controllers::chat_controller::history::{HistoryQuery, fetch_history};
+use crate::socket::handlers::joined_room;
+use chrono::{DateTime, Utc};
+use socketioxide::extract::{Data, SocketRef};
+use sqlx::Pool;
+use sqlx::Postgres;
+use serde_json::Value;
+
+fn time_field(data: &Value, key: &str) -> Option<DateTime<Utc>> {
+    data[key].as_str()?.parse().ok()
+}
+
+// Infinite scroll over the socket: same cursors as GET /v1/chat/history
+pub async fn handle_history(socket: SocketRef, Data(data): Data<Value>, db: Pool<Postgres>) {
+    if joined_room(&socket, &data).is_none() {
+        socket.emit("error", "Join the room before loading its history").ok();
+        return;
+    }
+
+    let room_id = data["roomId"].as_i64();
+    let params = HistoryQuery {
+        room_id,
+        before: data["before"].as_i64(),
+        after: data["after"].as_i64(),
+        before_time: time_field(&data, "beforeTime"),
+        after_time: time_field(&data, "afterTime"),
+        limit: data["limit"].as_i64(),
+    };
+
+    match fetch_history(&db, &params).await {
+        Ok(page) => {
+            socket
+                .emit(
+                    "history",
+                    &serde_json::json!({
+                        "roomId": room_id,
+                        "data": page.data,
+                        "limit": page.limit,
+                        "nextCursor": page.next_cursor,
+                        "prevCursor": page.prev_cursor
+                    }),
+                )
+                .ok();
+        }
+        Err(err) => {
+            eprintln!("Failed to fetch chat history: {}", err);
+            socket.emit("error", "Failed to load history").ok();
+        }
+    }
+}
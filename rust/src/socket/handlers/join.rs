@@ -1,45 +1,61 @@
-use crate::AppState;
-use crate::socket::handlers::UserSocketMap;
-use socketioxide::{
-    extract::{Data, SocketRef},
-    SocketIo,
-};
+use crate::controllers::room_controller::get_room::get_active_room;
+use crate::socket::auth::socket_user;
+use socketioxide::extract::{Data, SocketRef};
+use sqlx::Pool;
+use sqlx::Postgres;
 use serde_json::Value;
 use tracing::info;
 
 pub async fn handle_join(
     socket: SocketRef,
-    io: SocketIo,
     Data(data): Data<Value>,
-    user_sockets: UserSocketMap,
+    db: Pool<Postgres>,
 ) {
-    let user_id = data["userId"].as_i64().unwrap_or(0);
-    let nickname = data["nickname"].as_str().unwrap_or("Anonymous");
+    let user = match socket_user(&socket) {
+        Some(user) => user,
+        None => {
+            socket.emit("error", "Unauthenticated socket").ok();
+            return;
+        }
+    };
 
-    info!("User {} ({}) joined", nickname, user_id);
+    let room_id = match data["roomId"].as_i64() {
+        Some(room_id) => room_id,
+        None => {
+            socket.emit("error", "roomId is required").ok();
+            return;
+        }
+    };
 
-    // Store socket mapping
-    {
-        let mut sockets = user_sockets.write().await;
-        sockets.insert(user_id.to_string(), socket.clone());
-    }
+    let room = match get_active_room(room_id, &db).await {
+        Some(room) => room,
+        None => {
+            socket.emit("error", "Room not found or archived").ok();
+            return;
+        }
+    };
 
-    // Join user to a room (could be specific chat room or general)
-    socket.join("general_chat").ok();
+    info!("User {} ({}) joined room {}", user.nickname, user.id, room.id);
+
+    let channel = room.id.to_string();
+    socket.join(channel.clone());
 
     // Notify other users
     socket
-        .to("general_chat")
-        .emit("user_joined", serde_json::json!({
-            "userId": user_id,
-            "nickname": nickname,
-            "message": format!("{} joined the chat", nickname)
+        .to(channel)
+        .emit("user_joined", &serde_json::json!({
+            "userId": user.id,
+            "roomId": room.id,
+            "nickname": user.nickname,
+            "message": format!("{} joined the chat", user.nickname)
         }))
+        .await
         .ok();
 
     // Send acknowledgment
-    socket.emit("joined", serde_json::json!({
+    socket.emit("joined", &serde_json::json!({
         "status": "success",
-        "message": "Successfully joined the chat"
+        "roomId": room.id,
+        "message": format!("Successfully joined {}", room.name)
     })).ok();
-}
\ No newline at end of file
+}
@@ -1,38 +1,43 @@
-use crate::socket::handlers::UserSocketMap;
-use socketioxide::{
-    extract::{Data, SocketRef},
-    SocketIo,
-};
+use crate::socket::handlers::joined_room;
+use crate::socket::auth::socket_user;
+use socketioxide::extract::{Data, SocketRef};
 use serde_json::Value;
 use tracing::info;
 
 pub async fn handle_left(
     socket: SocketRef,
-    io: SocketIo,
     Data(data): Data<Value>,
-    user_sockets: UserSocketMap,
 ) {
-    let user_id = data["userId"].as_i64().unwrap_or(0);
-    let nickname = data["nickname"].as_str().unwrap_or("Anonymous");
+    let user = match socket_user(&socket) {
+        Some(user) => user,
+        None => {
+            socket.emit("error", "Unauthenticated socket").ok();
+            return;
+        }
+    };
 
-    info!("User {} ({}) left", nickname, user_id);
+    let channel = match joined_room(&socket, &data) {
+        Some(channel) => channel,
+        None => {
+            socket.emit("error", "Not a member of this room").ok();
+            return;
+        }
+    };
 
-    // Remove from socket mapping
-    {
-        let mut sockets = user_sockets.write().await;
-        sockets.remove(&user_id.to_string());
-    }
+    info!("User {} ({}) left room {}", user.nickname, user.id, channel);
 
-    // Leave room
-    socket.leave("general_chat").ok();
+    // Leave room; the user socket mapping is kept until disconnect
+    socket.leave(channel.clone());
 
     // Notify other users
     socket
-        .to("general_chat")
-        .emit("user_left", serde_json::json!({
-            "userId": user_id,
-            "nickname": nickname,
-            "message": format!("{} left the chat", nickname)
+        .to(channel)
+        .emit("user_left", &serde_json::json!({
+            "userId": user.id,
+            "roomId": data["roomId"],
+            "nickname": user.nickname,
+            "message": format!("{} left the chat", user.nickname)
         }))
+        .await
         .ok();
-}
\ No newline at end of file
+}
@@ -3,12 +3,38 @@ pub mod left;
 pub mod chat;
 pub mod writing;
 pub mod cancel_writing;
+pub mod disconnect;
+pub mod direct_message;
+pub mod history;
 
-pub type UserSocketMap = std::sync::Arc<tokio::sync::RwLock<std::collections::HashMap<String, socketioxide::extract::SocketRef>>>;
+use serde_json::Value;
+use socketioxide::extract::SocketRef;
+
+// A user may be connected from several tabs or devices at once
+pub type UserSocketMap = std::sync::Arc<tokio::sync::RwLock<std::collections::HashMap<String, Vec<socketioxide::extract::SocketRef>>>>;
+
+// Every authenticated socket is tracked from connect until disconnect
+pub async fn track_socket(user_sockets: &UserSocketMap, socket: &SocketRef, user_id: i64) {
+    let mut sockets = user_sockets.write().await;
+    let entry = sockets.entry(user_id.to_string()).or_default();
+    if !entry.iter().any(|user_socket| user_socket.id == socket.id) {
+        entry.push(socket.clone());
+    }
+}
+
+// Resolve the `roomId` of a payload to the Socket.IO room this socket joined.
+// Clients may only talk in rooms they entered through the `join` event.
+pub fn joined_room(socket: &SocketRef, data: &Value) -> Option<String> {
+    let room = data["roomId"].as_i64()?.to_string();
+    socket.rooms().iter().any(|r| *r == room).then_some(room)
+}
 
 // Re-export handlers for easier use
 pub use join::handle_join;
 pub use left::handle_left;
 pub use chat::handle_chat;
 pub use writing::handle_writing;
-pub use cancel_writing::handle_cancel_writing;
\ No newline at end of file
+pub use cancel_writing::handle_cancel_writing;
+pub use disconnect::{disconnect_sessions, handle_disconnect};
+pub use direct_message::handle_direct_message;
+pub use history::handle_history;
@@ -1,28 +1,39 @@
-use crate::socket::handlers::UserSocketMap;
-use socketioxide::{
-    extract::{Data, SocketRef},
-    SocketIo,
-};
+use crate::socket::handlers::joined_room;
+use crate::socket::auth::socket_user;
+use crate::socket::rate_limit::allow_event;
+use socketioxide::extract::{Data, SocketRef};
 use serde_json::Value;
 use tracing::info;
 
 pub async fn handle_writing(
     socket: SocketRef,
-    io: SocketIo,
     Data(data): Data<Value>,
 ) {
-    let user_id = data["userId"].as_i64().unwrap_or(0);
-    let nickname = data["nickname"].as_str().unwrap_or("Anonymous");
+    let user = match socket_user(&socket) {
+        Some(user) => user,
+        None => return,
+    };
 
-    info!("User {} is typing", user_id);
+    let channel = match joined_room(&socket, &data) {
+        Some(channel) => channel,
+        None => return,
+    };
+
+    if !allow_event(&socket, "writing", "writing").await {
+        return;
+    }
+
+    info!("User {} is typing in room {}", user.id, channel);
 
     // Broadcast typing indicator
     socket
-        .to("general_chat")
-        .emit("user_typing", serde_json::json!({
-            "userId": user_id,
-            "nickname": nickname,
+        .to(channel)
+        .emit("user_typing", &serde_json::json!({
+            "userId": user.id,
+            "roomId": data["roomId"],
+            "nickname": user.nickname,
             "typing": true
         }))
+        .await
         .ok();
-}
\ No newline at end of file
+}
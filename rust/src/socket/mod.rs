@@ -1,3 +1,6 @@
+pub mod auth;
+pub mod events;
 pub mod handlers;
+pub mod rate_limit;
 
 pub use handlers::*;
\ No newline at end of file
@@ -0,0 +1,28 @@
+use crate::ratelimit::{Subject, rate_limiter};
+use crate::socket::auth::socket_user;
+use socketioxide::extract::SocketRef;
+
+// Guard for event handlers: false when the sender is over the policy's limit, after
+// telling them with a `rate_limited` event. Sockets are always signed in, so buckets are per user.
+pub async fn allow_event(socket: &SocketRef, policy: &str, event: &str) -> bool {
+    let (limiter, user) = match (rate_limiter(), socket_user(socket)) {
+        (Some(limiter), Some(user)) => (limiter, user),
+        _ => return true,
+    };
+
+    match limiter.check(policy, &Subject::User(user.id)).await {
+        Some(decision) if !decision.allowed => {
+            socket
+                .emit(
+                    "rate_limited",
+                    &serde_json::json!({
+                        "event": event,
+                        "retryAfter": decision.retry_after_secs,
+                    }),
+                )
+                .ok();
+            false
+        }
+        _ => true,
+    }
+}
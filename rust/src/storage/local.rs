@@ -0,0 +1,57 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{Storage, StorageError};
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub async fn new(root: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let root = root.into();
+        tokio::fs::create_dir_all(&root)
+            .await
+            .map_err(|err| StorageError::Config(format!("{}: {}", root.display(), err)))?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        // Keys are server generated, but never let one escape the root
+        if key.is_empty() || key.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Err(StorageError::Backend(format!("invalid key: {}", key)));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+fn backend_error(err: std::io::Error) -> StorageError {
+    match err.kind() {
+        ErrorKind::NotFound => StorageError::NotFound,
+        _ => StorageError::Backend(err.to_string()),
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: &[u8], _content_type: &str) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(backend_error)?;
+        }
+        tokio::fs::write(path, bytes).await.map_err(backend_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        tokio::fs::read(self.path(key)?).await.map_err(backend_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(backend_error(err)),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+pub mod local;
+#[cfg(feature = "s3")]
+pub mod s3;
+
+use std::env;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Object not found")]
+    NotFound,
+    #[error("Storage configuration error: {0}")]
+    Config(String),
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+// Where uploaded attachment bytes live. Keys are generated by the server, never by clients.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), StorageError>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+pub type SharedStorage = Arc<dyn Storage>;
+
+// STORAGE_BACKEND selects the backend: `local` (default) or `s3` when built with the s3 feature
+pub async fn storage_from_env() -> Result<SharedStorage, StorageError> {
+    let backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+    match backend.as_str() {
+        "local" => {
+            let root = env::var("STORAGE_LOCAL_DIR").unwrap_or_else(|_| "uploads".to_string());
+            Ok(Arc::new(local::LocalStorage::new(root).await?))
+        }
+        #[cfg(feature = "s3")]
+        "s3" => Ok(Arc::new(s3::S3Storage::from_env()?)),
+        other => Err(StorageError::Config(format!("unsupported STORAGE_BACKEND: {}", other))),
+    }
+}
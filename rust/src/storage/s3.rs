@@ -0,0 +1,63 @@
+use std::env;
+
+use async_trait::async_trait;
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::{Bucket, Region};
+
+use super::{Storage, StorageError};
+
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+}
+
+impl S3Storage {
+    // S3_BUCKET and S3_REGION are required; S3_ENDPOINT points at MinIO or another
+    // S3-compatible server and switches to path-style URLs
+    pub fn from_env() -> Result<Self, StorageError> {
+        let required = |key: &str| env::var(key).map_err(|_| StorageError::Config(format!("{} is not set", key)));
+
+        let name = required("S3_BUCKET")?;
+        let region_name = required("S3_REGION")?;
+        let endpoint = env::var("S3_ENDPOINT").ok();
+        let region = match &endpoint {
+            Some(endpoint) => Region::Custom { region: region_name, endpoint: endpoint.clone() },
+            None => region_name.parse().map_err(|err| StorageError::Config(format!("{}", err)))?,
+        };
+        let credentials = Credentials::from_env_specific(Some("S3_ACCESS_KEY"), Some("S3_SECRET_KEY"), None, None)
+            .map_err(|err| StorageError::Config(err.to_string()))?;
+
+        let mut bucket = Bucket::new(&name, region, credentials).map_err(backend_error)?;
+        if endpoint.is_some() {
+            bucket = bucket.with_path_style();
+        }
+        Ok(Self { bucket })
+    }
+}
+
+fn backend_error(err: S3Error) -> StorageError {
+    match err {
+        S3Error::HttpFailWithBody(404, _) => StorageError::NotFound,
+        err => StorageError::Backend(err.to_string()),
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), StorageError> {
+        self.bucket
+            .put_object_with_content_type(key, bytes, content_type)
+            .await
+            .map(|_| ())
+            .map_err(backend_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let response = self.bucket.get_object(key).await.map_err(backend_error)?;
+        Ok(response.bytes().to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.bucket.delete_object(key).await.map(|_| ()).map_err(backend_error)
+    }
+}
@@ -1,53 +1,69 @@
-use axum::{body::Body, extract::Request, http::StatusCode};
+use axum::{
+    Router,
+    body::Body,
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    middleware::from_fn,
+    routing::get,
+};
+use chrono::Utc;
+use rust::libs::crypto::{generate_access_token, generate_refresh_token, is_refresh_token};
+use rust::libs::jwt::{Claims, TokenUse, key_set};
 use rust::middleware::auth::middleware_auth;
-use tower::ServiceBuilder;
-use tower_http::cors::CorsLayer;
+use tower::ServiceExt;
+
+const SESSION_ID: &str = "00000000-0000-4000-8000-000000000001";
+
+fn use_test_secret() {
+    std::env::set_var("SECRET", "auth-middleware-tests-secret-0123456789");
+}
+
+// Runs the request through a route guarded by middleware_auth
+async fn status_for(request: Request) -> StatusCode {
+    let app = Router::new()
+        .route("/protected", get(|| async { "ok" }))
+        .layer(from_fn(middleware_auth));
+
+    app.oneshot(request).await.unwrap().status()
+}
+
+async fn status_for_bearer(token: &str) -> StatusCode {
+    let request = Request::builder()
+        .uri("/protected")
+        .header("Authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    status_for(request).await
+}
 
 #[tokio::test]
 async fn test_auth_middleware_missing_header() {
-    let request = Request::new(Body::empty());
-    let next = tower::service::ServiceExt::ready(())
-        .await
+    let request = Request::builder()
+        .uri("/protected")
+        .body(Body::empty())
         .unwrap();
 
-    let result = middleware_auth(request, next).await;
-
-    match result {
-        Err((status, _)) => {
-            assert_eq!(status, StatusCode::UNAUTHORIZED);
-        }
-        Ok(_) => panic!("Should have failed with missing auth header"),
-    }
+    assert_eq!(status_for(request).await, StatusCode::UNAUTHORIZED);
 }
 
 #[tokio::test]
 async fn test_auth_middleware_invalid_header() {
-    use http::HeaderValue;
-
-    let mut request = Request::new(Body::empty());
-    request.headers_mut().insert(
-        "Authorization",
-        HeaderValue::from_static("InvalidHeader"),
-    );
-
-    let next = tower::service::ServiceExt::ready(())
-        .await
+    let mut request = Request::builder()
+        .uri("/protected")
+        .body(Body::empty())
         .unwrap();
+    request
+        .headers_mut()
+        .insert("Authorization", HeaderValue::from_static("InvalidHeader"));
 
-    let result = middleware_auth(request, next).await;
-
-    match result {
-        Err((status, _)) => {
-            assert_eq!(status, StatusCode::UNAUTHORIZED);
-        }
-        Ok(_) => panic!("Should have failed with invalid auth header"),
-    }
+    assert_eq!(status_for(request).await, StatusCode::UNAUTHORIZED);
 }
 
 #[tokio::test]
 async fn test_jwt_ext_trait() {
-    use rust::middleware::auth::JwtExt;
     use axum::extract::Request;
+    use rust::middleware::auth::JwtExt;
 
     let mut request = Request::new(Body::empty());
     request.extensions_mut().insert(42i64);
@@ -57,4 +73,58 @@ async fn test_jwt_ext_trait() {
     // Test with no extension
     let request_without_extension = Request::new(Body::empty());
     assert_eq!(request_without_extension.user_id(), 0);
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_auth_middleware_accepts_access_tokens() {
+    use_test_secret();
+    let access = generate_access_token(42, SESSION_ID).unwrap();
+
+    assert_eq!(status_for_bearer(&access.token).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_auth_middleware_rejects_refresh_tokens() {
+    use_test_secret();
+
+    assert_eq!(
+        status_for_bearer(&generate_refresh_token()).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_auth_middleware_rejects_tokens_of_another_type() {
+    use_test_secret();
+    let keys = key_set().unwrap();
+    let now = Utc::now().timestamp();
+
+    for token_use in [Some(TokenUse::MfaPending), None] {
+        let token = keys
+            .encode(&Claims {
+                sub: "42".to_string(),
+                iat: now,
+                nbf: Some(now),
+                exp: now + 60,
+                jti: format!("cross-use-{:?}", token_use),
+                iss: keys.validation().issuer.clone(),
+                aud: None,
+                sid: Some(SESSION_ID.to_string()),
+                token_use,
+            })
+            .unwrap();
+
+        assert_eq!(status_for_bearer(&token).await, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn test_is_refresh_token() {
+    use_test_secret();
+    let access = generate_access_token(42, SESSION_ID).unwrap();
+
+    assert!(is_refresh_token(&generate_refresh_token()));
+    assert!(!is_refresh_token(&access.token));
+    assert!(!is_refresh_token("rt_"));
+    assert!(!is_refresh_token(""));
+}
@@ -0,0 +1,38 @@
+use axum::{Router, body::Body, extract::Request, http::StatusCode, routing::post};
+use rust::middleware::rate_limit::RateLimitLayer;
+use rust::ratelimit;
+use tower::ServiceExt;
+
+async fn app() -> Router {
+    std::env::set_var("RATE_LIMIT_CHECK", "2/60");
+    ratelimit::init().await.unwrap();
+
+    Router::new().route("/check", post(|| async { "ok" }).layer(RateLimitLayer::new("check")))
+}
+
+fn request() -> Request {
+    Request::builder()
+        .method("POST")
+        .uri("/check")
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn refuses_requests_over_the_limit_with_headers() {
+    let app = app().await;
+
+    let first = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers()["ratelimit-limit"], "2");
+    assert_eq!(first.headers()["ratelimit-remaining"], "1");
+
+    let second = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(second.status(), StatusCode::OK);
+    assert_eq!(second.headers()["ratelimit-remaining"], "0");
+
+    let refused = app.oneshot(request()).await.unwrap();
+    assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(refused.headers()["retry-after"], "30");
+    assert_eq!(refused.headers()["ratelimit-remaining"], "0");
+}